@@ -9,6 +9,11 @@ use winit::{
 };
 use wgpu_simplified as ws;
 use wgpu_complex_function::complex3d_data as c3d;
+use num_complex::Complex;
+use wgpu_complex_function::{branch_cut, complex_catalog, complex_zeros, conformal_grid};
+use wgpu_complex_function::riemann_surface::{Multivalued, SheetHeight};
+use wgpu_complex_function::laurent_series::LaurentSeries;
+use wgpu_complex_function::contour_integral::{Contour, IntegrationOptions};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -28,6 +33,24 @@ fn create_vertices(c3d_data: c3d::IComplex3DOutput) -> (Vec<Vertex>, Vec<u32>) {
     (data.to_vec(), c3d_data.indices)
 }
 
+// thin horizontal quads along the segments of a line list, appended to the mesh, since the render
+// pipeline only draws triangles
+fn append_ribbons(out: &mut c3d::IComplex3DOutput, lines: &[[f32; 3]], width: f32, color: [f32; 3]) {
+    for seg in lines.chunks(2) {
+        let (a, b) = (seg[0], seg[1]);
+        let (dx, dz) = (b[0] - a[0], b[2] - a[2]);
+        let len = (dx * dx + dz * dz).sqrt().max(1e-9);
+        let (nx, nz) = (-0.5 * width * dz / len, 0.5 * width * dx / len);
+        let base = out.positions.len() as u32;
+        out.positions.push([a[0] + nx, a[1], a[2] + nz]);
+        out.positions.push([a[0] - nx, a[1], a[2] - nz]);
+        out.positions.push([b[0] - nx, b[1], b[2] - nz]);
+        out.positions.push([b[0] + nx, b[1], b[2] + nz]);
+        out.colors.extend([color; 4]);
+        out.indices.extend([base, base + 1, base + 2, base + 2, base + 3, base]);
+    }
+}
+
 struct State {
     init: ws::IWgpuInit,
     pipeline: wgpu::RenderPipeline,
@@ -39,17 +62,32 @@ struct State {
     project_mat: Matrix4<f32>,
     msaa_texture_view: wgpu::TextureView,
     depth_texture_view: wgpu::TextureView,
+    vertices_len: usize,
     indices_len: u32,
     recreate_buffers: bool,
     animation_speed: f32,
     rotation_speed: f32,
 
     surface: c3d::IComplex3D,
+    riemann_sphere: bool,
+    riemann_surface: Option<Multivalued>,
+    conformal_grid: bool,
+    polya: u32, // 0 off, 1 arrows, 2 streamlines
+    contours: bool,
+    series_order: u32, // truncated Taylor series shown with the surface, 0 for none
+    integration_contour: bool,
+    zeros_poles: bool,
     fps_counter: ws::FpsCounter,
 }
 
 impl State {
-    async fn new(window: &Window, sample_count: u32, resolution: u32, colormap_name: &str) -> Self {
+    async fn new(
+        window: &Window,
+        sample_count: u32,
+        resolution: (u32, u32),
+        colormap_name: &str,
+        func_expr: Option<&str>,
+    ) -> Self {
         let init = ws::IWgpuInit::new(&window, sample_count, None).await;
 
         let shader = init
@@ -108,10 +146,15 @@ impl State {
         let msaa_texture_view = ws::create_msaa_texture_view(&init);
         let depth_texture_view = ws::create_depth_view(&init);
 
-        let mut cc = c3d::IComplex3D::new();
-        cc.x_resolution = resolution;
-        cc.z_resolution = resolution;
+        let mut cc: c3d::IComplex3D = c3d::IComplex3D::new();
+        (cc.x_resolution, cc.z_resolution) = resolution;
         cc.colormap_name = colormap_name.to_string();
+        if let Some(expr) = func_expr {
+            if let Err(e) = cc.set_func_expr(expr) {
+                eprintln!("invalid expression '{}': {}", expr, e);
+                std::process::exit(1);
+            }
+        }
         let data = create_vertices(cc.create_complex_data());
 
         let vertex_buffer = init
@@ -141,11 +184,20 @@ impl State {
             project_mat,
             msaa_texture_view,
             depth_texture_view,
+            vertices_len: data.0.len(),
             indices_len: data.1.len() as u32,
             recreate_buffers: false,
             animation_speed: 1.0,
             rotation_speed: 1.0,
             surface: cc,
+            riemann_sphere: false,
+            riemann_surface: None,
+            conformal_grid: false,
+            polya: 0,
+            contours: false,
+            series_order: 0,
+            integration_contour: false,
+            zeros_poles: false,
             fps_counter: ws::FpsCounter::default(),
         }
     }
@@ -168,6 +220,99 @@ impl State {
         }
     }
 
+    fn surface_data(&mut self) -> c3d::IComplex3DOutput {
+        if self.riemann_sphere {
+            // x_resolution parallels by z_resolution meridians
+            let (u, v) = (self.surface.x_resolution, self.surface.z_resolution);
+            self.surface.create_riemann_sphere_data(u, v, c3d::SphereColoring::Hsv, 0.5)
+        } else if let Some(branch) = self.riemann_surface {
+            let height = if let Multivalued::Log(_) = branch { SheetHeight::Arg } else { SheetHeight::Im };
+            self.surface.create_riemann_surface_data(branch, height)
+        } else if self.polya == 1 {
+            let (positions, colors, indices) = self.surface.create_polya_arrows(&Default::default());
+            c3d::IComplex3DOutput { positions, colors, indices, ..Default::default() }
+        } else if self.polya == 2 {
+            let (positions, _, indices) = self.surface.create_polya_streamlines(&Default::default());
+            let lines: Vec<[f32; 3]> = indices.iter().map(|&i| positions[i as usize]).collect();
+            let mut out = c3d::IComplex3DOutput::default();
+            append_ribbons(&mut out, &lines, 0.006, [1.0, 1.0, 1.0]);
+            out
+        } else if self.conformal_grid {
+            let mut out = c3d::IComplex3DOutput::default();
+            for (kind, color) in [(true, [1.0, 0.3, 0.2]), (false, [0.2, 0.5, 1.0])] {
+                let curves: Vec<_> = self.surface.create_conformal_grid(&Default::default()).into_iter()
+                    .filter(|c| matches!(c.kind, conformal_grid::GridCurveKind::Re(_)) == kind).collect();
+                append_ribbons(&mut out, &conformal_grid::grid_line_list(&curves), 0.01, color);
+            }
+            out
+        } else {
+            let mut out = self.surface.create_complex_data();
+            if !out.cut_lines.is_empty() {
+                let cuts = mem::take(&mut out.cut_lines);
+                append_ribbons(&mut out, &cuts, 0.012, [1.0, 1.0, 1.0]);
+            }
+            if self.contours {
+                for q in [c3d::ContourQuantity::Modulus, c3d::ContourQuantity::Arg] {
+                    let lines = self.surface.create_contour_lines(q, q.default_spacing());
+                    let lifted: Vec<[f32; 3]> = c3d::contour_line_list(&lines).iter()
+                        .map(|p| [p[0], p[1] + 0.005, p[2]]).collect();
+                    append_ribbons(&mut out, &lifted, 0.008, [0.0, 0.0, 0.0]);
+                }
+            }
+            if self.series_order > 0 {
+                // in gray at the heights of f, so that the two surfaces part where the series stops converging
+                let mut plot: c3d::IComplex3D = c3d::IComplex3D::new();
+                plot.set_domain(self.surface.domain()).unwrap(); // already checked on self.surface
+                (plot.x_resolution, plot.z_resolution) = (self.surface.x_resolution, self.surface.z_resolution);
+                (plot.scale, plot.aspect_ratio, plot.t) = (self.surface.scale, self.surface.aspect_ratio, self.surface.t);
+                plot.height_quantity = self.surface.height_quantity;
+                plot.height_strategy = c3d::RangeStrategy::Fixed(out.height_range[0], out.height_range[1]);
+                plot.func_expr = Some(self.taylor_series().truncated(0, self.series_order as i32).to_expr());
+                let approx = plot.create_complex_data();
+                let offset = out.positions.len() as u32;
+                out.colors.extend(approx.positions.iter().map(|_| [0.6, 0.6, 0.6]));
+                out.positions.extend(approx.positions);
+                out.indices.extend(approx.indices.iter().map(|i| i + offset));
+            }
+            if self.integration_contour {
+                let path = self.surface.create_contour_path(&self.integration_contour(), out.height_range, 256);
+                let lifted: Vec<[f32; 3]> = path.iter().map(|p| [p[0], p[1] + 0.01, p[2]]).collect();
+                append_ribbons(&mut out, &lifted, 0.015, [1.0, 1.0, 1.0]);
+            }
+            if self.zeros_poles {
+                // crosses below the zeros (white) and above the poles (red), larger for higher orders
+                let zp = self.surface.zeros_poles(&complex_zeros::ZeroPoleOptions::default());
+                for (p, m) in zp.iter().zip(self.surface.zero_pole_markers(&zp)) {
+                    let r = 0.04 * (1.0 + 0.5 * (p.order - 1) as f32);
+                    let color = if p.kind == complex_zeros::ZeroPoleKind::Zero { [1.0, 1.0, 1.0] } else { [1.0, 0.2, 0.2] };
+                    let cross = [[m[0] - r, m[1], m[2]], [m[0] + r, m[1], m[2]], [m[0], m[1], m[2] - r], [m[0], m[1], m[2] + r]];
+                    append_ribbons(&mut out, &cross, 0.015, color);
+                }
+            }
+            out
+        }
+    }
+
+    // Taylor series around the center of the domain, sampled on half the radius of convergence but
+    // inside the domain
+    fn taylor_series(&self) -> LaurentSeries {
+        let d = self.surface.domain();
+        let center = Complex::new(0.5 * (d[0] + d[1]) as f64, 0.5 * (d[2] + d[3]) as f64);
+        let max_radius = 0.5 * (d[1] - d[0]).min(d[3] - d[2]) as f64;
+        self.surface.taylor_series_within(center, max_radius, 128)
+    }
+
+    // circle around the center of the domain, half its width across
+    fn integration_circle(&self) -> (Complex<f64>, f64) {
+        let d = self.surface.domain();
+        (Complex::new(0.5 * (d[0] + d[1]) as f64, 0.5 * (d[2] + d[3]) as f64), 0.25 * (d[1] - d[0]) as f64)
+    }
+
+    fn integration_contour(&self) -> Contour {
+        let (center, radius) = self.integration_circle();
+        Contour::circle(center, radius)
+    }
+
     #[allow(unused_variables)]
     fn input(&mut self, event: &WindowEvent) -> bool {
         match event {
@@ -181,15 +326,163 @@ impl State {
                 ..
             } => match keycode {
                 VirtualKeyCode::Space => {
-                    self.surface.func_selection = (self.surface.func_selection + 1) % 11;
-                    println!("function = {}", self.surface.func_selection);
+                    let functions = complex_catalog::complex_functions();
+                    self.surface.func_selection =
+                        (self.surface.func_selection + 1) % functions.len() as u32;
+                    self.surface.func_expr = None;
+                    self.surface.use_default_domain();
+                    println!(
+                        "function = {} ({})",
+                        self.surface.func_selection,
+                        functions[self.surface.func_selection as usize].name
+                    );
+                    true
+                }
+                VirtualKeyCode::R => {
+                    let hr = complex_catalog::get_complex_function(self.surface.func_selection)
+                        .map_or([0.0, 10.0], |f| f.height_range);
+                    self.surface.height_strategy = match self.surface.height_strategy {
+                        c3d::RangeStrategy::Raw => c3d::RangeStrategy::Percentile(1.0, 99.0),
+                        c3d::RangeStrategy::Percentile(..) => c3d::RangeStrategy::Mad(3.0),
+                        c3d::RangeStrategy::Mad(_) => c3d::RangeStrategy::Fixed(hr[0] as f64, hr[1] as f64),
+                        c3d::RangeStrategy::Fixed(..) => c3d::RangeStrategy::Raw,
+                    };
+                    println!("height range = {:?}", self.surface.height_strategy);
+                    true
+                }
+                VirtualKeyCode::H => {
+                    let k = (self.surface.height_quantity.id() as usize + 1) % c3d::ComplexQuantity::ALL.len();
+                    self.surface.height_quantity = c3d::ComplexQuantity::ALL[k];
+                    println!("height = {}", self.surface.height_quantity.name());
+                    true
+                }
+                VirtualKeyCode::C => {
+                    let k = (self.surface.color_quantity.id() as usize + 1) % c3d::ComplexQuantity::ALL.len();
+                    self.surface.color_quantity = c3d::ComplexQuantity::ALL[k];
+                    println!("color = {}", self.surface.color_quantity.name());
+                    true
+                }
+                VirtualKeyCode::Left | VirtualKeyCode::Right | VirtualKeyCode::Up | VirtualKeyCode::Down => {
+                    let (fx, fz) = match keycode {
+                        VirtualKeyCode::Left => (-0.1, 0.0),
+                        VirtualKeyCode::Right => (0.1, 0.0),
+                        VirtualKeyCode::Up => (0.0, 0.1),
+                        _ => (0.0, -0.1),
+                    };
+                    if let Err(e) = self.surface.pan(fx, fz) {
+                        println!("{}", e);
+                    }
+                    println!("domain = {:?}", self.surface.domain());
+                    true
+                }
+                VirtualKeyCode::PageUp | VirtualKeyCode::PageDown => {
+                    if let Err(e) = self.surface.zoom(if *keycode == VirtualKeyCode::PageUp { 0.8 } else { 1.25 }) {
+                        println!("{}", e);
+                    }
+                    println!("domain = {:?}", self.surface.domain());
+                    true
+                }
+                VirtualKeyCode::Home => {
+                    self.surface.use_default_domain();
+                    println!("domain = {:?}", self.surface.domain());
+                    true
+                }
+                VirtualKeyCode::P => {
+                    self.riemann_sphere = !self.riemann_sphere;
+                    self.recreate_buffers = true;
+                    println!("riemann sphere = {}", self.riemann_sphere);
+                    true
+                }
+                VirtualKeyCode::B => {
+                    self.surface.branch_cuts = match self.surface.branch_cuts {
+                        Some(_) => None,
+                        None => Some(branch_cut::BranchCutOptions { emit_cut_lines: true, ..Default::default() }),
+                    };
+                    self.recreate_buffers = true;
+                    println!("split at branch cuts = {}", self.surface.branch_cuts.is_some());
+                    true
+                }
+                VirtualKeyCode::M => {
+                    // sqrt, cube root and log of the current function, then back to the function itself
+                    self.riemann_surface = match self.riemann_surface {
+                        None => Some(Multivalued::Root(2)),
+                        Some(Multivalued::Root(2)) => Some(Multivalued::Root(3)),
+                        Some(Multivalued::Root(_)) => Some(Multivalued::Log(3)),
+                        Some(Multivalued::Log(_)) => None,
+                    };
+                    self.recreate_buffers = true;
+                    println!("riemann surface = {:?}", self.riemann_surface);
+                    true
+                }
+                VirtualKeyCode::L => {
+                    self.contours = !self.contours;
+                    self.recreate_buffers = true;
+                    println!("modulus and phase contours = {}", self.contours);
+                    true
+                }
+                VirtualKeyCode::V => {
+                    self.polya = (self.polya + 1) % 3;
+                    self.recreate_buffers = true;
+                    println!("polya field = {}", ["off", "arrows", "streamlines"][self.polya as usize]);
+                    true
+                }
+                VirtualKeyCode::G => {
+                    self.conformal_grid = !self.conformal_grid;
+                    self.recreate_buffers = true;
+                    println!("conformal grid = {}", self.conformal_grid);
+                    true
+                }
+                VirtualKeyCode::Y => {
+                    self.series_order = match self.series_order {
+                        0 => 4,
+                        32 => 0,
+                        n => 2 * n,
+                    };
+                    self.recreate_buffers = true;
+                    println!("taylor series order = {}, radius of convergence ~ {:.4}", self.series_order,
+                        self.taylor_series().convergence_radius());
+                    true
+                }
+                VirtualKeyCode::I => {
+                    self.integration_contour = !self.integration_contour;
+                    self.recreate_buffers = true;
+                    if self.integration_contour {
+                        let opts = IntegrationOptions::default();
+                        let contour = self.integration_contour();
+                        let integral = self.surface.contour_integral(&contour, &opts);
+                        println!("contour integral = {:.8} +/- {:.2e} ({} evaluations, converged = {})",
+                            integral.value, integral.error, integral.evaluations, integral.converged);
+
+                        // residues at the poles inside, on circles well clear of each other
+                        let zp = self.surface.zeros_poles(&complex_zeros::ZeroPoleOptions::default());
+                        let (c, r) = self.integration_circle();
+                        let poles: Vec<Complex<f64>> = zp.iter()
+                            .filter(|p| p.kind == complex_zeros::ZeroPoleKind::Pole && (p.location - c).norm() < r)
+                            .map(|p| p.location).collect();
+                        let radius = poles.iter().flat_map(|a| poles.iter().map(move |b| (a - b).norm()))
+                            .filter(|&s| s > 0.0).fold(0.01 * r, f64::min);
+                        for (p, res) in poles.iter().zip(self.surface.residues(&poles, 0.4 * radius, &opts)) {
+                            println!("residue at {:.6} = {:.8}", p, res.value);
+                        }
+                    }
+                    true
+                }
+                VirtualKeyCode::Z => {
+                    self.zeros_poles = !self.zeros_poles;
+                    self.recreate_buffers = true;
+                    println!("zero and pole markers = {}", self.zeros_poles);
+                    if self.zeros_poles {
+                        for p in self.surface.zeros_poles(&complex_zeros::ZeroPoleOptions::default()) {
+                            println!("{:?} of order {} at {}", p.kind, p.order, p.location);
+                        }
+                    }
                     true
                 }
                 VirtualKeyCode::Q => {
                     self.surface.x_resolution += 1;
                     self.surface.z_resolution += 1;
                     self.recreate_buffers = true;
-                    println!("resolution {}", self.surface.x_resolution);
+                    println!("resolution {}x{}", self.surface.x_resolution, self.surface.z_resolution);
                     true
                 }
                 VirtualKeyCode::A => {
@@ -202,7 +495,7 @@ impl State {
                         self.surface.z_resolution = 8;
                     }
                     self.recreate_buffers = true;
-                    println!("resolution {}", self.surface.x_resolution);
+                    println!("resolution {}x{}", self.surface.x_resolution, self.surface.z_resolution);
                     true
                 }
                 VirtualKeyCode::W => {
@@ -249,9 +542,11 @@ impl State {
             bytemuck::cast_slice(mvp_mat.as_ref() as &[f32; 16]),
         );
 
-        // recreate vertex and index buffers
-        if self.recreate_buffers {
-            let data = create_vertices(self.surface.create_complex_data());
+        // update vertex and index buffers for every frame; splitting at branch cuts changes the mesh with t
+        self.surface.t = 0.5 * (1.0 + (self.animation_speed * dt.as_secs_f32()).cos());
+        let data = create_vertices(self.surface_data());
+        if self.recreate_buffers || data.0.len() != self.vertices_len || data.1.len() != self.indices_len as usize {
+            self.vertices_len = data.0.len();
             self.indices_len = data.1.len() as u32;
 
             self.vertex_buffer.destroy();
@@ -274,14 +569,14 @@ impl State {
                     });
 
             self.recreate_buffers = false;
+        } else {
+            self.init
+                .queue
+                .write_buffer(&self.vertex_buffer, 0, cast_slice(&data.0));
+            self.init
+                .queue
+                .write_buffer(&self.index_buffer, 0, cast_slice(&data.1));
         }
-
-        // update vertex buffer for every frame
-        self.surface.t = 0.5 * (1.0 + (self.animation_speed * dt.as_secs_f32()).cos());
-        let data = create_vertices(self.surface.create_complex_data());
-        self.init
-            .queue
-            .write_buffer(&self.vertex_buffer, 0, cast_slice(&data.0));
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -327,21 +622,36 @@ impl State {
     }
 }
 
+// "256" or "512x128" (x by z)
+fn parse_resolution(s: &str) -> (u32, u32) {
+    match s.split_once('x') {
+        Some((x, z)) => (x.parse::<u32>().unwrap(), z.parse::<u32>().unwrap()),
+        None => {
+            let n = s.parse::<u32>().unwrap();
+            (n, n)
+        }
+    }
+}
+
 fn main() {
     let mut sample_count = 1 as u32;
-    let mut resolution = 256 as u32;
+    let mut resolution = (256u32, 256u32);
     let mut colormap_name = "jet";
+    let mut func_expr: Option<&str> = None;
 
     let args: Vec<String> = std::env::args().collect();
     if args.len() > 1 {
         sample_count = args[1].parse::<u32>().unwrap();
     }
     if args.len() > 2 {
-        resolution = args[2].parse::<u32>().unwrap();
+        resolution = parse_resolution(&args[2]);
     }
     if args.len() > 3 {
         colormap_name = &args[3];
     }
+    if args.len() > 4 {
+        func_expr = Some(&args[4]);
+    }
 
     env_logger::init();
     let event_loop = EventLoop::new();
@@ -350,8 +660,13 @@ fn main() {
         .unwrap();
     window.set_title(&*format!("{}", "complex3d_cpu"));
 
-    let mut state =
-        pollster::block_on(State::new(&window, sample_count, resolution, colormap_name));
+    let mut state = pollster::block_on(State::new(
+        &window,
+        sample_count,
+        resolution,
+        colormap_name,
+        func_expr,
+    ));
     let render_start_time = std::time::Instant::now();
 
     event_loop.run(move |event, _, control_flow| match event {
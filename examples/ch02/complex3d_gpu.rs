@@ -8,10 +8,7 @@ use winit::{
     window::Window,
 };
 use wgpu_simplified as ws;
-use wgpu_complex_function::colormap;
-
-#[include_wgsl_oil::include_wgsl_oil("complex3d_comp.wgsl")]
-mod cs_shader_mod {}
+use wgpu_complex_function::{colormap, complex3d_data as c3d, complex_catalog, complex_expr::Expr, complex_wgsl};
 
 fn create_color_data(colormap_name: &str) -> Vec<[f32; 4]> {
     let cdata = colormap::colormap_data(colormap_name);
@@ -22,6 +19,39 @@ fn create_color_data(colormap_name: &str) -> Vec<[f32; 4]> {
     data
 }
 
+fn create_cfunc(func_expr: Option<&Expr>, selection: u32) -> String {
+    match func_expr {
+        Some(e) => complex_wgsl::wgsl_cfunc(e),
+        None => complex_catalog::complex_functions()[selection as usize].wgsl_cfunc(),
+    }
+}
+
+// CPU surface of an expression on a coarse grid, sampled only for the height and color ranges
+fn create_expr_surface(expr: &str) -> c3d::IComplex3D {
+    c3d::IComplex3D::builder().expr(expr).resolution(64, 64).build().unwrap_or_else(|e| {
+        eprintln!("invalid expression '{}': {}", expr, e);
+        std::process::exit(1);
+    })
+}
+
+fn create_compute_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    cfunc: &str,
+) -> wgpu::ComputePipeline {
+    let source = complex_wgsl::compose_shader(include_str!("complex3d_comp.wgsl"), Some(cfunc));
+    let cs_comp = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Compute Shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::from(source)),
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Compute Pipeline"),
+        layout: Some(layout),
+        module: &cs_comp,
+        entry_point: "cs_main",
+    })
+}
+
 struct State {
     init: ws::IWgpuInit,
     pipeline: wgpu::RenderPipeline,
@@ -29,6 +59,7 @@ struct State {
     uniform_buffer: wgpu::Buffer,
 
     cs_pipelines: Vec<wgpu::ComputePipeline>,
+    cs_pipeline_layout: wgpu::PipelineLayout,
     cs_vertex_buffer: wgpu::Buffer,
     cs_index_buffer: wgpu::Buffer,
     cs_uniform_buffers: Vec<wgpu::Buffer>,
@@ -41,29 +72,35 @@ struct State {
     animation_speed: f32,
     rotation_speed: f32,
 
-    resolution: u32,
+    resolution: [u32; 2], // vertices along x and z
+    domain: Option<[f32; 4]>, // None uses the default domain of the function or expression
     triangles_count: u32,
     surface_type: u32,
+    expr_surface: Option<c3d::IComplex3D>, // overrides surface_type when set
     scale: f32,
     aspect_ratio: f32,
+    height_quantity: c3d::ComplexQuantity,
+    color_quantity: c3d::ComplexQuantity,
     fps_counter: ws::FpsCounter,
 }
 
 impl State {
-    async fn new(window: &Window, sample_count: u32, resolution: u32, colormap_name: &str) -> Self {
+    async fn new(
+        window: &Window,
+        sample_count: u32,
+        resolution: (u32, u32),
+        colormap_name: &str,
+        func_expr: Option<&str>,
+    ) -> Self {
         let init = ws::IWgpuInit::new(&window, sample_count, None).await;
 
-        let resol = ws::round_to_multiple(resolution, 8);
-        let vertices_count = resol * resol;
-        let triangles_count = 6 * (resol - 1) * (resol - 1);
-        println!("resolution = {}", resol);
+        let resol = [ws::round_to_multiple(resolution.0, 8), ws::round_to_multiple(resolution.1, 8)];
+        let vertices_count = resol[0] * resol[1];
+        let triangles_count = 6 * (resol[0] - 1) * (resol[1] - 1);
+        println!("resolution = {}x{}", resol[0], resol[1]);
 
         let shader = init.device.create_shader_module(wgpu::include_wgsl!("../ch01/shader_unlit.wgsl"));
         let cs_indices = init.device.create_shader_module(wgpu::include_wgsl!("indices_comp.wgsl"));
-        let cs_comp = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Compute Shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::from(cs_shader_mod::SOURCE)),
-        });
 
         // uniform data
         let camera_position = (2.0, 2.0, 2.0).into();
@@ -129,12 +166,12 @@ impl State {
 
         let cs_index_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Index Uniform Buffer"),
-            size: 4,
+            size: 8,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
         init.queue
-            .write_buffer(&cs_index_uniform_buffer, 0, cast_slice(&[resol]));
+            .write_buffer(&cs_index_uniform_buffer, 0, cast_slice(&resol));
 
         let (cs_index_bind_group_layout, cs_index_bind_group) = ws::create_bind_group_storage(
             &init.device,
@@ -187,7 +224,7 @@ impl State {
 
         let cs_vertex_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Vertex Uniform Buffer"),
-            size: 32,
+            size: 64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -219,14 +256,12 @@ impl State {
                     push_constant_ranges: &[],
                 });
 
-        let cs_pipeline = init
-            .device
-            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                label: Some("Compute Pipeline"),
-                layout: Some(&cs_pipeline_layout),
-                module: &cs_comp,
-                entry_point: "cs_main",
-            });
+        let expr_surface = func_expr.map(create_expr_surface);
+        let cs_pipeline = create_compute_pipeline(
+            &init.device,
+            &cs_pipeline_layout,
+            &create_cfunc(expr_surface.as_ref().and_then(|s| s.func_expr.as_ref()), 0),
+        );
 
         Self {
             init,
@@ -235,6 +270,7 @@ impl State {
             uniform_buffer: vert_uniform_buffer,
 
             cs_pipelines: vec![cs_index_pipeline, cs_pipeline],
+            cs_pipeline_layout,
             cs_vertex_buffer,
             cs_index_buffer,
             cs_uniform_buffers: vec![cs_index_uniform_buffer, cs_vertex_uniform_buffer],
@@ -248,10 +284,14 @@ impl State {
             rotation_speed: 1.0,
 
             resolution: resol,
+            domain: None,
             triangles_count,
             surface_type: 0,
+            expr_surface,
             scale: 1.5,
             aspect_ratio: 0.8,
+            height_quantity: c3d::ComplexQuantity::Modulus,
+            color_quantity: c3d::ComplexQuantity::Arg,
             fps_counter: ws::FpsCounter::default(),
         }
     }
@@ -274,6 +314,14 @@ impl State {
         }
     }
 
+    // the same domain as IComplex3D::default_domain, so a formula covers the same region on CPU and GPU
+    fn default_domain(&self) -> [f32; 4] {
+        match &self.expr_surface {
+            Some(s) => s.default_domain(),
+            None => complex_catalog::complex_functions()[self.surface_type as usize].domain,
+        }
+    }
+
     #[allow(unused_variables)]
     fn input(&mut self, event: &WindowEvent) -> bool {
         match event {
@@ -287,8 +335,55 @@ impl State {
                 ..
             } => match keycode {
                 VirtualKeyCode::Space => {
-                    self.surface_type = (self.surface_type + 1) % 11;
-                    println!("function = {}", self.surface_type);
+                    let functions = complex_catalog::complex_functions();
+                    self.surface_type = (self.surface_type + 1) % functions.len() as u32;
+                    self.expr_surface = None;
+                    self.domain = None;
+                    self.cs_pipelines[1] = create_compute_pipeline(
+                        &self.init.device,
+                        &self.cs_pipeline_layout,
+                        &create_cfunc(None, self.surface_type),
+                    );
+                    println!(
+                        "function = {} ({})",
+                        self.surface_type, functions[self.surface_type as usize].name
+                    );
+                    true
+                }
+                VirtualKeyCode::H => {
+                    let k = (self.height_quantity.id() as usize + 1) % c3d::ComplexQuantity::ALL.len();
+                    self.height_quantity = c3d::ComplexQuantity::ALL[k];
+                    println!("height = {}", self.height_quantity.name());
+                    true
+                }
+                VirtualKeyCode::C => {
+                    let k = (self.color_quantity.id() as usize + 1) % c3d::ComplexQuantity::ALL.len();
+                    self.color_quantity = c3d::ComplexQuantity::ALL[k];
+                    println!("color = {}", self.color_quantity.name());
+                    true
+                }
+                VirtualKeyCode::Left | VirtualKeyCode::Right | VirtualKeyCode::Up | VirtualKeyCode::Down => {
+                    let (fx, fz) = match keycode {
+                        VirtualKeyCode::Left => (-0.1, 0.0),
+                        VirtualKeyCode::Right => (0.1, 0.0),
+                        VirtualKeyCode::Up => (0.0, 0.1),
+                        _ => (0.0, -0.1),
+                    };
+                    let domain = self.domain.unwrap_or(self.default_domain());
+                    self.domain = Some(c3d::pan_domain(domain, fx, fz));
+                    println!("domain = {:?}", self.domain.unwrap());
+                    true
+                }
+                VirtualKeyCode::PageUp | VirtualKeyCode::PageDown => {
+                    let factor = if *keycode == VirtualKeyCode::PageUp { 0.8 } else { 1.25 };
+                    let domain = self.domain.unwrap_or(self.default_domain());
+                    self.domain = Some(c3d::zoom_domain(domain, factor));
+                    println!("domain = {:?}", self.domain.unwrap());
+                    true
+                }
+                VirtualKeyCode::Home => {
+                    self.domain = None;
+                    println!("domain = {:?}", self.default_domain());
                     true
                 }
                 VirtualKeyCode::Q => {
@@ -336,12 +431,45 @@ impl State {
         );
 
         // update uniform buffer for compute pipeline
+        let t = 0.5 * (1.0 + (self.animation_speed * 2.0 * dt.as_secs_f32()).cos());
+        let domain = self.domain.unwrap_or(self.default_domain());
+        let (y_range, c_range) = match &mut self.expr_surface {
+            // raw ranges over the current domain and t, as on the CPU surface of the expression
+            Some(s) => {
+                // a domain zoomed to nothing keeps the ranges of the default one
+                match self.domain {
+                    Some(d) => s.set_domain(d).unwrap_or_else(|_| s.use_default_domain()),
+                    None => s.use_default_domain(),
+                }
+                s.t = t;
+                s.height_quantity = self.height_quantity;
+                s.color_quantity = self.color_quantity;
+                let (yr, cr) = s.ranges();
+                ([yr[0] as f32, yr[1] as f32], [cr[0] as f32, cr[1] as f32])
+            }
+            None => {
+                let f = &complex_catalog::complex_functions()[self.surface_type as usize];
+                (self.height_quantity.gpu_range(f.height_range, f.phase_range),
+                    self.color_quantity.gpu_range(f.height_range, f.phase_range))
+            }
+        };
         let params = [
-            self.resolution as f32,
-            self.surface_type as f32,
-            0.5 * (1.0 + (self.animation_speed * 2.0 * dt.as_secs_f32()).cos()),
+            domain[0],
+            domain[1],
+            domain[2],
+            domain[3],
+            y_range[0],
+            y_range[1],
+            c_range[0],
+            c_range[1],
+            self.resolution[0] as f32,
+            self.resolution[1] as f32,
+            t,
             self.scale,
             self.aspect_ratio,
+            self.height_quantity.id() as f32,
+            self.color_quantity.id() as f32,
+            0.0, // padding
         ];
         self.init
             .queue
@@ -368,7 +496,7 @@ impl State {
             });
             cs_index_pass.set_pipeline(&self.cs_pipelines[0]);
             cs_index_pass.set_bind_group(0, &self.cs_bind_groups[0], &[]);
-            cs_index_pass.dispatch_workgroups(self.resolution / 8, self.resolution / 8, 1);
+            cs_index_pass.dispatch_workgroups(self.resolution[0] / 8, self.resolution[1] / 8, 1);
         }
 
         // compute pass for vertices
@@ -378,7 +506,7 @@ impl State {
             });
             cs_pass.set_pipeline(&self.cs_pipelines[1]);
             cs_pass.set_bind_group(0, &self.cs_bind_groups[1], &[]);
-            cs_pass.dispatch_workgroups(self.resolution / 8, self.resolution / 8, 1);
+            cs_pass.dispatch_workgroups(self.resolution[0] / 8, self.resolution[1] / 8, 1);
         }
 
         // render pass
@@ -412,21 +540,36 @@ impl State {
     }
 }
 
+// "1024" or "1024x256" (x by z)
+fn parse_resolution(s: &str) -> (u32, u32) {
+    match s.split_once('x') {
+        Some((x, z)) => (x.parse::<u32>().unwrap(), z.parse::<u32>().unwrap()),
+        None => {
+            let n = s.parse::<u32>().unwrap();
+            (n, n)
+        }
+    }
+}
+
 fn main() {
     let mut sample_count = 1 as u32;
-    let mut resolution = 1024u32;
+    let mut resolution = (1024u32, 1024u32);
     let mut colormap_name = "jet";
+    let mut func_expr: Option<&str> = None;
 
     let args: Vec<String> = std::env::args().collect();
     if args.len() > 1 {
         sample_count = args[1].parse::<u32>().unwrap();
     }
     if args.len() > 2 {
-        resolution = args[2].parse::<u32>().unwrap();
+        resolution = parse_resolution(&args[2]);
     }
     if args.len() > 3 {
         colormap_name = &args[3];
     }
+    if args.len() > 4 {
+        func_expr = Some(&args[4]);
+    }
 
     env_logger::init();
     let event_loop = EventLoop::new();
@@ -435,8 +578,13 @@ fn main() {
         .unwrap();
     window.set_title(&*format!("ch10_{}", "complex3d_gpu"));
 
-    let mut state =
-        pollster::block_on(State::new(&window, sample_count, resolution, colormap_name));
+    let mut state = pollster::block_on(State::new(
+        &window,
+        sample_count,
+        resolution,
+        colormap_name,
+        func_expr,
+    ));
     let render_start_time = std::time::Instant::now();
 
     event_loop.run(move |event, _, control_flow| match event {
@@ -1,22 +1,24 @@
 @group(0) @binding(0) var<storage, read_write> indices: array<u32>;
-@group(0) @binding(1) var<uniform> resolution: u32;
+@group(0) @binding(1) var<uniform> resolution: vec2u; // vertices along x and z
 
 @compute @workgroup_size(8, 8, 1)
 fn cs_main(@builtin(global_invocation_id) id : vec3u) {
     var i = id.x;
     var j = id.y;
+    let nx = resolution.x;
+    let nz = resolution.y;
 
-    if(i >= resolution - 1u || j >= resolution - 1u ) { return; } 
+    if(i >= nx - 1u || j >= nz - 1u ) { return; } 
 
-    let idx = (i + j * (resolution - 1u)) * 6u;
+    let idx = (i + j * (nx - 1u)) * 6u;
 
     // first triangle
-    indices[idx] = i + j * resolution;
-    indices[idx + 1u] = i + (j + 1u) * resolution;
-    indices[idx + 2u] = i + 1u + j * resolution;
+    indices[idx] = i + j * nx;
+    indices[idx + 1u] = i + (j + 1u) * nx;
+    indices[idx + 2u] = i + 1u + j * nx;
 
     // second triangle
-    indices[idx + 3u] = i + 1u + j * resolution;
-    indices[idx + 4u] = i + (j + 1u) * resolution;
-    indices[idx + 5u] = i + 1u + (j  + 1u) * resolution;
+    indices[idx + 3u] = i + 1u + j * nx;
+    indices[idx + 4u] = i + (j + 1u) * nx;
+    indices[idx + 5u] = i + 1u + (j  + 1u) * nx;
 }
\ No newline at end of file
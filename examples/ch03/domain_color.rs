@@ -8,10 +8,10 @@ use winit::{
 };
 use wgpu_simplified as ws;
 use wgpu_simplified::texture_data as td;
-use wgpu_complex_function::colormap;
-
-#[include_wgsl_oil::include_wgsl_oil("domain_color_comp.wgsl")]
-mod cs_shader_mod {}
+use num_complex::Complex;
+use wgpu_complex_function::{colormap, complex_catalog, complex_expr, complex_wgsl};
+use wgpu_complex_function::domain_color::{self, DomainColoring};
+use wgpu_complex_function::mobius::Mobius;
 
 fn create_color_data(colormap_name: &str) -> Vec<[f32; 4]> {
     let cdata = colormap::colormap_data(colormap_name);
@@ -21,33 +21,156 @@ fn create_color_data(colormap_name: &str) -> Vec<[f32; 4]> {
     }
     data
 }
+
+fn create_cfunc(func_expr: Option<&str>, selection: u32) -> String {
+    match func_expr {
+        Some(expr) => match complex_expr::parse(expr) {
+            Ok(e) => complex_wgsl::wgsl_cfunc(&e),
+            Err(e) => {
+                eprintln!("invalid expression '{}': {}", expr, e);
+                std::process::exit(1);
+            }
+        },
+        None => complex_catalog::complex_functions()[selection as usize].wgsl_cfunc(),
+    }
+}
+
+// one map of each kind; the animation parameter moves them from the identity to the map
+fn mobius_presets() -> Vec<(&'static str, Mobius)> {
+    let c = |re: f64, im: f64| Complex::new(re, im);
+    let (cos, sin) = (std::f64::consts::FRAC_PI_3.cos(), std::f64::consts::FRAC_PI_3.sin());
+    vec![
+        ("elliptic", Mobius::new(c(cos, 0.0), c(sin, 0.0), c(-sin, 0.0), c(cos, 0.0)).unwrap()),
+        ("parabolic", Mobius::new(c(1.0, 0.0), c(0.0, 0.0), c(1.0, 0.0), c(1.0, 0.0)).unwrap()),
+        ("hyperbolic", Mobius::new(c(1.25, 0.0), c(0.75, 0.0), c(0.75, 0.0), c(1.25, 0.0)).unwrap()),
+        ("loxodromic", Mobius::new(c(1.2, 0.9), c(0.6, 0.0), c(0.6, 0.0), c(1.2, 0.0)).unwrap()),
+    ]
+}
+
+// picture for image domain coloring, bound as group 2 with a repeating linear sampler
+fn create_picture_bind_group(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    picture: &image::RgbaImage,
+) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+    let (width, height) = picture.dimensions();
+    let size = wgpu::Extent3d { width, height, depth_or_array_layers: 1 };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Picture Texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        picture.as_raw(),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * width),
+            rows_per_image: Some(height),
+        },
+        size,
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Picture Sampler"),
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Picture Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Picture Bind Group"),
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&sampler) },
+        ],
+    });
+    (layout, bind_group)
+}
+
+fn create_compute_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    cfunc: &str,
+) -> wgpu::ComputePipeline {
+    let source = complex_wgsl::compose_shader(include_str!("domain_color_comp.wgsl"), Some(cfunc));
+    let cs_comp = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Compute Shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::from(source)),
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Compute Pipeline"),
+        layout: Some(layout),
+        module: &cs_comp,
+        entry_point: "cs_main",
+    })
+}
+
 struct State {
     init: ws::IWgpuInit,
     pipeline: wgpu::RenderPipeline,
     uniform_bind_group: wgpu::BindGroup,
 
     cs_pipeline: wgpu::ComputePipeline,
+    cs_pipeline_layout: wgpu::PipelineLayout,
     cs_uniform_buffers: Vec<wgpu::Buffer>,
     cs_bind_groups: Vec<wgpu::BindGroup>,
 
     animation_speed: f32,
     function_type: u32,
-    colormap_type: u32,
+    mobius_type: usize,
+    style_type: usize,
+    colormap_name: String, // for the colormap style, which wraps it around the phase
     scale: f32,
+    center: [f32; 2],
     fps_counter: ws::FpsCounter,
 }
 
 impl State {
-    async fn new(window: &Window, colormap_name: &str) -> Self {
+    async fn new(
+        window: &Window,
+        colormap_name: &str,
+        func_expr: Option<&str>,
+        picture: &image::RgbaImage,
+    ) -> Self {
         let init = ws::IWgpuInit::new(&window, 1, None).await;
 
         let shader = init.device.create_shader_module(wgpu::include_wgsl!("render_shader.wgsl"));
 
-        let cs_comp = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Compute Shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::from(cs_shader_mod::SOURCE)),
-        });
-
         let tex = td::ITexture::create_texture_store_data(
             &init.device,
             init.size.width,
@@ -93,7 +216,14 @@ impl State {
 
         let cs_float_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Compute float Uniform Buffer"),
-            size: 16,
+            size: 24,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let cs_style_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Style Uniform Buffer"),
+            size: std::mem::size_of::<domain_color::StyleParams>() as u64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -104,38 +234,45 @@ impl State {
                 wgpu::ShaderStages::COMPUTE,
                 wgpu::ShaderStages::COMPUTE,
                 wgpu::ShaderStages::COMPUTE,
+                wgpu::ShaderStages::COMPUTE,
             ],
             vec![
                 wgpu::BufferBindingType::Uniform,
                 wgpu::BufferBindingType::Uniform,
                 wgpu::BufferBindingType::Uniform,
+                wgpu::BufferBindingType::Uniform,
             ],
             &[
                 cs_colormap_buffer.as_entire_binding(),
                 cs_int_uniform_buffer.as_entire_binding(),
                 cs_float_uniform_buffer.as_entire_binding(),
+                cs_style_uniform_buffer.as_entire_binding(),
             ],
         );
 
         let (cs_texture_bind_group_layout, cs_texture_bind_group) =
             ws::create_compute_texture_bind_group(&init.device, &tex.view);
 
+        let (cs_picture_bind_group_layout, cs_picture_bind_group) =
+            create_picture_bind_group(&init.device, &init.queue, picture);
+
         let cs_pipeline_layout =
             init.device
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                     label: Some("Compute Pipeline Layout"),
-                    bind_group_layouts: &[&cs_bind_group_layout, &cs_texture_bind_group_layout],
+                    bind_group_layouts: &[
+                        &cs_bind_group_layout,
+                        &cs_texture_bind_group_layout,
+                        &cs_picture_bind_group_layout,
+                    ],
                     push_constant_ranges: &[],
                 });
 
-        let cs_pipeline = init
-            .device
-            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                label: Some("Compute Pipeline"),
-                layout: Some(&cs_pipeline_layout),
-                module: &cs_comp,
-                entry_point: "cs_main",
-            });
+        let cs_pipeline = create_compute_pipeline(
+            &init.device,
+            &cs_pipeline_layout,
+            &create_cfunc(func_expr, 0),
+        );
 
         Self {
             init,
@@ -143,17 +280,22 @@ impl State {
             uniform_bind_group: texture_bind_group,
 
             cs_pipeline,
+            cs_pipeline_layout,
             cs_uniform_buffers: vec![
                 cs_colormap_buffer,
                 cs_int_uniform_buffer,
                 cs_float_uniform_buffer,
+                cs_style_uniform_buffer,
             ],
-            cs_bind_groups: vec![cs_bind_group, cs_texture_bind_group],
+            cs_bind_groups: vec![cs_bind_group, cs_texture_bind_group, cs_picture_bind_group],
 
             animation_speed: 1.0,
             function_type: 0,
-            colormap_type: 0,
+            mobius_type: 0,
+            style_type: 0,
+            colormap_name: colormap_name.to_string(),
             scale: 5.0,
+            center: [0.0, 0.0],
             fps_counter: ws::FpsCounter::default(),
         }
     }
@@ -198,12 +340,38 @@ impl State {
                 ..
             } => match keycode {
                 VirtualKeyCode::Space => {
-                    self.function_type = (self.function_type + 1) % 13;
-                    println!("function = {}", self.function_type);
+                    let functions = complex_catalog::complex_functions();
+                    self.function_type = (self.function_type + 1) % functions.len() as u32;
+                    self.cs_pipeline = create_compute_pipeline(
+                        &self.init.device,
+                        &self.cs_pipeline_layout,
+                        &create_cfunc(None, self.function_type),
+                    );
+                    println!(
+                        "function = {} ({})",
+                        self.function_type, functions[self.function_type as usize].name
+                    );
+                    true
+                }
+                VirtualKeyCode::M => {
+                    let presets = mobius_presets();
+                    let (name, m) = presets[self.mobius_type];
+                    self.mobius_type = (self.mobius_type + 1) % presets.len();
+                    self.cs_pipeline = create_compute_pipeline(
+                        &self.init.device,
+                        &self.cs_pipeline_layout,
+                        &complex_wgsl::wgsl_cfunc(&m.animated_expr()),
+                    );
+                    println!("mobius = {} ({:?}, fixed points {:?})", name, m.kind(), m.fixed_points());
                     true
                 }
                 VirtualKeyCode::LControl => {
-                    self.colormap_type = (self.colormap_type + 1) % 2;
+                    let styles = DomainColoring::styles();
+                    self.style_type = (self.style_type + 1) % styles.len();
+                    println!("style = {:?}", styles[self.style_type]);
+                    if styles[self.style_type] == DomainColoring::Colormap {
+                        colormap::warn_if_not_cyclic(&self.colormap_name);
+                    }
                     true
                 }
                 VirtualKeyCode::Q => {
@@ -225,10 +393,14 @@ impl State {
 
     fn update(&mut self, dt: std::time::Duration) {
         // update uniform buffer for compute pipeline
-        let int_params = [self.function_type, self.colormap_type];
+        let style = DomainColoring::styles()[self.style_type];
+        let int_params = [style.id()];
         self.init
             .queue
             .write_buffer(&self.cs_uniform_buffers[1], 0, cast_slice(&int_params));
+        self.init
+            .queue
+            .write_buffer(&self.cs_uniform_buffers[3], 0, cast_slice(&[style.gpu_params()]));
 
         let dt1 = self.animation_speed * dt.as_secs_f32();
         let float_params = [
@@ -236,6 +408,8 @@ impl State {
             self.init.size.width as f32,
             self.init.size.height as f32,
             self.scale,
+            self.center[0],
+            self.center[1],
         ];
         self.init
             .queue
@@ -263,6 +437,7 @@ impl State {
             cs_pass.set_pipeline(&self.cs_pipeline);
             cs_pass.set_bind_group(0, &self.cs_bind_groups[0], &[]);
             cs_pass.set_bind_group(1, &self.cs_bind_groups[1], &[]);
+            cs_pass.set_bind_group(2, &self.cs_bind_groups[2], &[]);
             cs_pass.dispatch_workgroups(self.init.size.width / 8, self.init.size.height / 8, 1);
         }
 
@@ -288,11 +463,26 @@ impl State {
 }
 
 fn main() {
-    let mut colormap_name = "jet";
+    let mut colormap_name = "twilight";
+    let mut func_expr: Option<&str> = None;
     let args: Vec<String> = std::env::args().collect();
     if args.len() > 1 {
         colormap_name = &args[1];
     }
+    if args.len() > 2 {
+        func_expr = Some(&args[2]);
+    }
+    // picture for the image style
+    let picture = match args.get(3) {
+        Some(path) => match image::open(path) {
+            Ok(p) => p.to_rgba8(),
+            Err(e) => {
+                eprintln!("cannot open picture '{}': {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => domain_color::grid_picture(256),
+    };
 
     env_logger::init();
     let event_loop = EventLoop::new();
@@ -301,7 +491,7 @@ fn main() {
         .unwrap();
     window.set_title(&*format!("{}", "domain_color"));
 
-    let mut state = pollster::block_on(State::new(&window, colormap_name));
+    let mut state = pollster::block_on(State::new(&window, colormap_name, func_expr, &picture));
     let render_start_time = std::time::Instant::now();
 
     event_loop.run(move |event, _, control_flow| match event {
@@ -0,0 +1,63 @@
+use num_complex::Complex;
+use wgpu_complex_function::{colormap, complex_catalog, complex_expr};
+use wgpu_complex_function::domain_color::{self, DomainColor, DomainColoring};
+
+// renders the domain_color example on the CPU and writes a PNG, without a window or a GPU:
+// domain_color_png [style|colormap] [expression] [output.png] [width] [height] [samples] [picture]
+// where style is one of the DomainColoring names (hsv, phase, enhanced_phase, image, ...)
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let arg = |i: usize| args.get(i).map(|s| s.as_str());
+
+    let mut dc = DomainColor::new([0.0, 0.0], 5.0, 1024, 768);
+    match arg(1).map(|name| (name, DomainColoring::from_name(name))) {
+        None => dc.coloring = DomainColoring::Hsv,
+        Some((_, Some(style))) => dc.coloring = style,
+        Some((name, None)) => {
+            if let Err(e) = colormap::phase_colormap_data(name) {
+                eprintln!("{}; try twilight, twilight_shifted, cet_c2 or hsv", e);
+                std::process::exit(1);
+            }
+            dc.coloring = DomainColoring::Colormap;
+            dc.colormap_name = name.to_string();
+        }
+    }
+    let func_expr = match arg(2).map(complex_expr::parse) {
+        Some(Ok(e)) => Some(e),
+        Some(Err(e)) => {
+            eprintln!("invalid expression '{}': {}", arg(2).unwrap(), e);
+            std::process::exit(1);
+        }
+        None => None,
+    };
+    let path = arg(3).unwrap_or("domain_color.png");
+    let number = |i: usize, default: u32| arg(i).and_then(|s| s.parse().ok()).unwrap_or(default);
+    dc.width = number(4, dc.width);
+    dc.height = number(5, dc.height);
+    dc.samples = number(6, 2);
+    match arg(7) {
+        Some(picture) => {
+            if let Err(e) = dc.load_picture(picture) {
+                eprintln!("cannot open picture '{}': {}", picture, e);
+                std::process::exit(1);
+            }
+        }
+        None => dc.picture = Some(domain_color::grid_picture(256)),
+    }
+
+    let start = std::time::Instant::now();
+    let result = match &func_expr {
+        Some(e) => dc.save_png(path, |z| e.eval(z, 1.0)),
+        None => {
+            let f = &complex_catalog::complex_functions()[0];
+            dc.save_png(path, |z: Complex<f64>| f.eval(z, 1.0))
+        }
+    };
+    match result {
+        Ok(()) => println!("saved {} ({}x{}, {:?})", path, dc.width, dc.height, start.elapsed()),
+        Err(e) => {
+            eprintln!("failed to save {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use bytemuck::cast_slice;
+use std::{iter, mem, borrow::Cow};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+use wgpu_simplified as ws;
+use wgpu_simplified::texture_data as td;
+use num_complex::Complex;
+use wgpu_complex_function::{colormap, complex_wgsl};
+use wgpu_complex_function::escape_time::{EscapeColoring, EscapeParams, EscapeSet, EscapeTime};
+
+fn create_color_data(colormap_name: &str) -> Vec<[f32; 4]> {
+    let cdata = colormap::colormap_data(colormap_name);
+    let mut data: Vec<[f32; 4]> = vec![];
+    for i in 0..cdata.len() {
+        data.push([cdata[i][0], cdata[i][1], cdata[i][2], 1.0]);
+    }
+    data
+}
+
+struct State {
+    init: ws::IWgpuInit,
+    pipeline: wgpu::RenderPipeline,
+    uniform_bind_group: wgpu::BindGroup,
+
+    cs_pipeline: wgpu::ComputePipeline,
+    cs_uniform_buffers: Vec<wgpu::Buffer>,
+    cs_bind_groups: Vec<wgpu::BindGroup>,
+
+    fractal: EscapeTime,
+    fps_counter: ws::FpsCounter,
+}
+
+impl State {
+    async fn new(window: &Window, colormap_name: &str) -> Self {
+        let init = ws::IWgpuInit::new(&window, 1, None).await;
+
+        let shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("render_shader.wgsl"));
+
+        let cs_comp = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Compute Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::from(complex_wgsl::compose_shader(
+                include_str!("escape_time_comp.wgsl"), None))),
+        });
+
+        let tex = td::ITexture::create_texture_store_data(
+            &init.device,
+            init.size.width,
+            init.size.height,
+        )
+        .unwrap();
+        let (texture_bind_group_layout, texture_bind_group) =
+            ws::create_texture_store_bind_group(&init.device, &tex);
+
+        let pipeline_layout = init
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[&texture_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let mut ppl = ws::IRenderPipeline {
+            shader: Some(&shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &[],
+            is_depth_stencil: false,
+            ..Default::default()
+        };
+        let pipeline = ppl.new(&init);
+
+        // create compute pipeline for domain coloring
+        let cdata = create_color_data(colormap_name);
+        let cs_colormap_buffer =
+            init.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Colormap Uniform Buffer"),
+                    contents: bytemuck::cast_slice(&cdata),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+        let cs_params_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Params Uniform Buffer"),
+            size: mem::size_of::<EscapeParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let (cs_bind_group_layout, cs_bind_group) = ws::create_bind_group_storage(
+            &init.device,
+            vec![
+                wgpu::ShaderStages::COMPUTE,
+                wgpu::ShaderStages::COMPUTE,
+            ],
+            vec![
+                wgpu::BufferBindingType::Uniform,
+                wgpu::BufferBindingType::Uniform,
+            ],
+            &[
+                cs_colormap_buffer.as_entire_binding(),
+                cs_params_uniform_buffer.as_entire_binding(),
+            ],
+        );
+
+        let (cs_texture_bind_group_layout, cs_texture_bind_group) =
+            ws::create_compute_texture_bind_group(&init.device, &tex.view);
+
+        let cs_pipeline_layout =
+            init.device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Compute Pipeline Layout"),
+                    bind_group_layouts: &[&cs_bind_group_layout, &cs_texture_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let cs_pipeline = init
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Compute Pipeline"),
+                layout: Some(&cs_pipeline_layout),
+                module: &cs_comp,
+                entry_point: "cs_main",
+            });
+
+        Self {
+            init,
+            pipeline,
+            uniform_bind_group: texture_bind_group,
+
+            cs_pipeline,
+            cs_uniform_buffers: vec![
+                cs_colormap_buffer,
+                cs_params_uniform_buffer,
+            ],
+            cs_bind_groups: vec![cs_bind_group, cs_texture_bind_group],
+
+            fractal: EscapeTime { colormap_name: colormap_name.to_string(), ..EscapeTime::mandelbrot() },
+            fps_counter: ws::FpsCounter::default(),
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.init.size = new_size;
+            self.init.config.width = new_size.width;
+            self.init.config.height = new_size.height;
+            self.init
+                .surface
+                .configure(&self.init.device, &self.init.config);
+
+            // update texture bind groups for both render and compute pipelines when resizing
+            let tex = td::ITexture::create_texture_store_data(
+                &self.init.device,
+                self.init.size.width,
+                self.init.size.height,
+            )
+            .unwrap();
+            let (_, texture_bind_group) =
+                ws::create_texture_store_bind_group(&self.init.device, &tex);
+            self.uniform_bind_group = texture_bind_group;
+
+            let (_, cs_texture_bind_group) =
+                ws::create_compute_texture_bind_group(&self.init.device, &tex.view);
+
+            self.cs_bind_groups[1] = cs_texture_bind_group;
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(keycode),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::Space => {
+                    // the Julia set of the point at the center of the Mandelbrot view, and back
+                    self.fractal = match self.fractal.set {
+                        EscapeSet::Mandelbrot => {
+                            let c = Complex::new(self.fractal.center[0] as f64, self.fractal.center[1] as f64);
+                            println!("julia c = {}", c);
+                            EscapeTime { set: EscapeSet::Julia(c), center: [0.0, 0.0], zoom: 1.0, ..self.fractal.clone() }
+                        }
+                        EscapeSet::Julia(_) => EscapeTime { set: EscapeSet::Mandelbrot, center: [-0.5, 0.0], zoom: 1.0,
+                            ..self.fractal.clone() },
+                    };
+                    true
+                }
+                VirtualKeyCode::D => {
+                    self.fractal.coloring = match self.fractal.coloring {
+                        EscapeColoring::Smooth => EscapeColoring::Distance,
+                        EscapeColoring::Distance => EscapeColoring::Smooth,
+                    };
+                    println!("coloring = {:?}", self.fractal.coloring);
+                    true
+                }
+                VirtualKeyCode::Q | VirtualKeyCode::A => {
+                    let d = if *keycode == VirtualKeyCode::Q { 0.5 } else { -0.5 };
+                    self.fractal.exponent = (self.fractal.exponent + d).max(1.5);
+                    println!("exponent = {}", self.fractal.exponent);
+                    true
+                }
+                VirtualKeyCode::W | VirtualKeyCode::S => {
+                    let n = self.fractal.max_iterations;
+                    self.fractal.max_iterations = if *keycode == VirtualKeyCode::W { 2 * n } else { (n / 2).max(16) };
+                    println!("max iterations = {}", self.fractal.max_iterations);
+                    true
+                }
+                VirtualKeyCode::PageUp | VirtualKeyCode::PageDown => {
+                    self.fractal.zoom *= if *keycode == VirtualKeyCode::PageUp { 1.25 } else { 0.8 };
+                    println!("zoom = {}", self.fractal.zoom);
+                    true
+                }
+                VirtualKeyCode::Left | VirtualKeyCode::Right | VirtualKeyCode::Up | VirtualKeyCode::Down => {
+                    let d = 0.1 * self.fractal.scale();
+                    match keycode {
+                        VirtualKeyCode::Left => self.fractal.center[0] -= d,
+                        VirtualKeyCode::Right => self.fractal.center[0] += d,
+                        VirtualKeyCode::Up => self.fractal.center[1] += d,
+                        _ => self.fractal.center[1] -= d,
+                    }
+                    true
+                }
+                VirtualKeyCode::P => {
+                    // CPU reference image of the current view
+                    let (w, h) = (self.init.size.width, self.init.size.height);
+                    match self.fractal.save_png("escape_time.png", w, h) {
+                        Ok(_) => println!("saved escape_time.png"),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, _dt: std::time::Duration) {
+        // update uniform buffers for compute pipeline
+        let params = self.fractal.gpu_params(self.init.size.width, self.init.size.height);
+        self.init
+            .queue
+            .write_buffer(&self.cs_uniform_buffers[1], 0, cast_slice(&[params]));
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let output = self.init.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder =
+            self.init
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
+                });
+
+        // compute pass for vertices
+        {
+            let mut cs_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Compute Pass"),
+            });
+            cs_pass.set_pipeline(&self.cs_pipeline);
+            cs_pass.set_bind_group(0, &self.cs_bind_groups[0], &[]);
+            cs_pass.set_bind_group(1, &self.cs_bind_groups[1], &[]);
+            cs_pass.dispatch_workgroups(self.init.size.width / 8, self.init.size.height / 8, 1);
+        }
+
+        // render pass
+        {
+            let color_attachment = ws::create_color_attachment(&view);
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            render_pass.draw(0..6, 0..1);
+        }
+        self.fps_counter.print_fps(5);
+        self.init.queue.submit(iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+}
+
+fn main() {
+    let mut colormap_name = "jet";
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 {
+        colormap_name = &args[1];
+    }
+
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = winit::window::WindowBuilder::new()
+        .build(&event_loop)
+        .unwrap();
+    window.set_title(&*format!("{}", "escape_time"));
+
+    let mut state = pollster::block_on(State::new(&window, colormap_name));
+    let render_start_time = std::time::Instant::now();
+
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::WindowEvent {
+            ref event,
+            window_id,
+        } if window_id == window.id() => {
+            if !state.input(event) {
+                match event {
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        state.resize(**new_inner_size);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Event::RedrawRequested(_) => {
+            let now = std::time::Instant::now();
+            let dt = now - render_start_time;
+            state.update(dt);
+
+            match state.render() {
+                Ok(_) => {}
+                Err(wgpu::SurfaceError::Lost) => state.resize(state.init.size),
+                Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
+                Err(e) => eprintln!("{:?}", e),
+            }
+        }
+        Event::MainEventsCleared => {
+            window.request_redraw();
+        }
+        _ => {}
+    });
+}
\ No newline at end of file
@@ -0,0 +1,78 @@
+#import ../../src/complex_func.wgsl as cf;
+
+// Mandelbrot, Julia and multibrot sets by escape time, mirrored on the CPU by escape_time.rs
+
+@group(0) @binding(0) var<uniform> colormap: array<vec4f, 11>;
+
+struct EscapeParams {
+    center: vec2f,
+    juliaC: vec2f,
+    scale: f32,
+    width: f32,
+    height: f32,
+    bailout: f32,
+    exponent: f32,
+    boundaryWidth: f32,
+    maxIterations: u32,
+    julia: u32,          // 0 Mandelbrot, 1 Julia
+    coloring: u32,       // 0 smooth, 1 distance
+    pad0: u32,
+    pad1: u32,
+    pad2: u32,
+}
+@group(0) @binding(1) var<uniform> params: EscapeParams;
+
+@group(1) @binding(0) var tex: texture_storage_2d<rgba8unorm, write>;
+
+// z^(d-1), by repeated multiplication for integer d
+fn powD1(z: vec2f, d: f32) -> vec2f {
+    if (fract(d) == 0.0 && d <= 64.0) {
+        var p = z;
+        for (var k = 2u; k < u32(d); k = k + 1u) {
+            p = cf::cMul(p, z);
+        }
+        return p;
+    }
+    return cf::cPow(z, d - 1.0);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+    let w = params.width;
+    let h = params.height;
+    let scale = params.scale;
+    let p = params.center + vec2(scale*(f32(id.x) - 0.5*w)/w, -scale*(h/w)*(f32(id.y) - 0.5*h)/h);
+
+    let d = params.exponent;
+    var c = p;
+    var mandelbrot = 1.0;
+    if (params.julia == 1u) {
+        c = params.juliaC;
+        mandelbrot = 0.0;
+    }
+    var z = p;
+    var dz = vec2(1.0, 0.0);
+    let r2 = params.bailout * params.bailout;
+
+    var color = vec4(0.0, 0.0, 0.0, 1.0);
+    for (var i = 0u; i < params.maxIterations; i = i + 1u) {
+        let m2 = dot(z, z);
+        if (m2 > r2) {
+            let m = sqrt(m2);
+            let count = max(f32(i) - log(log(m) / log(params.bailout)) / log(d), 0.0);
+            let t = log(1.0 + count) / log(1.0 + f32(params.maxIterations));
+            var b = 1.0;
+            if (params.coloring == 1u) {
+                let distance = m * log(m) / length(dz);
+                b = clamp(distance / (params.boundaryWidth * scale / w), 0.0, 1.0);
+            }
+            color = vec4(cf::colormapLerp(colormap, t) * b, 1.0);
+            break;
+        }
+        let zd1 = powD1(z, d);
+        dz = d * cf::cMul(zd1, dz) + vec2(mandelbrot, 0.0);
+        z = cf::cMul(zd1, z) + c;
+    }
+
+    textureStore(tex, vec2(id.xy), color);
+}
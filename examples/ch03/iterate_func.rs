@@ -1,5 +1,5 @@
 use bytemuck::cast_slice;
-use std::{iter, borrow::Cow};
+use std::{iter, mem, borrow::Cow};
 use wgpu::util::DeviceExt;
 use winit::{
     event::*,
@@ -8,10 +8,8 @@ use winit::{
 };
 use wgpu_simplified as ws;
 use wgpu_simplified::texture_data as td;
-use wgpu_complex_function::colormap;
-
-#[include_wgsl_oil::include_wgsl_oil("iterate_func_comp.wgsl")]
-mod cs_shader_mod {}
+use wgpu_complex_function::{colormap, complex_expr, complex_wgsl};
+use wgpu_complex_function::iterate_func::{IterateColoring, IterateFunc, IterateParams, OrbitTrap, BUILTIN_COUNT};
 
 fn create_color_data(colormap_name: &str) -> Vec<[f32; 4]> {
     let cdata = colormap::colormap_data(colormap_name);
@@ -22,6 +20,17 @@ fn create_color_data(colormap_name: &str) -> Vec<[f32; 4]> {
     data
 }
 
+fn create_compute_source(func_expr: Option<&str>) -> String {
+    let cfunc = func_expr.map(|expr| match complex_expr::parse(expr) {
+        Ok(e) => complex_wgsl::wgsl_cfunc(&e),
+        Err(e) => {
+            eprintln!("invalid expression '{}': {}", expr, e);
+            std::process::exit(1);
+        }
+    });
+    complex_wgsl::compose_shader(include_str!("iterate_func_comp.wgsl"), cfunc.as_deref())
+}
+
 struct State {
     init: ws::IWgpuInit,
     pipeline: wgpu::RenderPipeline,
@@ -32,14 +41,13 @@ struct State {
     cs_bind_groups: Vec<wgpu::BindGroup>,
 
     animation_speed: f32,
-    function_type: u32,
-    colormap_type: u32,
-    scale: f32,
+    iterate: IterateFunc,
+    custom_func: bool, // cFunc replaced by a formula, which the CPU reference does not know
     fps_counter: ws::FpsCounter,
 }
 
 impl State {
-    async fn new(window: &Window, colormap_name: &str) -> Self {
+    async fn new(window: &Window, colormap_name: &str, func_expr: Option<&str>) -> Self {
         let init = ws::IWgpuInit::new(&window, 1, None).await;
 
         let shader = init
@@ -48,7 +56,7 @@ impl State {
 
         let cs_comp = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Compute Shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::from(cs_shader_mod::SOURCE)),
+            source: wgpu::ShaderSource::Wgsl(Cow::from(create_compute_source(func_expr))),
         });
 
         let tex = td::ITexture::create_texture_store_data(
@@ -87,16 +95,9 @@ impl State {
                     usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 });
 
-        let cs_int_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Compute Integer Uniform Buffer"),
-            size: 16,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let cs_float_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Compute float Uniform Buffer"),
-            size: 16,
+        let cs_params_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Params Uniform Buffer"),
+            size: mem::size_of::<IterateParams>() as u64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -106,17 +107,14 @@ impl State {
             vec![
                 wgpu::ShaderStages::COMPUTE,
                 wgpu::ShaderStages::COMPUTE,
-                wgpu::ShaderStages::COMPUTE,
             ],
             vec![
                 wgpu::BufferBindingType::Uniform,
                 wgpu::BufferBindingType::Uniform,
-                wgpu::BufferBindingType::Uniform,
             ],
             &[
                 cs_colormap_buffer.as_entire_binding(),
-                cs_int_uniform_buffer.as_entire_binding(),
-                cs_float_uniform_buffer.as_entire_binding(),
+                cs_params_uniform_buffer.as_entire_binding(),
             ],
         );
 
@@ -148,15 +146,13 @@ impl State {
             cs_pipeline,
             cs_uniform_buffers: vec![
                 cs_colormap_buffer,
-                cs_int_uniform_buffer,
-                cs_float_uniform_buffer,
+                cs_params_uniform_buffer,
             ],
             cs_bind_groups: vec![cs_bind_group, cs_texture_bind_group],
 
             animation_speed: 1.0,
-            function_type: 0,
-            colormap_type: 0,
-            scale: 5.0,
+            iterate: IterateFunc { colormap_name: colormap_name.to_string(), ..IterateFunc::default() },
+            custom_func: func_expr.is_some(),
             fps_counter: ws::FpsCounter::default(),
         }
     }
@@ -201,12 +197,55 @@ impl State {
                 ..
             } => match keycode {
                 VirtualKeyCode::Space => {
-                    self.function_type = (self.function_type + 1) % 11;
-                    println!("function = {}", self.function_type);
+                    let function = (self.iterate.function + 1) % BUILTIN_COUNT;
+                    self.iterate = IterateFunc { trap: self.iterate.trap, coloring: self.iterate.coloring,
+                        colormap_name: self.iterate.colormap_name.clone(), ..IterateFunc::builtin(function).unwrap() };
+                    println!("function = {}, iterations = {}", function, self.iterate.iterations);
                     true
                 }
                 VirtualKeyCode::LControl => {
-                    self.colormap_type = (self.colormap_type + 1) % 2;
+                    self.iterate.coloring = match self.iterate.coloring {
+                        IterateColoring::Hsv => IterateColoring::Colormap,
+                        IterateColoring::Colormap => IterateColoring::EscapeTime,
+                        IterateColoring::EscapeTime => IterateColoring::OrbitTrap,
+                        IterateColoring::OrbitTrap => IterateColoring::Hsv,
+                    };
+                    println!("coloring = {:?}", self.iterate.coloring);
+                    true
+                }
+                VirtualKeyCode::W | VirtualKeyCode::S => {
+                    let n = self.iterate.iterations;
+                    self.iterate.iterations = if *keycode == VirtualKeyCode::W { n + 1 } else { n.max(2) - 1 };
+                    println!("iterations = {}", self.iterate.iterations);
+                    true
+                }
+                VirtualKeyCode::E | VirtualKeyCode::D => {
+                    self.iterate.bailout *= if *keycode == VirtualKeyCode::E { 10.0 } else { 0.1 };
+                    self.iterate.bailout = self.iterate.bailout.clamp(1.0, 1.0e18);
+                    println!("bailout = {}", self.iterate.bailout);
+                    true
+                }
+                VirtualKeyCode::T => {
+                    self.iterate.trap = match self.iterate.trap {
+                        OrbitTrap::Point { .. } => OrbitTrap::Line { point: [0.0, 0.0], angle: 0.0 },
+                        OrbitTrap::Line { .. } => OrbitTrap::Circle { center: [0.0, 0.0], radius: 1.0 },
+                        OrbitTrap::Circle { .. } => OrbitTrap::Point { center: [0.0, 0.0] },
+                    };
+                    println!("trap = {:?}", self.iterate.trap);
+                    true
+                }
+                VirtualKeyCode::P => {
+                    // CPU reference image of the current frame, built-in functions only
+                    if self.custom_func {
+                        eprintln!("no CPU reference for a formula, iterate_func.png not saved");
+                    } else {
+                        let (w, h) = (self.init.size.width, self.init.size.height);
+                        let rgba = self.iterate.render_rgba(w, h);
+                        match image::save_buffer("iterate_func.png", &rgba, w, h, image::ColorType::Rgba8) {
+                            Ok(_) => println!("saved iterate_func.png"),
+                            Err(e) => eprintln!("{}", e),
+                        }
+                    }
                     true
                 }
                 VirtualKeyCode::Q => {
@@ -228,21 +267,12 @@ impl State {
 
     fn update(&mut self, dt: std::time::Duration) {
         // update uniform buffer for compute pipeline
-        let int_params = [self.function_type, self.colormap_type];
-        self.init
-            .queue
-            .write_buffer(&self.cs_uniform_buffers[1], 0, cast_slice(&int_params));
-
         let dt1 = self.animation_speed * dt.as_secs_f32();
-        let float_params = [
-            0.5 * (1.0 + dt1.cos()),
-            self.init.size.width as f32,
-            self.init.size.height as f32,
-            self.scale,
-        ];
+        self.iterate.animate = 0.5 * (1.0 + dt1.cos());
+        let params = self.iterate.gpu_params(self.init.size.width, self.init.size.height);
         self.init
             .queue
-            .write_buffer(&self.cs_uniform_buffers[2], 0, cast_slice(&float_params));
+            .write_buffer(&self.cs_uniform_buffers[1], 0, cast_slice(&[params]));
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -292,10 +322,14 @@ impl State {
 
 fn main() {
     let mut colormap_name = "jet";
+    let mut func_expr: Option<&str> = None;
     let args: Vec<String> = std::env::args().collect();
     if args.len() > 1 {
         colormap_name = &args[1];
     }
+    if args.len() > 2 {
+        func_expr = Some(&args[2]);
+    }
 
     env_logger::init();
     let event_loop = EventLoop::new();
@@ -304,7 +338,7 @@ fn main() {
         .unwrap();
     window.set_title(&*format!("{}", "iterate_func"));
 
-    let mut state = pollster::block_on(State::new(&window, colormap_name));
+    let mut state = pollster::block_on(State::new(&window, colormap_name, func_expr));
     let render_start_time = std::time::Instant::now();
 
     event_loop.run(move |event, _, control_flow| match event {
@@ -1,7 +1,10 @@
 #import ../../src/complex_func.wgsl as cf;
 
-// define iterated complex functions
-fn cFunc(z:vec2f, a:f32, selectId:u32) -> vec2f {
+// built-in functions, replaced by a generated cFunc when a formula is given
+// number of built-in functions; cs_main rejects larger funcSelect values before iterating
+const builtinCount: u32 = 11u;
+
+fn builtinFunc(z:vec2f, a:f32, selectId:u32) -> vec2f {
     var fz = z;
    
     if (selectId == 0u) {
@@ -34,58 +37,93 @@ fn cFunc(z:vec2f, a:f32, selectId:u32) -> vec2f {
         fz = cf::cConj(z) + cf::cDiv(cf::cMul(z,z), cf::cSin(cf::cPow(z,2.0) - vec2(2.0*a, 0.0)));
     } else if (selectId == 10u){
         fz = cf::cSqrt(cf::cMul(vec2(0.0,1.0), z)) + cf::cDiv(cf::cMul(z,z), cf::cSin(cf::cPow(z,2.0) - vec2(2.0*a, 0.0)));
-    } else {
-        fz = cf::cMul(vec2(a), cf::cLog(cf::cMul(z,z)));
     }
     return fz;
 }
 
 @group(0) @binding(0) var<uniform> colormap: array<vec4f, 11>;
 
-struct IntParams {
-    funcSelect: u32,   
-    colorSelect: u32,             
-}
-@group(0) @binding(1) var<uniform> ips: IntParams;
-
-struct FloatParams {
+// mirrored by IterateParams in iterate_func.rs
+struct IterateParams {
+    trap: vec4f,         // point or center in xy, line direction in zw or circle radius in z
     animateParam: f32,
     width: f32,
-    height: f32, 
-    scale: f32,          
+    height: f32,
+    scale: f32,
+    bailout: f32,
+    trapWidth: f32,
+    pad0: f32,
+    pad1: f32,
+    funcSelect: u32,
+    colorSelect: u32,    // 0 hsv, 1 colormap, 2 escape time, 3 orbit trap
+    iterations: u32,
+    trapType: u32,       // 0 point, 1 line, 2 circle
+}
+@group(0) @binding(1) var<uniform> ps: IterateParams;
+
+fn cFunc(z:vec2f, a:f32) -> vec2f {
+    return builtinFunc(z, a, ps.funcSelect);
 }
-@group(0) @binding(2) var<uniform> fps: FloatParams;
 
 @group(1) @binding(0) var tex: texture_storage_2d<rgba8unorm, write>;
 
+fn trapDistance(z: vec2f) -> f32 {
+    let p = ps.trap.xy;
+    if (ps.trapType == 1u) {
+        return abs((z.x - p.x)*ps.trap.w - (z.y - p.y)*ps.trap.z);
+    } else if (ps.trapType == 2u) {
+        return abs(length(z - p) - ps.trap.z);
+    }
+    return length(z - p);
+}
+
+// NaN or inf: all exponent bits set. A bit test, since the compiler may drop a comparison of v
+// with itself under the assumption that NaN and inf do not occur.
+fn notFinite(v: f32) -> bool {
+    return (bitcast<u32>(v) & 0x7f800000u) == 0x7f800000u;
+}
+
 @compute @workgroup_size(8, 8, 1)
 fn cs_main(@builtin(global_invocation_id) id: vec3u) {
-    let a = fps.animateParam;    
-    let w = fps.width;
-    let h = fps.height;
-    let scale = fps.scale;
-    var funcId =  ips.funcSelect;
-    let colorId = ips.colorSelect;    
+    let a = ps.animateParam;
+    let w = ps.width;
+    let h = ps.height;
+    let scale = ps.scale;
+    let colorId = ps.colorSelect;
 
     var z = vec2(scale*(f32(id.x) - 0.5*w)/w, -scale*(h/w)*(f32(id.y) - 0.5*h)/h);
-    var iters = array<u32,11>(4u,3u,4u,2u,2u,5u,4u,10u,6u,9u,4u);
-    if(funcId >= 10u) {
-        funcId = 0u;
-    }
 
-    var i = 0u;
+    // the orbit stops at the last value inside the bailout radius and before a NaN or inf; an
+    // unknown function escapes at once
+    let r2 = ps.bailout * ps.bailout;
+    var trap = trapDistance(z);
+    var steps = 0u;
+    var escaped = ps.funcSelect >= builtinCount;
     loop {
-        if(i >= iters[funcId]) { break; }      
-        z = cFunc(z, a, funcId);
-        i = i + 1u;
+        if (escaped || steps >= ps.iterations) { break; }
+        let next = cFunc(z, a);
+        if (notFinite(next.x) || notFinite(next.y) || dot(next, next) > r2) {
+            escaped = true;
+            break;
+        }
+        z = next;
+        trap = min(trap, trapDistance(z));
+        steps = steps + 1u;
     }
 
     var color:vec4f;
     if (colorId == 0u) {    // default
-        color = cf::hsv2Rgb(z); 
-    } else {                // colormaps
+        color = cf::hsv2Rgb(z);
+    } else if (colorId == 1u) {  // colormaps
         color = cf::colormap2Rgb(z, colormap);
+    } else if (colorId == 2u) {  // escape time
+        color = vec4(0.0, 0.0, 0.0, 1.0);
+        if (escaped) {
+            color = vec4(cf::colormapLerp(colormap, (f32(steps) + 1.0) / f32(ps.iterations)), 1.0);
+        }
+    } else {                     // orbit trap
+        color = vec4(cf::colormapLerp(colormap, trap / ps.trapWidth), 1.0);
     }
 
     textureStore(tex, vec2(id.xy), color);
-}
\ No newline at end of file
+}
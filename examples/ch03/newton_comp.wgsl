@@ -0,0 +1,114 @@
+#import ../../src/complex_func.wgsl as cf;
+
+// Newton fractal of f = p/q, mirrored on the CPU by newton_fractal.rs
+
+@group(0) @binding(0) var<uniform> colormap: array<vec4f, 11>;
+
+struct NewtonParams {
+    center: vec2f,
+    relaxation: vec2f,
+    scale: f32,
+    width: f32,
+    height: f32,
+    tolerance: f32,
+    shading: f32,
+    maxIterations: u32,
+    method: u32,         // 0 Newton, 1 Halley
+    numDegree: u32,
+    denDegree: u32,
+    rootCount: u32,
+    pad0: u32,
+    pad1: u32,
+}
+@group(0) @binding(1) var<uniform> params: NewtonParams;
+
+// coefficients in xy, constant term first
+struct Polynomials {
+    num: array<vec4f, 17>,
+    den: array<vec4f, 17>,
+    roots: array<vec4f, 16>,
+}
+@group(0) @binding(2) var<uniform> polys: Polynomials;
+
+@group(1) @binding(0) var tex: texture_storage_2d<rgba8unorm, write>;
+
+// p, p' and p'' by Horner's scheme
+struct Poly {
+    p: vec2f,
+    dp: vec2f,
+    ddp: vec2f,
+}
+
+fn evalNum(z: vec2f) -> Poly {
+    var r = Poly(polys.num[params.numDegree].xy, vec2(0.0), vec2(0.0));
+    for (var k = i32(params.numDegree) - 1; k >= 0; k = k - 1) {
+        r.ddp = cf::cMul(r.ddp, z) + r.dp;
+        r.dp = cf::cMul(r.dp, z) + r.p;
+        r.p = cf::cMul(r.p, z) + polys.num[k].xy;
+    }
+    r.ddp = 2.0 * r.ddp;
+    return r;
+}
+
+fn evalDen(z: vec2f) -> Poly {
+    var r = Poly(polys.den[params.denDegree].xy, vec2(0.0), vec2(0.0));
+    for (var k = i32(params.denDegree) - 1; k >= 0; k = k - 1) {
+        r.ddp = cf::cMul(r.ddp, z) + r.dp;
+        r.dp = cf::cMul(r.dp, z) + r.p;
+        r.p = cf::cMul(r.p, z) + polys.den[k].xy;
+    }
+    r.ddp = 2.0 * r.ddp;
+    return r;
+}
+
+// relaxed Newton or Halley step for f = p/q
+fn newtonStep(z: vec2f) -> vec2f {
+    let p = evalNum(z);
+    let q = evalDen(z);
+    let f = cf::cDiv(p.p, q.p);
+    let df = cf::cDiv(p.dp - cf::cMul(f, q.dp), q.p);
+    var s: vec2f;
+    if (params.method == 1u) {
+        let ddf = cf::cDiv(p.ddp - 2.0 * cf::cMul(df, q.dp) - cf::cMul(f, q.ddp), q.p);
+        s = cf::cDiv(2.0 * cf::cMul(f, df), 2.0 * cf::cMul(df, df) - cf::cMul(f, ddf));
+    } else {
+        s = cf::cDiv(f, df);
+    }
+    return cf::cMul(params.relaxation, s);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+    let w = params.width;
+    let h = params.height;
+    let scale = params.scale;
+    var z = params.center + vec2(scale*(f32(id.x) - 0.5*w)/w, -scale*(h/w)*(f32(id.y) - 0.5*h)/h);
+
+    var color = vec4(0.0, 0.0, 0.0, 1.0);
+    var dPrev = 0.0;
+    for (var i = 0u; i < params.maxIterations; i = i + 1u) {
+        let s = newtonStep(z);
+        let d = length(s);
+        if (!(d <= 3.0e38)) { break; }
+        z = z - s;
+        if (d < params.tolerance) {
+            // iteration count interpolated in log |step| between the last two steps
+            var n = f32(i + 1u);
+            if (i > 0u) {
+                n = f32(i) + (log(dPrev) - log(params.tolerance)) / (log(dPrev) - log(d));
+            }
+            for (var k = 0u; k < params.rootCount; k = k + 1u) {
+                let r = polys.roots[k].xy;
+                if (length(z - r) < 1e-3 * (1.0 + length(r))) {
+                    let rgb = cf::colormapLerp(colormap, (f32(k) + 0.5) / f32(params.rootCount));
+                    color = vec4(rgb * exp(-params.shading * n), 1.0);
+                    break;
+                }
+            }
+            break;
+        }
+        dPrev = d;
+    }
+
+    textureStore(tex, vec2(id.xy), color);
+}
@@ -0,0 +1,388 @@
+use bytemuck::cast_slice;
+use std::{iter, mem, borrow::Cow};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+use wgpu_simplified as ws;
+use wgpu_simplified::texture_data as td;
+use num_complex::Complex;
+use wgpu_complex_function::{colormap, complex_wgsl};
+use wgpu_complex_function::newton_fractal::{NewtonFractal, NewtonMethod, NewtonParams, NewtonPolynomials};
+
+fn create_color_data(colormap_name: &str) -> Vec<[f32; 4]> {
+    let cdata = colormap::colormap_data(colormap_name);
+    let mut data: Vec<[f32; 4]> = vec![];
+    for i in 0..cdata.len() {
+        data.push([cdata[i][0], cdata[i][1], cdata[i][2], 1.0]);
+    }
+    data
+}
+
+// polynomials given by their roots, switched with the space key
+fn preset(k: u32) -> NewtonFractal {
+    let c = |re: f64, im: f64| Complex::new(re, im);
+    match k % 4 {
+        0 => NewtonFractal::default(),
+        1 => NewtonFractal::from_roots(&[c(1.0, 0.0), c(-1.0, 0.0), c(0.0, 1.0), c(0.0, -1.0), c(0.0, 0.0)]).unwrap(),
+        2 => NewtonFractal::from_roots(&[c(1.0, 0.0), c(-0.5, 0.5), c(-0.5, -0.5), c(0.2, 1.2)]).unwrap(),
+        // z^3 - 2z + 2 has attracting cycles, which stay black
+        _ => NewtonFractal::from_coefficients(&[c(2.0, 0.0), c(-2.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)]).unwrap(),
+    }
+}
+
+struct State {
+    init: ws::IWgpuInit,
+    pipeline: wgpu::RenderPipeline,
+    uniform_bind_group: wgpu::BindGroup,
+
+    cs_pipeline: wgpu::ComputePipeline,
+    cs_uniform_buffers: Vec<wgpu::Buffer>,
+    cs_bind_groups: Vec<wgpu::BindGroup>,
+
+    fractal: NewtonFractal,
+    preset: u32,
+    fps_counter: ws::FpsCounter,
+}
+
+impl State {
+    async fn new(window: &Window, colormap_name: &str) -> Self {
+        let init = ws::IWgpuInit::new(&window, 1, None).await;
+
+        let shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("render_shader.wgsl"));
+
+        let cs_comp = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Compute Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::from(complex_wgsl::compose_shader(
+                include_str!("newton_comp.wgsl"), None))),
+        });
+
+        let tex = td::ITexture::create_texture_store_data(
+            &init.device,
+            init.size.width,
+            init.size.height,
+        )
+        .unwrap();
+        let (texture_bind_group_layout, texture_bind_group) =
+            ws::create_texture_store_bind_group(&init.device, &tex);
+
+        let pipeline_layout = init
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[&texture_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let mut ppl = ws::IRenderPipeline {
+            shader: Some(&shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &[],
+            is_depth_stencil: false,
+            ..Default::default()
+        };
+        let pipeline = ppl.new(&init);
+
+        // create compute pipeline for domain coloring
+        let cdata = create_color_data(colormap_name);
+        let cs_colormap_buffer =
+            init.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Colormap Uniform Buffer"),
+                    contents: bytemuck::cast_slice(&cdata),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+        let cs_params_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Params Uniform Buffer"),
+            size: mem::size_of::<NewtonParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let cs_poly_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Polynomial Uniform Buffer"),
+            size: mem::size_of::<NewtonPolynomials>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let (cs_bind_group_layout, cs_bind_group) = ws::create_bind_group_storage(
+            &init.device,
+            vec![
+                wgpu::ShaderStages::COMPUTE,
+                wgpu::ShaderStages::COMPUTE,
+                wgpu::ShaderStages::COMPUTE,
+            ],
+            vec![
+                wgpu::BufferBindingType::Uniform,
+                wgpu::BufferBindingType::Uniform,
+                wgpu::BufferBindingType::Uniform,
+            ],
+            &[
+                cs_colormap_buffer.as_entire_binding(),
+                cs_params_uniform_buffer.as_entire_binding(),
+                cs_poly_uniform_buffer.as_entire_binding(),
+            ],
+        );
+
+        let (cs_texture_bind_group_layout, cs_texture_bind_group) =
+            ws::create_compute_texture_bind_group(&init.device, &tex.view);
+
+        let cs_pipeline_layout =
+            init.device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Compute Pipeline Layout"),
+                    bind_group_layouts: &[&cs_bind_group_layout, &cs_texture_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let cs_pipeline = init
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Compute Pipeline"),
+                layout: Some(&cs_pipeline_layout),
+                module: &cs_comp,
+                entry_point: "cs_main",
+            });
+
+        Self {
+            init,
+            pipeline,
+            uniform_bind_group: texture_bind_group,
+
+            cs_pipeline,
+            cs_uniform_buffers: vec![
+                cs_colormap_buffer,
+                cs_params_uniform_buffer,
+                cs_poly_uniform_buffer,
+            ],
+            cs_bind_groups: vec![cs_bind_group, cs_texture_bind_group],
+
+            fractal: NewtonFractal { colormap_name: colormap_name.to_string(), ..preset(0) },
+            preset: 0,
+            fps_counter: ws::FpsCounter::default(),
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.init.size = new_size;
+            self.init.config.width = new_size.width;
+            self.init.config.height = new_size.height;
+            self.init
+                .surface
+                .configure(&self.init.device, &self.init.config);
+
+            // update texture bind groups for both render and compute pipelines when resizing
+            let tex = td::ITexture::create_texture_store_data(
+                &self.init.device,
+                self.init.size.width,
+                self.init.size.height,
+            )
+            .unwrap();
+            let (_, texture_bind_group) =
+                ws::create_texture_store_bind_group(&self.init.device, &tex);
+            self.uniform_bind_group = texture_bind_group;
+
+            let (_, cs_texture_bind_group) =
+                ws::create_compute_texture_bind_group(&self.init.device, &tex.view);
+
+            self.cs_bind_groups[1] = cs_texture_bind_group;
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(keycode),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::Space => {
+                    self.preset += 1;
+                    let f = preset(self.preset);
+                    self.fractal.numerator = f.numerator;
+                    self.fractal.denominator = f.denominator;
+                    self.fractal.roots = f.roots;
+                    println!("roots = {:?}", self.fractal.roots);
+                    true
+                }
+                VirtualKeyCode::M => {
+                    self.fractal.method = match self.fractal.method {
+                        NewtonMethod::Newton => NewtonMethod::Halley,
+                        NewtonMethod::Halley => NewtonMethod::Newton,
+                    };
+                    println!("method = {:?}", self.fractal.method);
+                    true
+                }
+                VirtualKeyCode::Q | VirtualKeyCode::A => {
+                    let d = if *keycode == VirtualKeyCode::Q { 0.1 } else { -0.1 };
+                    self.fractal.relaxation.re = (self.fractal.relaxation.re + d).max(0.1);
+                    println!("relaxation = {}", self.fractal.relaxation);
+                    true
+                }
+                VirtualKeyCode::W | VirtualKeyCode::S => {
+                    self.fractal.relaxation.im += if *keycode == VirtualKeyCode::W { 0.1 } else { -0.1 };
+                    println!("relaxation = {}", self.fractal.relaxation);
+                    true
+                }
+                VirtualKeyCode::PageUp | VirtualKeyCode::PageDown => {
+                    self.fractal.scale *= if *keycode == VirtualKeyCode::PageUp { 0.8 } else { 1.25 };
+                    true
+                }
+                VirtualKeyCode::Left | VirtualKeyCode::Right | VirtualKeyCode::Up | VirtualKeyCode::Down => {
+                    let d = 0.1 * self.fractal.scale;
+                    match keycode {
+                        VirtualKeyCode::Left => self.fractal.center[0] -= d,
+                        VirtualKeyCode::Right => self.fractal.center[0] += d,
+                        VirtualKeyCode::Up => self.fractal.center[1] += d,
+                        _ => self.fractal.center[1] -= d,
+                    }
+                    true
+                }
+                VirtualKeyCode::P => {
+                    // CPU reference image of the current view
+                    let (w, h) = (self.init.size.width, self.init.size.height);
+                    let rgba = self.fractal.render_rgba(w, h);
+                    match image::save_buffer("newton_fractal.png", &rgba, w, h, image::ColorType::Rgba8) {
+                        Ok(_) => println!("saved newton_fractal.png"),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, _dt: std::time::Duration) {
+        // update uniform buffers for compute pipeline
+        let params = self.fractal.gpu_params(self.init.size.width, self.init.size.height);
+        self.init
+            .queue
+            .write_buffer(&self.cs_uniform_buffers[1], 0, cast_slice(&[params]));
+
+        let polys = self.fractal.gpu_polynomials();
+        self.init
+            .queue
+            .write_buffer(&self.cs_uniform_buffers[2], 0, cast_slice(&[polys]));
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let output = self.init.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder =
+            self.init
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
+                });
+
+        // compute pass for vertices
+        {
+            let mut cs_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Compute Pass"),
+            });
+            cs_pass.set_pipeline(&self.cs_pipeline);
+            cs_pass.set_bind_group(0, &self.cs_bind_groups[0], &[]);
+            cs_pass.set_bind_group(1, &self.cs_bind_groups[1], &[]);
+            cs_pass.dispatch_workgroups(self.init.size.width / 8, self.init.size.height / 8, 1);
+        }
+
+        // render pass
+        {
+            let color_attachment = ws::create_color_attachment(&view);
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            render_pass.draw(0..6, 0..1);
+        }
+        self.fps_counter.print_fps(5);
+        self.init.queue.submit(iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+}
+
+fn main() {
+    let mut colormap_name = "jet";
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 {
+        colormap_name = &args[1];
+    }
+
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = winit::window::WindowBuilder::new()
+        .build(&event_loop)
+        .unwrap();
+    window.set_title(&*format!("{}", "newton_fractal"));
+
+    let mut state = pollster::block_on(State::new(&window, colormap_name));
+    let render_start_time = std::time::Instant::now();
+
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::WindowEvent {
+            ref event,
+            window_id,
+        } if window_id == window.id() => {
+            if !state.input(event) {
+                match event {
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        state.resize(**new_inner_size);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Event::RedrawRequested(_) => {
+            let now = std::time::Instant::now();
+            let dt = now - render_start_time;
+            state.update(dt);
+
+            match state.render() {
+                Ok(_) => {}
+                Err(wgpu::SurfaceError::Lost) => state.resize(state.init.size),
+                Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
+                Err(e) => eprintln!("{:?}", e),
+            }
+        }
+        Event::MainEventsCleared => {
+            window.request_redraw();
+        }
+        _ => {}
+    });
+}
\ No newline at end of file
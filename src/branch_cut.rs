@@ -0,0 +1,246 @@
+#![allow(dead_code)]
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use num_complex::Complex;
+use super::complex3d_data::IComplex3DOutput;
+
+// Splitting of a triangle mesh along discontinuities of f, such as branch cuts, where the grid would
+// otherwise bridge the jump with a tall wall and smear the colormap across it. An edge is cut when f
+// jumps between its end points: the phase turns by more than phase_jump the short way round and
+// |f(a) - f(b)| is more than relative_jump times the larger of |f(a)| and |f(b)|. Across a square root
+// cut f -> -f, which turns the phase by about pi at a relative jump of 2, and across a log cut f jumps
+// by 2 pi i. A zero of f next to an edge also turns the phase by up to pi, but unless it lies close to
+// the middle of the edge the relative jump stays below 3/2, and a single cut edge leaves its triangle
+// whole. Edges are also cut when the heights differ by more than height_jump times the height of the
+// plot, and, with phase_seam, where arg f wraps from pi to -pi, which is where the colormap of arg f
+// has its seam even though f is continuous there.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BranchCutOptions {
+    pub phase_jump: f64,      // radians, of the phase difference the short way round
+    pub relative_jump: f64,   // |f(a) - f(b)| over max(|f(a)|, |f(b)|), at most 2
+    pub height_jump: f32,     // fraction of the height of the plot
+    pub phase_seam: bool,     // also cut along the negative real axis of f
+    pub emit_cut_lines: bool, // fill IComplex3DOutput::cut_lines
+}
+
+impl Default for BranchCutOptions {
+    fn default() -> Self {
+        Self {
+            phase_jump: 0.6 * PI,
+            relative_jump: 1.5,
+            height_jump: 0.25,
+            phase_seam: false,
+            emit_cut_lines: false,
+        }
+    }
+}
+
+impl BranchCutOptions {
+    // whether f jumps between the samples fa and fb
+    pub fn is_jump(&self, fa: Complex<f64>, fb: Complex<f64>) -> bool {
+        let turn = (fa.arg() - fb.arg()).abs();
+        let jump = (fa - fb).norm() / fa.norm().max(fb.norm());
+        (turn.min(2.0 * PI - turn) > self.phase_jump && jump > self.relative_jump)
+            || (self.phase_seam && turn > PI)
+    }
+}
+
+// Replaces every triangle that crosses a cut edge by the pieces on either side of the cut. The pieces
+// meet the cut at the edge midpoints, where new vertices take the height and color of the vertex on
+// their own side, so the two sheets end at the cut instead of being joined. values holds f for every
+// vertex of mesh; height_span is the height of the plot in normalized coordinates.
+pub fn split_branch_cuts(mesh: &mut IComplex3DOutput, values: &[Complex<f64>], opts: &BranchCutOptions,
+    height_span: f32) {
+    let heights: Vec<f32> = mesh.positions.iter().map(|p| p[1]).collect();
+    let is_cut = |a: u32, b: u32| {
+        let (a, b) = (a as usize, b as usize);
+        opts.is_jump(values[a], values[b]) || (heights[a] - heights[b]).abs() > opts.height_jump * height_span
+    };
+
+    let mut indices: Vec<u32> = vec![];
+    let mut cut_triangles: Vec<[u32; 3]> = vec![];
+    for tri in mesh.indices.chunks(3) {
+        let t = [tri[0], tri[1], tri[2]];
+        if (0..3).any(|k| is_cut(t[k], t[(k + 1) % 3])) {
+            cut_triangles.push(t);
+        } else {
+            indices.extend(t);
+        }
+    }
+
+    // midpoint vertices keyed by (vertex on whose side they are, other end of the edge)
+    let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+    let mut midpoint = |mesh: &mut IComplex3DOutput, own: u32, other: u32| -> u32 {
+        *midpoints.entry((own, other)).or_insert_with(|| {
+            let (p, q) = (mesh.positions[own as usize], mesh.positions[other as usize]);
+            mesh.positions.push([0.5 * (p[0] + q[0]), p[1], 0.5 * (p[2] + q[2])]);
+            mesh.colors.push(mesh.colors[own as usize]);
+            mesh.positions.len() as u32 - 1
+        })
+    };
+    let mut lines: Vec<[f32; 3]> = vec![];
+    let mut line = |mesh: &IComplex3DOutput, a: u32, b: u32, c: u32, d: u32| {
+        // from the middle of edge a-b to the middle of edge c-d, halfway between both sheets
+        for (p, q) in [(a, b), (c, d)] {
+            let (p, q) = (mesh.positions[p as usize], mesh.positions[q as usize]);
+            lines.push([0.5 * (p[0] + q[0]), 0.5 * (p[1] + q[1]), 0.5 * (p[2] + q[2])]);
+        }
+    };
+
+    for t in cut_triangles {
+        let cut = [is_cut(t[0], t[1]), is_cut(t[1], t[2]), is_cut(t[2], t[0])];
+        match cut.iter().filter(|&&c| c).count() {
+            // the cut ends inside this triangle: keep it whole
+            1 => indices.extend(t),
+            2 => {
+                // rotate so that a is the vertex cut off from b and c
+                let k = (0..3).find(|&k| cut[k] && cut[(k + 2) % 3]).unwrap();
+                let (a, b, c) = (t[k], t[(k + 1) % 3], t[(k + 2) % 3]);
+                let (mab, mac) = (midpoint(mesh, a, b), midpoint(mesh, a, c));
+                let (mba, mca) = (midpoint(mesh, b, a), midpoint(mesh, c, a));
+                indices.extend([a, mab, mac]);
+                indices.extend([mba, b, c, mba, c, mca]);
+                if opts.emit_cut_lines {
+                    line(mesh, a, b, a, c);
+                }
+            }
+            _ => {
+                // every edge is cut: each vertex keeps the corner up to the edge midpoints
+                for k in 0..3 {
+                    let (a, b, c) = (t[k], t[(k + 1) % 3], t[(k + 2) % 3]);
+                    let (mab, mac) = (midpoint(mesh, a, b), midpoint(mesh, a, c));
+                    indices.extend([a, mab, mac]);
+                    if opts.emit_cut_lines {
+                        line(mesh, a, b, b, c);
+                    }
+                }
+            }
+        }
+    }
+
+    mesh.indices = indices;
+    mesh.cut_lines = lines;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one triangle in the x-z plane with the given heights and a distinct color per vertex
+    fn triangle(heights: [f32; 3]) -> IComplex3DOutput {
+        IComplex3DOutput {
+            positions: vec![[0.0, heights[0], 0.0], [1.0, heights[1], 0.0], [0.0, heights[2], 1.0]],
+            colors: vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }
+    }
+
+    fn opts() -> BranchCutOptions {
+        BranchCutOptions { emit_cut_lines: true, ..Default::default() }
+    }
+
+    // values of modulus 1 at the given phases
+    fn unit(phases: &[f64]) -> Vec<Complex<f64>> {
+        phases.iter().map(|&p| Complex::from_polar(1.0, p)).collect()
+    }
+
+    fn is_split_with(heights: [f32; 3], values: &[Complex<f64>], height_span: f32, opts: &BranchCutOptions) -> bool {
+        let mut mesh = triangle(heights);
+        split_branch_cuts(&mut mesh, values, opts, height_span);
+        mesh.indices != [0, 1, 2]
+    }
+
+    fn is_split(heights: [f32; 3], values: &[Complex<f64>], height_span: f32) -> bool {
+        is_split_with(heights, values, height_span, &opts())
+    }
+
+    #[test]
+    fn phase_threshold() {
+        // two edges turn by the phase of vertex 2; at modulus 1 the relative jump is 2 sin(turn/2) > 3/2
+        let turn = opts().phase_jump;
+        assert!(!is_split([0.0; 3], &unit(&[0.0, 0.0, turn - 1e-3]), 1.0));
+        assert!(is_split([0.0; 3], &unit(&[0.0, 0.0, turn + 1e-3]), 1.0));
+        assert!(is_split([0.0; 3], &unit(&[0.0, 0.0, -turn - 1e-3]), 1.0));
+
+        // f continuous across its negative real axis: only the phase seam cuts there
+        let across = unit(&[PI - 0.1, PI - 0.1, -PI + 0.1]);
+        assert!(!is_split([0.0; 3], &across, 1.0));
+        assert!(is_split_with([0.0; 3], &across, 1.0, &BranchCutOptions { phase_seam: true, ..opts() }));
+    }
+
+    #[test]
+    fn relative_jump_threshold() {
+        // f -> -f with a modulus of 1 on one side: the relative jump is 1 + |f(b)|
+        let c = |m: f64| vec![Complex::new(1.0, 0.0), Complex::new(1.0, 0.0), Complex::new(-m, 0.0)];
+        assert!(!is_split([0.0; 3], &c(0.45), 1.0));
+        assert!(is_split([0.0; 3], &c(0.55), 1.0));
+        // a zero at a vertex turns the phase arbitrarily, at a relative jump of 1
+        assert!(!is_split([0.0; 3], &c(0.0), 1.0));
+        let c = vec![Complex::new(0.0, 0.0), Complex::new(-1.0, 0.0), Complex::new(-1.0, 0.0)];
+        assert!(!is_split([0.0; 3], &c, 1.0));
+    }
+
+    #[test]
+    fn modulus_threshold() {
+        // height_jump = 0.25 of a plot 2 high
+        let f = unit(&[0.0; 3]);
+        assert!(!is_split([0.0, 0.0, 0.49], &f, 2.0));
+        assert!(is_split([0.0, 0.0, 0.51], &f, 2.0));
+        assert!(!is_split([0.0, 0.0, 0.51], &f, 4.0));
+    }
+
+    #[test]
+    fn split_at_edge_midpoints() {
+        // vertex 2 is cut off from 0 and 1
+        let mut mesh = triangle([0.1, 0.2, 0.9]);
+        split_branch_cuts(&mut mesh, &unit(&[0.0, 0.0, 3.0]), &opts(), 100.0);
+
+        assert_eq!(mesh.indices, [2, 3, 4, 5, 0, 1, 5, 1, 6]);
+        // new vertices halfway along the edges, at the height and in the color of their own side
+        assert_eq!(mesh.positions[3..], [[0.0, 0.9, 0.5], [0.5, 0.9, 0.5], [0.0, 0.1, 0.5], [0.5, 0.2, 0.5]]);
+        assert_eq!(mesh.colors[3..], [[0.0, 0.0, 1.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+        // the cut line runs between the edge midpoints, halfway between both sheets
+        assert_eq!(mesh.cut_lines, [[0.0, 0.5, 0.5], [0.5, 0.55, 0.5]]);
+
+        let mut mesh = triangle([0.1, 0.2, 0.9]);
+        split_branch_cuts(&mut mesh, &unit(&[0.0, 0.0, 3.0]), &BranchCutOptions::default(), 100.0);
+        assert_eq!(mesh.indices.len(), 9);
+        assert!(mesh.cut_lines.is_empty());
+    }
+
+    #[test]
+    fn neighbours_share_midpoints() {
+        // a unit square of two triangles, cut between z = 0 and z = 1
+        let mut mesh = IComplex3DOutput {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 1.0]],
+            colors: vec![[0.0; 3]; 4],
+            indices: vec![0, 1, 2, 1, 3, 2],
+            ..Default::default()
+        };
+        split_branch_cuts(&mut mesh, &unit(&[0.0, 0.0, 3.0, 3.0]), &opts(), 1.0);
+
+        // the diagonal 1-2 is split once for both triangles
+        assert_eq!(mesh.positions.len(), 4 + 6);
+        assert_eq!(mesh.indices.len(), 2 * 9);
+        assert_eq!(mesh.cut_lines.len(), 4);
+        assert!(mesh.cut_lines.iter().all(|p| p[2] == 0.5));
+    }
+
+    #[test]
+    fn cuts_ending_inside_and_crossing_every_edge() {
+        // only the edge 0-1 jumps: the cut ends inside, and the triangle is kept
+        let mut mesh = triangle([0.0; 3]);
+        split_branch_cuts(&mut mesh, &unit(&[0.0, 2.5, 1.25]), &opts(), 1.0);
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert!(mesh.cut_lines.is_empty());
+
+        // every edge jumps: three corners with a midpoint vertex per corner and edge
+        let mut mesh = triangle([0.0; 3]);
+        split_branch_cuts(&mut mesh, &unit(&[0.0, 2.1, 4.2]), &opts(), 1.0);
+        assert_eq!(mesh.indices.len(), 9);
+        assert_eq!(mesh.positions.len(), 3 + 6);
+        assert_eq!(mesh.cut_lines.len(), 6);
+    }
+}
@@ -1,13 +1,38 @@
 #![allow(dead_code)]
+use std::fmt;
+use std::sync::Mutex;
+use num_traits::Float;
 
-pub fn color_lerp(colors:[[f32;3];11], min:f32, max:f32, mut t:f32) -> [f32; 3]{
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColormapKind {
+    Sequential, // ordered from one end to the other
+    Diverging,  // two sequential halves meeting at a neutral center
+    Cyclic,     // the ends meet, as phase coloring needs
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColormapError {
+    pub name: String,
+    pub kind: ColormapKind,
+}
+
+impl fmt::Display for ColormapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "colormap '{}' is {:?}, not cyclic, and puts a seam at arg = pi", self.name, self.kind)
+    }
+}
+
+impl std::error::Error for ColormapError {}
+
+// t is clamped and normalized in its own precision (f32 or f64); the colors are always f32
+pub fn color_lerp<T: Float>(colors:[[f32;3];11], min:T, max:T, mut t:T) -> [f32; 3]{
     if t < min {
         t = min;
     } 
     if t > max {
         t = max;
     }
-    let tn = (t-min)/(max - min);
+    let tn = ((t-min)/(max - min)).to_f32().unwrap_or(0.0);
     let indx = (10.0 * tn).floor() as usize;
     
     if indx as f32 == 10.0 * tn {
@@ -99,6 +124,47 @@ pub fn colormap_data(colormap_name: &str) -> [[f32; 3]; 11] {
             [0.0000,0.0392,0.3098],[0.0000,0.0902,0.3961],[0.0000,0.2275,0.4863],[0.0000,0.3804,0.5843],
             [0.0510,0.5255,0.6863],[0.3137,0.6549,0.7686],[0.5922,0.7961,0.8627],[0.9020,0.9490,0.9647]],
 
+        // perceptually uniform sequential maps of matplotlib, sampled at t = k/10
+        "viridis" => [[0.267, 0.004, 0.329],[0.282, 0.141, 0.459],[0.255, 0.267, 0.529],[0.208, 0.373, 0.553],
+            [0.165, 0.471, 0.557],[0.129, 0.569, 0.549],[0.133, 0.659, 0.518],[0.267, 0.749, 0.439],
+            [0.478, 0.821, 0.3182],[0.741, 0.875, 0.149],[0.992, 0.906, 0.145]],
+
+        "magma" => [[0.000, 0.000, 0.016],[0.078, 0.055, 0.212],[0.231, 0.059, 0.439],[0.392, 0.102, 0.502],
+            [0.549, 0.161, 0.506],[0.718, 0.216, 0.475],[0.871, 0.286, 0.408],[0.969, 0.439, 0.361],
+            [0.996, 0.624, 0.427],[0.996, 0.812, 0.573],[0.988, 0.992, 0.749]],
+
+        "inferno" => [[0.000, 0.000, 0.016],[0.086, 0.043, 0.224],[0.259, 0.039, 0.408],[0.416, 0.090, 0.431],
+            [0.576, 0.149, 0.404],[0.737, 0.216, 0.329],[0.865, 0.317, 0.226],[0.953, 0.471, 0.098],
+            [0.988, 0.647, 0.039],[0.965, 0.843, 0.275],[0.988, 1.000, 0.643]],
+
+        "plasma" => [[0.051, 0.031, 0.529],[0.255, 0.016, 0.616],[0.416, 0.000, 0.659],[0.561, 0.051, 0.643],
+            [0.694, 0.165, 0.565],[0.800, 0.278, 0.471],[0.882, 0.392, 0.384],[0.949, 0.518, 0.294],
+            [0.988, 0.651, 0.212],[0.988, 0.808, 0.145],[0.941, 0.976, 0.129]],
+
+        "cividis" => [[0.000, 0.125, 0.302],[0.000, 0.193, 0.422],[0.179, 0.266, 0.423],[0.306, 0.337, 0.425],
+            [0.400, 0.410, 0.442],[0.490, 0.486, 0.463],[0.585, 0.564, 0.468],[0.686, 0.644, 0.448],
+            [0.794, 0.730, 0.410],[0.905, 0.822, 0.349],[1.000, 0.918, 0.275]],
+
+        // cyclic maps for phase coloring, built in CIELAB: twilight follows matplotlib's (lightness
+        // linear from 88 at the ends to 12 at t = 1/2, blue then red), twilight_shifted is
+        // twilight(1/2 - t) as in matplotlib, and cet_c2 approximates Kovesi's CET-C2
+        // (magenta, yellow, green, blue)
+        "twilight" => [[0.886, 0.851, 0.886],[0.571, 0.713, 0.835],[0.316, 0.551, 0.819],[0.279, 0.375, 0.669],
+            [0.270, 0.218, 0.412],[0.183, 0.080, 0.214],[0.400, 0.167, 0.306],[0.654, 0.250, 0.357],
+            [0.796, 0.439, 0.398],[0.820, 0.665, 0.579],[0.886, 0.851, 0.886]],
+
+        "twilight_shifted" => [[0.183, 0.080, 0.214],[0.270, 0.218, 0.412],[0.279, 0.375, 0.669],[0.316, 0.551, 0.819],
+            [0.571, 0.713, 0.835],[0.886, 0.851, 0.886],[0.820, 0.665, 0.579],[0.796, 0.439, 0.398],
+            [0.654, 0.250, 0.357],[0.400, 0.167, 0.306],[0.183, 0.080, 0.214]],
+
+        "cet_c2" => [[0.937, 0.333, 0.945],[0.990, 0.408, 0.449],[0.947, 0.688, 0.279],[0.778, 0.863, 0.235],
+            [0.512, 0.866, 0.304],[0.243, 0.769, 0.482],[0.230, 0.617, 0.714],[0.237, 0.449, 0.812],
+            [0.386, 0.339, 0.867],[0.640, 0.305, 0.917],[0.937, 0.333, 0.945]],
+        // Moreland's cool to warm map, blue and red around a light gray center
+        "coolwarm" => [[0.231, 0.298, 0.753],[0.352, 0.469, 0.890],[0.484, 0.622, 0.975],[0.622, 0.746, 0.999],
+            [0.754, 0.831, 0.961],[0.865, 0.865, 0.865],[0.948, 0.795, 0.717],[0.970, 0.677, 0.560],
+            [0.933, 0.520, 0.406],[0.842, 0.327, 0.267],[0.706, 0.016, 0.149]],
+
         // "jet" as default
         _ => [[0.0,0.0,0.51],[0.0,0.24,0.67],[0.01,0.49,0.78],[0.01,0.75,0.89],[0.02,1.0,1.0],
             [0.51,1.0,0.5],[1.0,1.0,0.0],[0.99,0.67,0.0],[0.99,0.33,0.0],[0.98,0.0,0.0],[0.5,0.0,0.0]],
@@ -107,6 +173,47 @@ pub fn colormap_data(colormap_name: &str) -> [[f32; 3]; 11] {
     colors
 }
 
+// names not in colormap_data fall back to jet, and so are sequential
+pub fn colormap_kind(colormap_name: &str) -> ColormapKind {
+    match colormap_name {
+        "hsv" | "rainbow_soft" | "twilight" | "twilight_shifted" | "cet_c2" => ColormapKind::Cyclic,
+        "coolwarm" => ColormapKind::Diverging,
+        _ => ColormapKind::Sequential,
+    }
+}
+
+pub fn is_cyclic(colormap_name: &str) -> bool {
+    colormap_kind(colormap_name) == ColormapKind::Cyclic
+}
+
+// colormap_data for coloring by phase, refusing maps that are not cyclic
+pub fn phase_colormap_data(colormap_name: &str) -> Result<[[f32; 3]; 11], ColormapError> {
+    match colormap_kind(colormap_name) {
+        ColormapKind::Cyclic => Ok(colormap_data(colormap_name)),
+        kind => Err(ColormapError { name: colormap_name.to_string(), kind }),
+    }
+}
+
+// for the phase coloring paths that keep working with any map: logs a warning the first time a
+// non-cyclic map is used for phase, and returns whether it did
+pub fn warn_if_not_cyclic(colormap_name: &str) -> bool {
+    static WARNED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+    warn_once(&mut warned, colormap_name)
+}
+
+// warn_if_not_cyclic with the names warned about so far in warned
+fn warn_once(warned: &mut Vec<String>, colormap_name: &str) -> bool {
+    match phase_colormap_data(colormap_name) {
+        Err(e) if !warned.iter().any(|name| name == colormap_name) => {
+            warned.push(colormap_name.to_string());
+            log::warn!("{}; use a cyclic map such as twilight for phase coloring", e);
+            true
+        }
+        _ => false,
+    }
+}
+
 fn convert_f32(a: [[i32; 3]; 11]) -> [[f32; 3]; 11] {
     let b: Vec<[f32;3]> = a.iter().map(|&c| [c[0] as f32, c[1] as f32, c[2] as f32]).collect();
     let mut arr:[[f32; 3]; 11] = [[0.0; 3]; 11];
@@ -115,3 +222,76 @@ fn convert_f32(a: [[i32; 3]; 11]) -> [[f32; 3]; 11] {
     }
     arr
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // CIELAB lightness of an sRGB color
+    fn lightness(c: [f32; 3]) -> f32 {
+        let lin = |v: f32| if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) };
+        let y = 0.2126 * lin(c[0]) + 0.7152 * lin(c[1]) + 0.0722 * lin(c[2]);
+        if y > 216.0 / 24389.0 { 116.0 * y.cbrt() - 16.0 } else { 24389.0 / 27.0 * y }
+    }
+
+    #[test]
+    fn perceptual_maps_have_even_lightness_steps() {
+        for name in ["viridis", "magma", "inferno", "plasma", "cividis"] {
+            let l: Vec<f32> = colormap_data(name).iter().map(|&c| lightness(c)).collect();
+            let steps: Vec<f32> = l.windows(2).map(|w| w[1] - w[0]).collect();
+            let mean = (l[10] - l[0]) / 10.0;
+            assert!(mean > 7.0, "{}: {:?}", name, l);
+            assert!(steps.iter().all(|&s| (s - mean).abs() < 0.5 * mean), "{}: {:?}", name, l);
+        }
+        // jet is far from it
+        let l: Vec<f32> = colormap_data("jet").iter().map(|&c| lightness(c)).collect();
+        assert!(l.windows(2).any(|w| w[1] < w[0]), "{:?}", l);
+    }
+
+    #[test]
+    fn cyclic_maps_close_up() {
+        for name in ["hsv", "rainbow_soft", "twilight", "twilight_shifted", "cet_c2"] {
+            assert_eq!(colormap_kind(name), ColormapKind::Cyclic);
+            let c = colormap_data(name);
+            assert!((0..3).all(|k| (c[0][k] - c[10][k]).abs() < 0.03), "{}: {:?} {:?}", name, c[0], c[10]);
+        }
+        // twilight is light at the ends and dark in the middle, with symmetric lightness
+        let l: Vec<f32> = colormap_data("twilight").iter().map(|&c| lightness(c)).collect();
+        assert!(l[0] > 85.0 && l[5] < 15.0, "{:?}", l);
+        assert!((0..5).all(|k| (l[k] - l[10 - k]).abs() < 1.0 && l[k] > l[k + 1]), "{:?}", l);
+        // and its shifted version is twilight(1/2 - t)
+        let (t, s) = (colormap_data("twilight"), colormap_data("twilight_shifted"));
+        assert!((0..=10).all(|k| s[k] == t[(15 - k) % 10]));
+    }
+
+    #[test]
+    fn phase_coloring_refuses_sequential_maps() {
+        assert_eq!(phase_colormap_data("twilight"), Ok(colormap_data("twilight")));
+        let e = phase_colormap_data("viridis").unwrap_err();
+        assert_eq!(e, ColormapError { name: "viridis".to_string(), kind: ColormapKind::Sequential });
+        assert!(e.to_string().contains("viridis"));
+        // unknown names fall back to jet
+        assert_eq!(colormap_kind("no such map"), ColormapKind::Sequential);
+        assert!(!is_cyclic("jet") && is_cyclic("cet_c2"));
+
+        // one warning per map
+        let mut warned = vec![];
+        assert!(warn_once(&mut warned, "jet"));
+        assert!(!warn_once(&mut warned, "jet"));
+        assert!(warn_once(&mut warned, "coolwarm"));
+        assert!(!warn_once(&mut warned, "twilight"));
+        assert_eq!(warned, ["jet", "coolwarm"]);
+    }
+
+    #[test]
+    fn diverging_map_is_symmetric_about_its_center() {
+        assert_eq!(colormap_kind("coolwarm"), ColormapKind::Diverging);
+        assert_eq!(phase_colormap_data("coolwarm").unwrap_err().kind, ColormapKind::Diverging);
+        // lightness rises to the neutral center and falls again at the same rate
+        let l: Vec<f32> = colormap_data("coolwarm").iter().map(|&c| lightness(c)).collect();
+        assert!((0..5).all(|k| l[k] < l[k + 1] && l[10 - k] < l[9 - k]), "{:?}", l);
+        assert!((0..5).all(|k| (l[k] - l[10 - k]).abs() < 3.0), "{:?}", l);
+        let c = colormap_data("coolwarm")[5];
+        assert!(c[0] == c[1] && c[1] == c[2]);
+    }
+}
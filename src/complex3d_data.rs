@@ -1,62 +1,392 @@
 #![allow(dead_code)]
-use num_complex::{Complex, ComplexFloat};
+use std::fmt;
+use num_complex::Complex;
 use super::colormap;
+use super::branch_cut::{self, BranchCutOptions};
+use super::contour;
+use super::conformal_grid::{self, ConformalGridOptions, GridCurve};
+use super::polya_field::{self, ArrowOptions, StreamlineOptions};
+use super::complex_func;
+use super::complex_expr::{self, Expr, ParseError};
+use super::complex_catalog::{self, Real};
+use super::complex_zeros::{self, ZeroPole, ZeroPoleKind, ZeroPoleOptions};
+use super::riemann_surface::{Multivalued, RiemannSurface, SheetHeight};
+use super::laurent_series::{self, LaurentSeries};
+use super::contour_integral::{self, Contour, ContourIntegral, IntegrationOptions};
+use super::mobius::Mobius;
 
 #[derive(Default)]
 pub struct IComplex3DOutput {
     pub positions: Vec<[f32; 3]>,
     pub colors: Vec<[f32; 3]>,
     pub indices: Vec<u32>,
+    pub height_strategy: RangeStrategy,
+    pub height_range: [f64; 2], // range of the height quantity mapped onto the surface height
+    pub color_strategy: RangeStrategy,
+    pub color_range: [f64; 2],  // range of the color quantity mapped onto the colormap
+    pub cut_lines: Vec<[f32; 3]>, // line list along branch cuts, when requested
 }
 
-pub struct IComplex3D {
-    xmin: f32,
-    xmax: f32,
-    zmin: f32,
-    zmax: f32,
+// How the range of the sampled values is chosen before they are normalized. Non-finite samples are
+// ignored by every strategy, and values outside the range are clipped to it. Fixed takes its bounds
+// in either order, and a range that collapses to a single value v, such as that of a constant
+// quantity, is widened to [v - 1/2, v + 1/2] so that normalizing does not divide by zero.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RangeStrategy {
+    #[default]
+    Raw,                  // min/max over the grid
+    Fixed(f64, f64),      // user-given min and max
+    Percentile(f64, f64), // lower and upper percentile in [0, 100], e.g. (1.0, 99.0)
+    Mad(f64),             // median +/- k scaled median absolute deviations, bounded by min/max
+}
+
+impl RangeStrategy {
+    pub fn range<T: Real>(&self, values: &[T]) -> [T; 2] {
+        let [min, max] = self.raw_range(values);
+        if max > min {
+            [min, max]
+        } else if min > max {
+            [max, min]
+        } else {
+            let half = T::from(0.5).unwrap();
+            [min - half, max + half]
+        }
+    }
+
+    fn raw_range<T: Real>(&self, values: &[T]) -> [T; 2] {
+        if let RangeStrategy::Fixed(min, max) = *self {
+            return [T::from(min).unwrap(), T::from(max).unwrap()];
+        }
+
+        let mut v: Vec<T> = values.iter().copied().filter(|x| x.is_finite()).collect();
+        if v.is_empty() {
+            return [T::zero(), T::one()];
+        }
+        v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let (min, max) = (v[0], v[v.len() - 1]);
+
+        match *self {
+            RangeStrategy::Percentile(lower, upper) => [percentile(&v, lower), percentile(&v, upper)],
+            RangeStrategy::Mad(k) => {
+                let median = percentile(&v, 50.0);
+                let mut deviations: Vec<T> = v.iter().map(|&x| (x - median).abs()).collect();
+                deviations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                // 1.4826 scales the MAD to the standard deviation of normally distributed data
+                let mad = T::from(1.4826).unwrap() * percentile(&deviations, 50.0);
+                if mad == T::zero() {
+                    return [min, max];
+                }
+                let k = T::from(k).unwrap();
+                [min.max(median - k * mad), max.min(median + k * mad)]
+            }
+            _ => [min, max],
+        }
+    }
+}
+
+// linearly interpolated percentile p (in percent) of sorted values
+fn percentile<T: Real>(sorted: &[T], p: f64) -> T {
+    let pos = (p.clamp(0.0, 100.0) / 100.0) * (sorted.len() - 1) as f64;
+    let i = pos.floor() as usize;
+    let frac = T::from(pos - i as f64).unwrap();
+    if i + 1 < sorted.len() {
+        sorted[i] + (sorted[i + 1] - sorted[i]) * frac
+    } else {
+        sorted[i]
+    }
+}
+
+// Real quantity of f(z) used for the surface height or the color value. The ids are the
+// heightSelect/colorSelect values of ComplexParams in complex3d_comp.wgsl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComplexQuantity {
+    Re,
+    Im,
+    Modulus,
+    LogModulus,
+    Arg,
+    Derivative, // |f'(z)|, by central differences
+}
+
+impl ComplexQuantity {
+    pub const ALL: [ComplexQuantity; 6] = [
+        ComplexQuantity::Re,
+        ComplexQuantity::Im,
+        ComplexQuantity::Modulus,
+        ComplexQuantity::LogModulus,
+        ComplexQuantity::Arg,
+        ComplexQuantity::Derivative,
+    ];
+
+    pub fn id(&self) -> u32 {
+        *self as u32
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ComplexQuantity::Re => "Re f",
+            ComplexQuantity::Im => "Im f",
+            ComplexQuantity::Modulus => "|f|",
+            ComplexQuantity::LogModulus => "log|f|",
+            ComplexQuantity::Arg => "arg f",
+            ComplexQuantity::Derivative => "|f'|",
+        }
+    }
+
+    pub fn value<T: Real>(&self, fz: Complex<T>, dfz: Complex<T>) -> T {
+        match self {
+            ComplexQuantity::Re => fz.re,
+            ComplexQuantity::Im => fz.im,
+            ComplexQuantity::Modulus => fz.norm(),
+            ComplexQuantity::LogModulus => fz.norm().ln(),
+            ComplexQuantity::Arg => fz.arg(),
+            ComplexQuantity::Derivative => dfz.norm(),
+        }
+    }
+
+    // Fixed range for the GPU surface, which has no access to the sampled values. Derived from the
+    // catalog ranges of |f| and arg f.
+    pub fn gpu_range(&self, height_range: [f32; 2], phase_range: [f32; 2]) -> [f32; 2] {
+        let h = height_range[1];
+        match self {
+            ComplexQuantity::Re | ComplexQuantity::Im => [-h, h],
+            ComplexQuantity::Modulus => height_range,
+            ComplexQuantity::LogModulus => [-(1.0 + h).ln(), (1.0 + h).ln()],
+            ComplexQuantity::Arg => phase_range,
+            ComplexQuantity::Derivative => [0.0, h],
+        }
+    }
+}
+
+// coloring of the Riemann sphere, as the colorSelect of domain_color_comp.wgsl
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SphereColoring {
+    Hsv,      // complex_func::hsv2_rgb
+    Colormap, // complex_func::colormap2_rgb with colormap_name, preferably a cyclic one
+}
+
+// quantities whose iso-lines create_contour_lines extracts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContourQuantity {
+    Modulus, // spacing in log2 |f|, so that lines at |f| = 2^k follow each other at equal distances
+    Arg,     // spacing in radians
+    Re,
+    Im,
+}
+
+impl ContourQuantity {
+    pub const ALL: [ContourQuantity; 4] = [ContourQuantity::Modulus, ContourQuantity::Arg, ContourQuantity::Re,
+        ContourQuantity::Im];
+
+    // the spacing of the lines hsv2Rgb shades: doubling modulus and twelve phase sectors
+    pub fn default_spacing(&self) -> f64 {
+        match self {
+            ContourQuantity::Modulus => 1.0,
+            ContourQuantity::Arg => std::f64::consts::PI / 6.0,
+            ContourQuantity::Re | ContourQuantity::Im => 0.5,
+        }
+    }
+}
+
+// one polyline in the normalized coordinates of create_complex_data; closed lines end at their
+// first point
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContourLine {
+    pub level: f64, // |f|, arg f, Re f or Im f on the line
+    pub points: Vec<[f32; 3]>,
+}
+
+// at most this many levels per call, so that a small spacing near a pole cannot run away
+pub const MAX_CONTOUR_LEVELS: usize = 256;
+
+// contour lines as a line list, two points per segment
+pub fn contour_line_list(lines: &[ContourLine]) -> Vec<[f32; 3]> {
+    lines.iter().flat_map(|l| l.points.windows(2).flat_map(|w| [w[0], w[1]])).collect()
+}
+
+// a domain with xmin >= xmax or zmin >= zmax, or with a NaN or infinite bound
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainError {
+    pub domain: [f64; 4],
+}
+
+impl fmt::Display for DomainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "domain {:?} is empty, inverted or not finite", self.domain)
+    }
+}
+
+impl std::error::Error for DomainError {}
+
+fn check_domain<T: Real>(domain: [T; 4]) -> Result<[T; 4], DomainError> {
+    if domain.iter().all(|v| v.is_finite()) && domain[0] < domain[1] && domain[2] < domain[3] {
+        Ok(domain)
+    } else {
+        Err(DomainError { domain: domain.map(|v| v.to_f64().unwrap_or(f64::NAN)) })
+    }
+}
+
+// errors of IComplex3DBuilder::build
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    Parse(ParseError),
+    Domain(DomainError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Parse(e) => e.fmt(f),
+            BuildError::Domain(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<ParseError> for BuildError {
+    fn from(e: ParseError) -> Self {
+        BuildError::Parse(e)
+    }
+}
+
+impl From<DomainError> for BuildError {
+    fn from(e: DomainError) -> Self {
+        BuildError::Domain(e)
+    }
+}
+
+// domain [xmin, xmax, zmin, zmax] moved by fx and fz times its width and height
+pub fn pan_domain<T: Real>(d: [T; 4], fx: T, fz: T) -> [T; 4] {
+    let (w, h) = (d[1] - d[0], d[3] - d[2]);
+    [d[0] + fx * w, d[1] + fx * w, d[2] + fz * h, d[3] + fz * h]
+}
+
+// domain scaled by factor about its center; factor < 1 zooms in
+pub fn zoom_domain<T: Real>(d: [T; 4], factor: T) -> [T; 4] {
+    let two = T::from(2.0).unwrap();
+    let (cx, cz) = ((d[0] + d[1]) / two, (d[2] + d[3]) / two);
+    let (hw, hh) = (factor * (d[1] - d[0]) / two, factor * (d[3] - d[2]) / two);
+    [cx - hw, cx + hw, cz - hh, cz + hh]
+}
+
+// T is the float type used for evaluating and normalizing the surface (f32 or f64); the output is
+// always f32 for upload
+pub struct IComplex3D<T: Real = f32> {
+    domain: Option<[T; 4]>, // xmin, xmax, zmin, zmax; None uses the function's default domain
     pub func_selection: u32,
-    pub x_resolution: u32,
+    pub func_expr: Option<Expr>, // overrides func_selection when set
+    pub x_resolution: u32, // cells along x, independent of z_resolution
     pub z_resolution: u32,
     pub scale: f32,
     pub aspect_ratio: f32,
     pub colormap_name: String,
-    pub t: f32,  // animation time parameter
+    pub t: T,  // animation time parameter
+    pub height_strategy: RangeStrategy,
+    pub color_strategy: RangeStrategy,
+    pub height_quantity: ComplexQuantity,
+    pub color_quantity: ComplexQuantity,
+    pub branch_cuts: Option<BranchCutOptions>, // split the mesh where f jumps
+}
+
+// samples of f on the grid, in T
+struct GridSamples<T> {
+    pts: Vec<Vec<[T; 3]>>, // [x, height, z]
+    cps: Vec<Vec<T>>,      // color values
+    fzs: Vec<Complex<T>>,  // f at every vertex, in mesh order
 }
 
-impl Default for IComplex3D {
+impl<T: Real> Default for IComplex3D<T> {
     fn default() -> Self {
         Self {
             func_selection: 0,
-            xmin: -2.0,
-            xmax: 2.0,
-            zmin: -2.0,
-            zmax: 2.0,
+            func_expr: None,
+            domain: None,
             x_resolution: 101,
             z_resolution: 101,
             scale: 1.0,
             aspect_ratio: 1.0,
             colormap_name: "jet".to_string(),
-            t: 0.0,
+            t: T::zero(),
+            height_strategy: RangeStrategy::Raw,
+            color_strategy: RangeStrategy::Raw,
+            height_quantity: ComplexQuantity::Modulus,
+            color_quantity: ComplexQuantity::Arg,
+            branch_cuts: None,
         }
     }
 }
 
-impl IComplex3D {
+impl<T: Real> IComplex3D<T> {
     pub fn new() -> Self {
         Default::default()
     }
 
+    pub fn builder() -> IComplex3DBuilder<T> {
+        IComplex3DBuilder { c3d: Self::new(), func_expr: None, domain: None }
+    }
+
+    pub fn set_func_expr(&mut self, expr: &str) -> Result<(), ParseError> {
+        self.func_expr = Some(complex_expr::parse(expr)?);
+        Ok(())
+    }
+
+    // domain the surface is sampled on, [xmin, xmax, zmin, zmax]
+    pub fn domain(&self) -> [T; 4] {
+        self.domain.unwrap_or_else(|| self.default_domain())
+    }
+
+    // an invalid domain is rejected and the current one kept
+    pub fn set_domain(&mut self, domain: [T; 4]) -> Result<(), DomainError> {
+        self.domain = Some(check_domain(domain)?);
+        Ok(())
+    }
+
+    // the catalog domain of the selected function, or [-2, 2]^2 for expressions
+    pub fn default_domain(&self) -> [T; 4] {
+        let d = match (&self.func_expr, complex_catalog::get_complex_function(self.func_selection)) {
+            (None, Some(f)) => f.domain,
+            _ => [-2.0, 2.0, -2.0, 2.0],
+        };
+        d.map(|v| T::from(v).unwrap())
+    }
+
+    pub fn use_default_domain(&mut self) {
+        self.domain = None;
+    }
+
+    pub fn uses_default_domain(&self) -> bool {
+        self.domain.is_none()
+    }
+
+    pub fn pan(&mut self, fx: T, fz: T) -> Result<(), DomainError> {
+        self.set_domain(pan_domain(self.domain(), fx, fz))
+    }
+
+    pub fn zoom(&mut self, factor: T) -> Result<(), DomainError> {
+        self.set_domain(zoom_domain(self.domain(), factor))
+    }
+
     pub fn create_complex_data(&mut self) -> IComplex3DOutput {
         let mut positions:Vec<[f32; 3]> = vec![];
         let mut colors:Vec<[f32; 3]> = vec![];
-        let cdr = self.complex_data_range();
+        let d = self.domain();
+        let GridSamples { pts, cps, fzs } = self.complex_data_range(d);
+
+        let heights: Vec<T> = pts.iter().flatten().map(|pt| pt[1]).collect();
+        let cvalues: Vec<T> = cps.iter().flatten().copied().collect();
+        let yr = self.height_strategy.range(&heights);
+        let cr = self.color_strategy.range(&cvalues);
 
+        if self.color_quantity == ComplexQuantity::Arg {
+            colormap::warn_if_not_cyclic(&self.colormap_name);
+        }
         let cdata = colormap::colormap_data(&self.colormap_name);
 
         for i in 0..=self.x_resolution as usize{
             for j in 0..=self.z_resolution as usize {
-                positions.push(cdr.0[i][j]);
-                let color = colormap::color_lerp(cdata, cdr.2[0], cdr.2[1], cdr.1[i][j]);
+                positions.push(self.normalize_point(d, pts[i][j], yr[0], yr[1]));
+                let color = colormap::color_lerp(cdata, cr[0], cr[1], cps[i][j]);
                 colors.push(color);
             }
         }
@@ -69,113 +399,793 @@ impl IComplex3D {
                 let idx0 = j + i * vertices_per_row;
                 let idx1 = j + 1 + i * vertices_per_row;
                 let idx2 = j + 1 + (i + 1) * vertices_per_row;
-                let idx3 = j + (i + 1) * vertices_per_row; 
+                let idx3 = j + (i + 1) * vertices_per_row;
 
                 let values = vec![idx0, idx1, idx2, idx2, idx3, idx0];
                 indices.extend(values);
             }
         }
-        IComplex3DOutput { positions, colors, indices }
+        let mut out = IComplex3DOutput {
+            positions,
+            colors,
+            indices,
+            height_strategy: self.height_strategy,
+            height_range: [yr[0].to_f64().unwrap(), yr[1].to_f64().unwrap()],
+            color_strategy: self.color_strategy,
+            color_range: [cr[0].to_f64().unwrap(), cr[1].to_f64().unwrap()],
+            cut_lines: vec![],
+        };
+        if let Some(opts) = &self.branch_cuts {
+            let values: Vec<Complex<f64>> = fzs.iter()
+                .map(|fz| Complex::new(fz.re.to_f64().unwrap_or(f64::NAN), fz.im.to_f64().unwrap_or(f64::NAN)))
+                .collect();
+            branch_cut::split_branch_cuts(&mut out, &values, opts, 2.0 * self.scale * self.aspect_ratio);
+        }
+        out
     }
 
+    // height and color ranges of create_complex_data, without building the mesh; the GPU surface
+    // takes them for expressions, which have no catalog ranges
+    pub fn ranges(&self) -> ([f64; 2], [f64; 2]) {
+        let GridSamples { pts, cps, .. } = self.complex_data_range(self.domain());
+        let heights: Vec<T> = pts.iter().flatten().map(|pt| pt[1]).collect();
+        let cvalues: Vec<T> = cps.iter().flatten().copied().collect();
+        let yr = self.height_strategy.range(&heights);
+        let cr = self.color_strategy.range(&cvalues);
+        (yr.map(|v| v.to_f64().unwrap()), cr.map(|v| v.to_f64().unwrap()))
+    }
 
-    fn complex_data_range(&mut self) -> (Vec<Vec<[f32;3]>>, Vec<Vec<f32>>, [f32; 2]) {
-        let dx = (self.xmax - self.xmin)/self.x_resolution as f32;
-        let dz = (self.zmax - self.zmin)/self.z_resolution as f32;
+    // grid points [x, height, z] and color values, in T
+    fn complex_data_range(&self, d: [T; 4]) -> GridSamples<T> {
+        let dx = (d[1] - d[0])/T::from(self.x_resolution).unwrap();
+        let dz = (d[3] - d[2])/T::from(self.z_resolution).unwrap();
+        let derivative = self.height_quantity == ComplexQuantity::Derivative
+            || self.color_quantity == ComplexQuantity::Derivative;
+        let h = T::from(1e-3).unwrap() * (d[1] - d[0]);
 
-        let (mut cmin, mut cmax) = (std::f32::MAX, std::f32::MIN);
-        let (mut ymin, mut ymax) = (std::f32::MAX, std::f32::MIN);
+        let mut pts:Vec<Vec<[T;3]>> = vec![];
+        let mut cps:Vec<Vec<T>> = vec![];
+        let mut fzs:Vec<Complex<T>> = vec![];
 
-        let mut pts:Vec<Vec<[f32;3]>> = vec![];
-        let mut cps:Vec<Vec<f32>> = vec![];
-       
         for i in 0..=self.x_resolution {
-            let x = self.xmin + dx * i as f32;
-            let mut pt1:Vec<[f32; 3]> = vec![];
-            let mut cp1:Vec<f32> = vec![];
+            let x = d[0] + dx * T::from(i).unwrap();
+            let mut pt1:Vec<[T; 3]> = vec![];
+            let mut cp1:Vec<T> = vec![];
             for j in 0..=self.z_resolution {
-                let z = self.zmin + dz * j as f32;
-                let pt = self.complex_func(x, z);
-                //let pt = pp.0;
-                pt1.push(pt.0);
-                cp1.push(pt.1[1]);
-
-                ymin = if pt.0[1] < ymin { pt.0[1] } else { ymin };
-                ymax = if pt.0[1] > ymax { pt.0[1] } else { ymax };
-                cmin = if pt.1[1] < cmin { pt.1[1] } else { cmin };
-                cmax = if pt.1[1] > cmax { pt.1[1] } else { cmax };
+                let z = d[2] + dz * T::from(j).unwrap();
+                let fz = self.complex_func(x, z);
+                let dfz = if derivative {
+                    (self.complex_func(x + h, z) - self.complex_func(x - h, z)) / (h + h)
+                } else {
+                    Complex::new(T::zero(), T::zero())
+                };
+                pt1.push([x, self.height_quantity.value(fz, dfz), z]);
+                cp1.push(self.color_quantity.value(fz, dfz));
+                fzs.push(fz);
             }
             pts.push(pt1);
             cps.push(cp1);
         }
+        GridSamples { pts, cps, fzs }
+    }
+
+    // Iso-lines of quantity at multiples of spacing, extracted by marching squares from the grid of
+    // create_complex_data and lying on the surface; for a flat overlay over a domain coloring image
+    // drop the height. Phase lines stop at the wrap of arg f, and no line crosses a pole.
+    pub fn create_contour_lines(&self, quantity: ContourQuantity, spacing: f64) -> Vec<ContourLine> {
+        let d = self.domain();
+        let GridSamples { pts, fzs, .. } = self.complex_data_range(d);
+        let heights: Vec<T> = pts.iter().flatten().map(|pt| pt[1]).collect();
+        let yr = self.height_strategy.range(&heights);
+        let positions: Vec<[f32; 3]> = pts.iter().flatten().map(|&pt| self.normalize_point(d, pt, yr[0], yr[1])).collect();
+
+        let field = |fz: &Complex<T>| -> f64 {
+            let v = match quantity {
+                ContourQuantity::Modulus => fz.norm().log2(),
+                ContourQuantity::Arg => fz.arg(),
+                ContourQuantity::Re => fz.re,
+                ContourQuantity::Im => fz.im,
+            };
+            v.to_f64().unwrap_or(f64::NAN)
+        };
+        let values: Vec<f64> = fzs.iter().map(field).collect();
+
+        let spacing = spacing.abs();
+        let mut lines: Vec<ContourLine> = vec![];
+        let mut extract = |values: &[f64], level: f64, max_jump: f64, label: f64| {
+            for line in contour::marching_squares(values, self.x_resolution, self.z_resolution, level, max_jump) {
+                let points = line.iter().map(|p| p.lerp(positions[p.a], positions[p.b])).collect();
+                lines.push(ContourLine { level: label, points });
+            }
+        };
+        if spacing == 0.0 || spacing.is_nan() {
+            return lines;
+        }
+        if quantity == ContourQuantity::Arg {
+            // arg f - level wrapped into [-pi, pi), crossed only where it changes sign without wrapping
+            let pi = std::f64::consts::PI;
+            let n = ((2.0 * pi / spacing).ceil() as usize).min(MAX_CONTOUR_LEVELS);
+            for k in 0..n {
+                let level = k as f64 * spacing;
+                let level = if level >= pi { level - 2.0 * pi } else { level };
+                let wrapped: Vec<f64> = values.iter().map(|v| (v - level + pi).rem_euclid(2.0 * pi) - pi).collect();
+                extract(&wrapped, 0.0, pi, level);
+            }
+        } else {
+            let r = RangeStrategy::Raw.range(&values);
+            let k0 = (r[0] / spacing).ceil() as i64;
+            let k1 = ((r[1] / spacing).floor() as i64).min(k0 + MAX_CONTOUR_LEVELS as i64 - 1);
+            for k in k0..=k1 {
+                let level = k as f64 * spacing;
+                let label = if quantity == ContourQuantity::Modulus { level.exp2() } else { level };
+                extract(&values, level, f64::INFINITY, label);
+            }
+        }
+        lines
+    }
 
-        for i in 0..=self.x_resolution as usize {
-            for j in 0..=self.z_resolution as usize {
-                pts[i][j] = self.normalize_point(pts[i][j], ymin, ymax);
+    // heights outside [ymin, ymax] are clipped, which flattens poles to the top of the range
+    fn normalize_point(&self, d:[T; 4], pt:[T; 3], ymin:T, ymax:T) -> [f32; 3] {
+        let one = T::one();
+        let two = T::from(2.0).unwrap();
+        let scale = T::from(self.scale).unwrap();
+        let aspect_ratio = T::from(self.aspect_ratio).unwrap();
+        let y = if pt[1].is_nan() { ymin } else { pt[1].max(ymin).min(ymax) };
+        let yn = if ymax > ymin { (y - ymin) / (ymax - ymin) } else { T::zero() };
+        let x = scale * (-one + two * (pt[0] - d[0]) / (d[1] - d[0]));
+        let y = scale * (-one + two * yn) * aspect_ratio;
+        let z = scale * (-one + two * (pt[2] - d[2]) / (d[3] - d[2]));
+        [x.to_f32().unwrap_or(f32::NAN), y.to_f32().unwrap_or(f32::NAN), z.to_f32().unwrap_or(f32::NAN)]
+    }
+
+    // The whole Riemann sphere with radius scale, on the u x v grid of vertex_data::create_sphere_data
+    // but with u32 indices, so that it is not limited to 65536 vertices. A
+    // vertex at polar angle theta (from +y) and azimuth phi is mapped by inverse stereographic
+    // projection to z = cot(theta/2) e^(-i phi), so the south pole is 0 and the north pole is infinity.
+    // The northern hemisphere is evaluated as f(1/u) with u = tan(theta/2) e^(i phi), and at u = 0
+    // the point at infinity is approached along the direction of the vertex. With displacement > 0 the
+    // radius grows by displacement * |f|/(1 + |f|), which stays finite at poles.
+    pub fn create_riemann_sphere_data(&self, u: u32, v: u32, coloring: SphereColoring, displacement: f32)
+    -> IComplex3DOutput {
+        let (unit_positions, uvs, indices) = sphere_grid(u, v);
+        if coloring == SphereColoring::Colormap {
+            colormap::warn_if_not_cyclic(&self.colormap_name);
+        }
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let half = T::from(0.5).unwrap();
+        let pi = T::PI();
+
+        let mut positions:Vec<[f32; 3]> = vec![];
+        let mut colors:Vec<[f32; 3]> = vec![];
+        let (mut fmin, mut fmax) = (f64::MAX, f64::MIN);
+        for (pos, uv) in unit_positions.iter().zip(uvs.iter()) {
+            let theta = pi * T::from(uv[0]).unwrap();
+            let phi = T::from(2.0).unwrap() * pi * T::from(uv[1]).unwrap();
+            let fz = if theta < half * pi {
+                let r = (half * theta).tan().max(T::epsilon());
+                self.complex_func_at_infinity(Complex::from_polar(r, phi))
+            } else {
+                let z = Complex::from_polar(T::one() / (half * theta).tan(), -phi);
+                self.complex_func(z.re, z.im)
+            };
+
+            let m = fz.norm().to_f64().unwrap_or(f64::NAN);
+            if m.is_finite() {
+                fmin = fmin.min(m);
+                fmax = fmax.max(m);
             }
+            let s = if m.is_nan() { 0.0 } else if m.is_infinite() { 1.0 } else { m / (1.0 + m) };
+            let r = self.scale * (1.0 + displacement * s as f32);
+            positions.push([r * pos[0], r * pos[1], r * pos[2]]);
+
+            let w = complex_func::Vec2::new(fz.re.to_f32().unwrap_or(f32::NAN), fz.im.to_f32().unwrap_or(f32::NAN));
+            let c = match coloring {
+                SphereColoring::Hsv => complex_func::hsv2_rgb(w),
+                SphereColoring::Colormap => complex_func::colormap2_rgb(w, &cdata),
+            };
+            // log2|f| is undefined at zeros and poles: black at zeros, white at poles
+            let c = if c.iter().all(|x| x.is_finite()) { [c[0], c[1], c[2]] }
+                else if m.is_nan() { [0.5, 0.5, 0.5] } else if m < 1.0 { [0.0, 0.0, 0.0] } else { [1.0, 1.0, 1.0] };
+            colors.push(c);
+        }
+
+        IComplex3DOutput {
+            positions,
+            colors,
+            indices,
+            height_strategy: RangeStrategy::Raw,
+            height_range: [fmin, fmax],
+            color_strategy: RangeStrategy::Fixed(-std::f64::consts::PI, std::f64::consts::PI),
+            color_range: [-std::f64::consts::PI, std::f64::consts::PI],
+            cut_lines: vec![],
         }
+    }
+
+    // f(1/u) for the neighbourhood of infinity
+    fn complex_func_at_infinity(&self, u: Complex<T>) -> Complex<T> {
+        let z = u.inv();
+        self.complex_func(z.re, z.im)
+    }
+
+    // zeros and poles of the current function inside the current domain, evaluated in f64
+    pub fn zeros_poles(&self, opts: &ZeroPoleOptions) -> Vec<ZeroPole> {
+        let d = self.domain().map(|v| v.to_f64().unwrap());
+        complex_zeros::find_zeros_poles(self.complex_func64(), d, opts)
+    }
+
+    // Riemann surface of the n-th root or the logarithm of the current function, over the current
+    // domain and with the resolution, scale and colormap of the surface
+    pub fn create_riemann_surface_data(&self, branch: Multivalued, height: SheetHeight) -> IComplex3DOutput {
+        let rs = RiemannSurface {
+            branch,
+            height,
+            domain: self.domain().map(|v| v.to_f64().unwrap()),
+            x_resolution: self.x_resolution,
+            z_resolution: self.z_resolution,
+            scale: self.scale,
+            aspect_ratio: self.aspect_ratio,
+            colormap_name: self.colormap_name.clone(),
+        };
+        rs.create_surface_data(self.complex_func64())
+    }
+
+    // marker positions in the normalized coordinates of create_complex_data: zeros on the bottom and
+    // poles on the top of the bounding box of the surface
+    pub fn zero_pole_markers(&self, zp: &[ZeroPole]) -> Vec<[f32; 3]> {
+        let d = self.domain().map(|v| v.to_f64().unwrap());
+        let scale = self.scale as f64;
+        zp.iter().map(|p| {
+            let x = scale * (-1.0 + 2.0 * (p.location.re - d[0]) / (d[1] - d[0]));
+            let z = scale * (-1.0 + 2.0 * (p.location.im - d[2]) / (d[3] - d[2]));
+            let y = if p.kind == ZeroPoleKind::Zero { -scale } else { scale } * self.aspect_ratio as f64;
+            [x as f32, y as f32, z as f32]
+        }).collect()
+    }
+
+    // images of a grid over the current domain under the current function, at the current t
+    pub fn create_conformal_grid(&self, opts: &ConformalGridOptions) -> Vec<GridCurve> {
+        let d = self.domain().map(|v| v.to_f64().unwrap());
+        conformal_grid::map_grid(self.complex_func64(), d, self.scale, opts)
+    }
 
-        (pts, cps, [cmin, cmax])
-    } 
+    // arrows of the Polya field conj f over the current domain, colored by |f| with the colormap of
+    // the surface; positions, colors and triangle indices
+    pub fn create_polya_arrows(&self, opts: &ArrowOptions) -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u32>) {
+        let d = self.domain().map(|v| v.to_f64().unwrap());
+        polya_field::create_arrow_data(self.complex_func64(), d, self.scale, &self.colormap_name, opts)
+    }
+
+    // streamlines of the Polya field; positions, colors and line list indices
+    pub fn create_polya_streamlines(&self, opts: &StreamlineOptions) -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u32>) {
+        let d = self.domain().map(|v| v.to_f64().unwrap());
+        polya_field::create_streamline_data(self.complex_func64(), d, self.scale, &self.colormap_name, opts)
+    }
+
+    // Taylor series of the current function around center, from samples on |z - center| = radius
+    pub fn taylor_series(&self, center: Complex<f64>, radius: f64, samples: usize) -> LaurentSeries {
+        laurent_series::taylor_series(self.complex_func64(), center, radius, samples)
+    }
+
+    // Taylor series of the current function around center, sampled on half the estimated radius of
+    // convergence but at most max_radius away
+    pub fn taylor_series_within(&self, center: Complex<f64>, max_radius: f64, samples: usize) -> LaurentSeries {
+        laurent_series::taylor_series_within(self.complex_func64(), center, max_radius, samples)
+    }
+
+    // Laurent series of the current function on the annulus r0 < |z - center| < r1
+    pub fn laurent_series(&self, center: Complex<f64>, annulus: [f64; 2], samples: usize) -> LaurentSeries {
+        laurent_series::laurent_series(self.complex_func64(), center, annulus, samples)
+    }
 
-    fn normalize_point(&mut self, pt:[f32; 3], ymin:f32, ymax:f32) -> [f32; 3] {
-        let mut pt1 = [0f32; 3];
-        pt1[0] = self.scale * (-1.0 + 2.0 * (pt[0] - self.xmin) / (self.xmax - self.xmin));
-        pt1[1] = self.scale * (-1.0 + 2.0 * (pt[1] - ymin) / (ymax - ymin)) * self.aspect_ratio;
-        pt1[2] = self.scale * (-1.0 + 2.0 * (pt[2] - self.zmin) / (self.zmax - self.zmin));
-        pt1
+    // integral of the current function along contour, at the current t
+    pub fn contour_integral(&self, contour: &Contour, opts: &IntegrationOptions) -> ContourIntegral {
+        contour.integrate(self.complex_func64(), opts)
     }
 
-    fn complex_func(&mut self, x:f32, y:f32) -> ([f32; 3], [f32; 3]) {
+    // residues of the current function at points, each from a circle of the given radius
+    pub fn residues(&self, points: &[Complex<f64>], radius: f64, opts: &IntegrationOptions) -> Vec<ContourIntegral> {
+        contour_integral::residues(self.complex_func64(), points, radius, opts)
+    }
+
+    // contour as a line list lying on the surface of create_complex_data, whose height_range is passed
+    // in so that the path and the surface are normalized alike
+    pub fn create_contour_path(&self, contour: &Contour, height_range: [f64; 2], samples: usize) -> Vec<[f32; 3]> {
+        let d = self.domain();
+        let f = self.complex_func64();
+        let h = 1e-3 * (d[1] - d[0]).to_f64().unwrap();
+        let derivative = self.height_quantity == ComplexQuantity::Derivative;
+        let ymin = T::from(height_range[0]).unwrap();
+        let ymax = T::from(height_range[1]).unwrap();
+        let points: Vec<[f32; 3]> = contour.polyline(samples).iter().map(|&z| {
+            let fz = f(z);
+            let dfz = if derivative {
+                (f(z + h) - f(z - h)) / (2.0 * h)
+            } else {
+                Complex::new(0.0, 0.0)
+            };
+            let y = self.height_quantity.value(fz, dfz);
+            let pt = [T::from(z.re).unwrap(), T::from(y).unwrap_or(T::nan()), T::from(z.im).unwrap()];
+            self.normalize_point(d, pt, ymin, ymax)
+        }).collect();
+        points.windows(2).flat_map(|w| [w[0], w[1]]).collect()
+    }
+
+    // the current function evaluated in f64, whatever T is
+    fn complex_func64(&self) -> impl Fn(Complex<f64>) -> Complex<f64> + '_ {
+        let t = self.t.to_f64().unwrap();
+        let catalog = complex_catalog::get_complex_function(self.func_selection);
+        move |z| match (&self.func_expr, catalog) {
+            (Some(expr), _) => expr.eval(z, t),
+            (None, Some(f)) => f.eval(z, t),
+            (None, None) => z,
+        }
+    }
+
+    fn complex_func(&self, x:T, y:T) -> Complex<T> {
         let z = Complex::new(x, y);
-        let mut fz = z;
-        
-        let func_select = self.func_selection;
         let t = self.t;
 
-        if func_select == 0 {
-            fz = (z - t)/(z*z + z + t);
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-3.0, 2.0, -2.0, 2.0); 
-        } else if func_select == 1 {
-            let f1 = Complex::new(-z.im - 3.0 * t, z.re);
-            let f2 = Complex::new(-z.im + t, z.re);
-            fz = (f1.ln()/f2.ln()).sqrt();
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-6.0, 6.0, -6.0, 6.0);
-        } else if func_select == 2 {
-            fz = t * (t * z).sin();
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-6.0, 6.0, -6.0, 6.0);
-        } else if func_select == 3 {
-            fz = (0.5 + t) * ((0.5 + t) * z).tan().tan();
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-10.0, 10.0, -1.0, 1.0);
-        } else if func_select == 4 {
-            fz = t * ((0.5 + t) *z).sin().tan();
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-8.0, 8.0, -2.0, 2.0);
-        } else if func_select == 5 {
-            let f1 = Complex::new(t + z.re, z.im);
-            let f2 = Complex::new(t - z.re, -z.im);
-            fz = f1.sqrt() + f2.sqrt();
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-2.0, 2.0, -2.0, 2.0);
-        } else if func_select == 6 {
-            fz = ((0.5 + t) * z).exp().tan()/z;
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-1.0, 2.0, -1.0, 1.0);
-        } else if func_select == 7 {
-            fz = ((0.5 + t) * z).sin().cos().sin()/(z*z - t);
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-2.0, 2.0, -1.0, 1.0);
-        } else if func_select == 8 {
-            let f1 = 0.5 + t;
-            let f2 = 1.0 + ((0.5 + t)*z).powi(5);
-            fz = f1/f2;
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-1.0, 1.0, -1.0, 1.0);
-        } else if func_select == 9 {
-            let f1 = ((0.5 + t) * z).sin();
-            let f2 = ((0.5 + t) * z).exp().cos() *(z * z - (0.5 + t)*(0.5 + t));
-            fz = f1/f2;
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-4.0, 6.0, -2.0, 2.0);
-        } else if func_select == 10 {
-            fz = 1.0/(z + t) + 1.0/(z - t);
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-2.0, 2.0, -2.0, 2.0);
-        }
-
-        ([x, fz.abs(), y], [x, fz.arg(), y])
-    }
-}
\ No newline at end of file
+        if let Some(expr) = &self.func_expr {
+            expr.eval(z, t)
+        } else if let Some(f) = complex_catalog::get_complex_function(self.func_selection) {
+            f.eval(z, t)
+        } else {
+            z
+        }
+    }
+}
+
+pub struct IComplex3DBuilder<T: Real = f32> {
+    c3d: IComplex3D<T>,
+    func_expr: Option<String>,
+    domain: Option<[T; 4]>,
+}
+
+impl<T: Real> IComplex3DBuilder<T> {
+    pub fn function(mut self, selection: u32) -> Self {
+        self.c3d.func_selection = selection;
+        self.c3d.func_expr = None;
+        self.func_expr = None;
+        self
+    }
+
+    // parsed by build()
+    pub fn expr(mut self, expr: &str) -> Self {
+        self.func_expr = Some(expr.to_string());
+        self
+    }
+
+    // the Mobius map animated from the identity at t = 0 to the map at t = 1
+    pub fn mobius(mut self, m: &Mobius) -> Self {
+        self.c3d.func_expr = Some(m.animated_expr());
+        self.func_expr = None;
+        self
+    }
+
+    // checked by build()
+    pub fn domain(mut self, domain: [T; 4]) -> Self {
+        self.domain = Some(domain);
+        self
+    }
+
+    pub fn default_domain(mut self) -> Self {
+        self.domain = None;
+        self
+    }
+
+    pub fn resolution(mut self, x_resolution: u32, z_resolution: u32) -> Self {
+        self.c3d.x_resolution = x_resolution;
+        self.c3d.z_resolution = z_resolution;
+        self
+    }
+
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.c3d.scale = scale;
+        self
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: f32) -> Self {
+        self.c3d.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn colormap(mut self, colormap_name: &str) -> Self {
+        self.c3d.colormap_name = colormap_name.to_string();
+        self
+    }
+
+    pub fn t(mut self, t: T) -> Self {
+        self.c3d.t = t;
+        self
+    }
+
+    pub fn height(mut self, quantity: ComplexQuantity, strategy: RangeStrategy) -> Self {
+        self.c3d.height_quantity = quantity;
+        self.c3d.height_strategy = strategy;
+        self
+    }
+
+    pub fn color(mut self, quantity: ComplexQuantity, strategy: RangeStrategy) -> Self {
+        self.c3d.color_quantity = quantity;
+        self.c3d.color_strategy = strategy;
+        self
+    }
+
+    pub fn build(mut self) -> Result<IComplex3D<T>, BuildError> {
+        if let Some(expr) = &self.func_expr {
+            self.c3d.set_func_expr(expr)?;
+        }
+        if let Some(domain) = self.domain {
+            self.c3d.set_domain(domain)?;
+        }
+        Ok(self.c3d)
+    }
+}
+
+// unit sphere positions, uvs and triangle indices in the layout of vertex_data::create_sphere_data
+fn sphere_grid(u: u32, v: u32) -> (Vec<[f32; 3]>, Vec<[f32; 2]>, Vec<u32>) {
+    let mut positions = vec![];
+    let mut uvs = vec![];
+    for i in 0..=u {
+        for j in 0..=v {
+            let theta = std::f32::consts::PI * i as f32 / u as f32;
+            let phi = 2.0 * std::f32::consts::PI * j as f32 / v as f32;
+            positions.push([theta.sin() * phi.cos(), theta.cos(), -theta.sin() * phi.sin()]);
+            uvs.push([i as f32 / u as f32, j as f32 / v as f32]);
+        }
+    }
+    let mut indices = vec![];
+    for i in 0..u {
+        for j in 0..v {
+            let idx0 = j + i * (v + 1);
+            let idx1 = j + 1 + i * (v + 1);
+            let idx2 = j + 1 + (i + 1) * (v + 1);
+            let idx3 = j + (i + 1) * (v + 1);
+            indices.extend([idx0, idx1, idx2, idx2, idx3, idx0]);
+        }
+    }
+    (positions, uvs, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // distinct surface heights along the real axis for |z^2 - 1| on a 1e-6 wide domain at z = 1
+    fn distinct_heights<T: Real>() -> usize {
+        let mut c3d = IComplex3D::<T>::new();
+        c3d.set_func_expr("z^2 - 1").unwrap();
+        c3d.set_domain([1.0, 1.0 + 1e-6, 0.0, 1e-6].map(|v| T::from(v).unwrap())).unwrap();
+        let out = c3d.create_complex_data();
+        let mut ys: Vec<f32> = out.positions.iter().step_by(c3d.z_resolution as usize + 1).map(|p| p[1]).collect();
+        ys.dedup();
+        ys.len()
+    }
+
+    #[test]
+    fn f64_resolves_tight_domains() {
+        let (n32, n64) = (distinct_heights::<f32>(), distinct_heights::<f64>());
+        assert!(n32 < 50, "f32 gave {} distinct heights", n32);
+        assert_eq!(n64, 102);
+    }
+
+    #[test]
+    fn range_strategies() {
+        let mut v: Vec<f64> = (0..100).map(|k| k as f64).collect();
+        v.push(1e6);
+        v.push(f64::INFINITY);
+        v.push(f64::NAN);
+
+        assert_eq!(RangeStrategy::Raw.range(&v), [0.0, 1e6]);
+        assert_eq!(RangeStrategy::Fixed(-1.0, 2.0).range(&v), [-1.0, 2.0]);
+        let p = RangeStrategy::Percentile(0.0, 99.0).range(&v);
+        assert_eq!(p[0], 0.0);
+        assert!(p[1] > 98.0 && p[1] < 100.0, "{:?}", p);
+        let m = RangeStrategy::Mad(3.0).range(&v);
+        assert_eq!(m[0], 0.0);
+        assert!(m[1] > 150.0 && m[1] < 300.0, "{:?}", m);
+
+        // a constant signal has no spread: every strategy widens it around the value
+        for strategy in [RangeStrategy::Raw, RangeStrategy::Percentile(1.0, 99.0), RangeStrategy::Mad(3.0)] {
+            assert_eq!(strategy.range(&[2.0f32; 5]), [1.5, 2.5]);
+        }
+        assert_eq!(RangeStrategy::Raw.range::<f32>(&[]), [0.0, 1.0]);
+        assert_eq!(RangeStrategy::Fixed(2.0, 2.0).range(&v), [1.5, 2.5]);
+        assert_eq!(RangeStrategy::Fixed(2.0, -1.0).range(&v), [-1.0, 2.0]);
+    }
+
+    #[test]
+    fn constant_quantity_gets_finite_colors() {
+        // z conj(z) is real and positive: arg f is 0 everywhere
+        let mut c3d = IComplex3D::<f64>::builder().expr("z*conj(z)").resolution(20, 20).build().unwrap();
+        let out = c3d.create_complex_data();
+        assert_eq!(out.color_range, [-0.5, 0.5]);
+        assert!(out.colors.iter().flatten().all(|c| c.is_finite()));
+
+        c3d.height_strategy = RangeStrategy::Fixed(1.0, 1.0);
+        let out = c3d.create_complex_data();
+        assert_eq!(out.height_range, [0.5, 1.5]);
+        assert!(out.positions.iter().flatten().all(|p| p.is_finite()));
+    }
+
+    #[test]
+    fn pole_is_clipped_and_range_reported() {
+        // 1/z on an odd grid over [-2, 2]^2 samples z = 0 exactly
+        let mut c3d = IComplex3D::<f64>::new();
+        c3d.set_func_expr("1/z").unwrap();
+        c3d.x_resolution = 40;
+        c3d.z_resolution = 40;
+
+        let raw = c3d.create_complex_data();
+        assert_eq!(raw.height_strategy, RangeStrategy::Raw);
+        assert!(raw.height_range[1] > 5.0 && raw.height_range[1].is_finite());
+
+        c3d.height_strategy = RangeStrategy::Percentile(1.0, 99.0);
+        let clipped = c3d.create_complex_data();
+        assert_eq!(clipped.height_strategy, RangeStrategy::Percentile(1.0, 99.0));
+        assert!(clipped.height_range[1] < raw.height_range[1]);
+        assert!(clipped.positions.iter().all(|p| p[1].is_finite() && p[1] <= 1.0 + 1e-6));
+        assert_eq!(c3d.ranges(), (clipped.height_range, clipped.color_range));
+
+        // raw normalization squeezes most of the surface against the bottom
+        let mean = |out: &IComplex3DOutput| out.positions.iter().map(|p| p[1] as f64).sum::<f64>()
+            / out.positions.len() as f64;
+        assert!(mean(&clipped) > mean(&raw));
+
+        c3d.color_strategy = RangeStrategy::Fixed(-std::f64::consts::PI, std::f64::consts::PI);
+        assert_eq!(c3d.create_complex_data().color_range, [-std::f64::consts::PI, std::f64::consts::PI]);
+    }
+
+    #[test]
+    fn height_and_color_quantities() {
+        let mut c3d = IComplex3D::<f64>::new();
+        c3d.set_func_expr("z^2").unwrap();
+        c3d.x_resolution = 4;
+        c3d.z_resolution = 4;
+        let n = c3d.z_resolution as usize + 1;
+
+        // Re z^2 = x^2 - y^2 is a saddle: equal heights at (±2, 0), lowest at (0, ±2)
+        c3d.height_quantity = ComplexQuantity::Re;
+        let out = c3d.create_complex_data();
+        assert_eq!(out.height_range, [-4.0, 4.0]);
+        assert_eq!(out.positions[2][1], out.positions[4 * n + 2][1]);
+        assert_eq!(out.positions[2 * n][1], -1.0);
+
+        // |(z^2)'| = 2|z|, compared to the modulus through the reported ranges
+        c3d.height_quantity = ComplexQuantity::Modulus;
+        c3d.color_quantity = ComplexQuantity::Derivative;
+        let out = c3d.create_complex_data();
+        assert!((out.color_range[1] - 4.0 * 2f64.sqrt()).abs() < 1e-6);
+        assert!(out.color_range[0].abs() < 1e-9);
+
+        c3d.height_quantity = ComplexQuantity::LogModulus;
+        assert!(c3d.create_complex_data().positions.iter().all(|p| p[1].is_finite()));
+
+        for (k, q) in ComplexQuantity::ALL.iter().enumerate() {
+            assert_eq!(q.id(), k as u32);
+        }
+    }
+
+    #[test]
+    fn domain_is_kept_and_grid_may_be_non_square() {
+        let mut c3d = IComplex3D::<f64>::builder()
+            .function(0)
+            .domain([-1.0, 1.0, 0.0, 0.5])
+            .resolution(8, 2)
+            .scale(2.0)
+            .build()
+            .unwrap();
+        assert!(!c3d.uses_default_domain());
+
+        let out = c3d.create_complex_data();
+        assert_eq!(c3d.domain(), [-1.0, 1.0, 0.0, 0.5]);
+        assert_eq!(out.positions.len(), 9 * 3);
+        assert_eq!(out.indices.len(), 6 * 8 * 2);
+        assert_eq!((out.positions[0][0], out.positions[0][2]), (-2.0, -2.0));
+        assert_eq!((out.positions[26][0], out.positions[26][2]), (2.0, 2.0));
+        assert_eq!(out.positions[3][0], -1.5); // second column, x = -0.75
+
+        c3d.zoom(0.5).unwrap();
+        c3d.pan(0.5, -1.0).unwrap();
+        assert_eq!(c3d.domain(), [0.0, 1.0, -0.125, 0.125]);
+
+        // rejected domains leave the current one in place
+        for d in [[1.0, 1.0, 0.0, 1.0], [1.0, -1.0, 0.0, 1.0], [0.0, 1.0, f64::NAN, 1.0], [0.0, f64::INFINITY, 0.0, 1.0]] {
+            assert!(c3d.set_domain(d).is_err());
+        }
+        assert!(c3d.zoom(0.0).is_err());
+        assert_eq!(c3d.domain(), [0.0, 1.0, -0.125, 0.125]);
+
+        c3d.use_default_domain();
+        assert_eq!(c3d.domain(), [-3.0, 2.0, -2.0, 2.0]);
+        c3d.func_selection = 2;
+        assert_eq!(c3d.domain(), [-6.0, 6.0, -6.0, 6.0]);
+
+        let e = IComplex3D::<f32>::builder().expr("z +* 2").build();
+        assert!(matches!(e, Err(BuildError::Parse(_))));
+        let e = IComplex3D::<f32>::builder().domain([2.0, -2.0, -2.0, 2.0]).build();
+        assert!(matches!(e, Err(BuildError::Domain(_))));
+    }
+
+    #[test]
+    fn zero_pole_markers_on_the_surface_box() {
+        let c3d = IComplex3D::<f32>::builder().expr("(z - 1)/(z + i)").build().unwrap();
+        let zp = c3d.zeros_poles(&ZeroPoleOptions::default());
+        assert_eq!(zp.len(), 2, "{:?}", zp);
+
+        let markers = c3d.zero_pole_markers(&zp);
+        // sorted by location: the pole at -i comes first
+        assert!((markers[0][0] - 0.0).abs() < 1e-6 && markers[0][1] == 1.0 && (markers[0][2] + 0.5).abs() < 1e-6);
+        assert!((markers[1][0] - 0.5).abs() < 1e-6 && markers[1][1] == -1.0 && markers[1][2].abs() < 1e-6);
+    }
+
+    #[test]
+    fn riemann_sphere_covers_infinity() {
+        let (u, v) = (16, 24);
+        let mut c3d = IComplex3D::<f64>::builder().expr("z^2").scale(2.0).build().unwrap();
+        let out = c3d.create_riemann_sphere_data(u, v, SphereColoring::Hsv, 0.5);
+        assert_eq!(out.positions.len(), 17 * 25);
+        assert_eq!(out.indices.len(), 6 * 16 * 24);
+
+        let radius = |p: [f32; 3]| (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+        // f(0) = 0 at the south pole, the double pole of z^2 at infinity on the north pole
+        let south = out.positions.len() - 1;
+        assert!((radius(out.positions[south]) - 2.0).abs() < 1e-6);
+        assert!((radius(out.positions[0]) - 3.0).abs() < 1e-4);
+        assert!(out.positions[0][1] > 0.0 && out.positions[south][1] < 0.0);
+        assert!(out.colors.iter().flatten().all(|c| c.is_finite()));
+
+        // the equator is the unit circle, where |z^2| = 1
+        let equator = 8 * 25 + 3;
+        assert!((radius(out.positions[equator]) - 2.5).abs() < 1e-5);
+
+        // 1/(z + t) + 1/(z - t) vanishes at infinity and at 0 and has its poles on the real axis
+        c3d.set_func_expr("1/(z + t) + 1/(z - t)").unwrap();
+        c3d.t = 0.5;
+        let out = c3d.create_riemann_sphere_data(u, v, SphereColoring::Colormap, 1.0);
+        assert!((radius(out.positions[0]) - 2.0).abs() < 1e-5);
+        assert!((radius(out.positions[south]) - 2.0).abs() < 1e-6);
+        assert!(out.positions.iter().all(|&p| radius(p) <= 4.0 + 1e-4));
+
+        // the example's default 256 x 256 grid has more vertices than u16 indices can address
+        let out = c3d.create_riemann_sphere_data(256, 256, SphereColoring::Hsv, 0.5);
+        assert_eq!(out.positions.len(), 257 * 257);
+        assert_eq!(out.indices.len(), 6 * 256 * 256);
+        assert_eq!(out.indices.iter().max(), Some(&(257 * 257 - 1)));
+
+        // the same layout as vertex_data::create_sphere_data
+        let (positions, _, uvs, indices, _) = crate::vertex_data::create_sphere_data(1.0, 16, 24);
+        let (p, uv, idx) = sphere_grid(16, 24);
+        assert!(positions.iter().zip(&p).all(|(a, b)| (0..3).all(|k| (a[k] - b[k]).abs() < 1e-6)));
+        assert_eq!(uvs, uv);
+        assert!(indices.iter().zip(&idx).all(|(&a, &b)| a as u32 == b));
+    }
+
+    #[test]
+    fn branch_cut_splits_the_mesh() {
+        // sqrt(z) jumps from i*sqrt(x) to -i*sqrt(x) across the negative real axis
+        let mut c3d = IComplex3D::<f64>::builder().expr("sqrt(z)").resolution(20, 21).build().unwrap();
+        c3d.height_quantity = ComplexQuantity::Im;
+        let whole = c3d.create_complex_data();
+        assert_eq!(whole.indices.len(), 6 * 20 * 21);
+
+        c3d.branch_cuts = Some(BranchCutOptions { emit_cut_lines: true, ..Default::default() });
+        let split = c3d.create_complex_data();
+        assert!(split.positions.len() > whole.positions.len());
+        assert_eq!(split.positions.len(), split.colors.len());
+        assert!(split.indices.iter().all(|&i| (i as usize) < split.positions.len()));
+
+        // away from the branch point no triangle bridges the cut any more
+        for tri in split.indices.chunks(3) {
+            let ps: Vec<[f32; 3]> = tri.iter().map(|&i| split.positions[i as usize]).collect();
+            if ps.iter().all(|p| p[0] < -0.2) {
+                let (lo, hi) = ps.iter().fold((f32::MAX, f32::MIN), |(lo, hi), p| (lo.min(p[1]), hi.max(p[1])));
+                assert!(hi - lo < 0.5, "triangle {:?} spans {}", tri, hi - lo);
+            }
+        }
+
+        // the cut lines run along the negative real axis (z = 0 in normalized coordinates)
+        assert!(!split.cut_lines.is_empty() && split.cut_lines.len().is_multiple_of(2));
+        assert!(split.cut_lines.iter().all(|p| p[0] < 0.05 && p[2].abs() < 0.1), "{:?}", split.cut_lines);
+    }
+
+    #[test]
+    fn entire_functions_have_no_branch_cuts() {
+        // f crosses its negative real axis on whole lines here, without jumping
+        for expr in ["z", "z^3 - 1", "exp(z)", "sin(z)"] {
+            for (nx, nz) in [(64, 63), (256, 256)] {
+                let mut c3d = IComplex3D::<f64>::builder().expr(expr).resolution(nx, nz).build().unwrap();
+                c3d.branch_cuts = Some(BranchCutOptions { emit_cut_lines: true, ..Default::default() });
+                let out = c3d.create_complex_data();
+                assert!(out.cut_lines.is_empty(), "{} at {}x{}: {:?}", expr, nx, nz, out.cut_lines);
+                assert_eq!(out.indices.len(), 6 * nx as usize * nz as usize);
+            }
+        }
+
+        // the phase seam of the colormap is opt-in
+        let mut c3d = IComplex3D::<f64>::builder().expr("z").resolution(64, 63).build().unwrap();
+        c3d.branch_cuts = Some(BranchCutOptions { emit_cut_lines: true, phase_seam: true, ..Default::default() });
+        let out = c3d.create_complex_data();
+        assert!(!out.cut_lines.is_empty());
+        assert!(out.cut_lines.iter().all(|p| p[0] < 0.05 && p[2].abs() < 0.1), "{:?}", out.cut_lines);
+    }
+
+    #[test]
+    fn contour_lines_of_the_identity() {
+        let c3d = IComplex3D::<f64>::builder().expr("z").domain([-2.0, 2.0, -2.0, 2.0]).resolution(64, 63)
+            .build().unwrap();
+        let to_z = |p: &[f32; 3]| Complex::new(2.0 * p[0] as f64, 2.0 * p[2] as f64);
+
+        // |z| = 1/2, 1 and 2 inside the square; 2 only touches it at the edge midpoints
+        let lines = c3d.create_contour_lines(ContourQuantity::Modulus, 1.0);
+        for level in [0.5, 1.0] {
+            let circle: Vec<&ContourLine> = lines.iter().filter(|l| l.level == level).collect();
+            assert_eq!(circle.len(), 1);
+            assert_eq!(circle[0].points.first(), circle[0].points.last());
+            assert!(circle[0].points.iter().all(|p| (to_z(p).norm() - level).abs() < 0.02));
+        }
+
+        // twelve rays from the origin, each on its own side of the wrap
+        let lines = c3d.create_contour_lines(ContourQuantity::Arg, ContourQuantity::Arg.default_spacing());
+        assert_eq!(lines.len(), 12);
+        for l in &lines {
+            assert!(l.points.iter().all(|p| {
+                let z = to_z(p);
+                z.norm() < 0.1 || (z * Complex::from_polar(1.0, -l.level)).arg().abs() < 0.02
+            }), "phase line {}", l.level);
+        }
+
+        // Re z = -1.5, -0.75, ..., 1.5 are straight lines across the domain
+        let lines = c3d.create_contour_lines(ContourQuantity::Re, 0.75);
+        assert_eq!(lines.iter().map(|l| l.level).collect::<Vec<_>>(), vec![-1.5, -0.75, 0.0, 0.75, 1.5]);
+        assert!(lines.iter().all(|l| l.points.iter().all(|p| (to_z(p).re - l.level).abs() < 1e-6)));
+        assert_eq!(contour_line_list(&lines).len(), lines.iter().map(|l| 2 * (l.points.len() - 1)).sum::<usize>());
+    }
+
+    #[test]
+    fn taylor_series_follows_f_inside_its_disk() {
+        let builder = || IComplex3D::<f64>::builder().domain([-1.5, 1.5, -1.5, 1.5]).resolution(30, 30)
+            .height(ComplexQuantity::Re, RangeStrategy::Fixed(-4.0, 4.0));
+        let mut f = builder().expr("1/(1 - z) + t").t(0.5).build().unwrap();
+        let series = f.taylor_series(Complex::new(0.0, 0.0), 0.5, 64);
+        assert!((series.convergence_radius() - 1.0).abs() < 1e-3);
+
+        // the truncated series plotted as a formula agrees with f well inside |z| < 1 only
+        let mut g = builder().build().unwrap();
+        g.func_expr = Some(series.truncated(0, 24).to_expr());
+        let (pf, pg) = (f.create_complex_data().positions, g.create_complex_data().positions);
+        let mut outside = 0.0f32;
+        for (a, b) in pf.iter().zip(&pg) {
+            let r = 1.5 * (a[0] * a[0] + a[2] * a[2]).sqrt();
+            if r < 0.6 {
+                assert!((a[1] - b[1]).abs() < 1e-4, "{:?} {:?}", a, b);
+            } else if r > 1.2 {
+                outside = outside.max((a[1] - b[1]).abs());
+            }
+        }
+        assert!(outside > 0.1);
+    }
+
+    #[test]
+    fn contour_integral_finds_the_pole_and_path_lies_on_the_surface() {
+        let mut f = IComplex3D::<f64>::builder().expr("1/(1 - z) + t").domain([-2.0, 2.0, -2.0, 2.0])
+            .resolution(40, 40).height(ComplexQuantity::Re, RangeStrategy::Fixed(-4.0, 4.0)).t(0.5).build().unwrap();
+        let opts = IntegrationOptions::default();
+        let circle = Contour::circle(Complex::new(1.0, 0.0), 0.5);
+        let integral = f.contour_integral(&circle, &opts);
+        assert!(integral.converged);
+        assert!((integral.value - Complex::new(0.0, -2.0 * std::f64::consts::PI)).norm() < 1e-8);
+        let res = f.residues(&[Complex::new(1.0, 0.0), Complex::new(-1.0, 0.0)], 0.25, &opts);
+        assert!((res[0].value + 1.0).norm() < 1e-9 && res[1].value.norm() < 1e-9);
+
+        // the path heights are Re f on the circle, normalized like the surface
+        let out = f.create_complex_data();
+        let path = f.create_contour_path(&circle, out.height_range, 64);
+        assert_eq!(path.len(), 2 * 64);
+        for p in &path {
+            let z = Complex::new(2.0 * p[0] as f64, 2.0 * p[2] as f64);
+            let re = (1.0 / (1.0 - z) + 0.5).re;
+            assert!((p[1] as f64 - re.clamp(-4.0, 4.0) / 4.0).abs() < 1e-4, "{:?}", p);
+        }
+    }
+}
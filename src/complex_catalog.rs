@@ -0,0 +1,568 @@
+#![allow(dead_code)]
+use std::f32::consts::PI;
+use std::fmt::Debug;
+use num_complex::Complex;
+use num_traits::{Float, FloatConst};
+use super::complex_wgsl;
+use super::special_func;
+
+// Catalog of the built-in complex functions shared by the CPU mesh (IComplex3D) and the GPU
+// compute shaders. Each entry keeps the CPU evaluator and the WGSL expression side by side, where
+// the WGSL expression uses z: vec2f, the animation parameter a: f32 and the cf:: primitives.
+// The CPU evaluators are generic and instantiated for both f32 and f64. The tests evaluate every
+// WGSL expression through the f32 ports of complex_func.rs and special_func_f32.rs and compare it
+// with the CPU evaluator, so that the two sides of an entry cannot drift apart.
+
+pub struct ComplexFunction {
+    pub name: &'static str,
+    pub func: fn(Complex<f32>, f32) -> Complex<f32>,
+    pub func64: fn(Complex<f64>, f64) -> Complex<f64>,
+    pub wgsl: &'static str,
+    pub domain: [f32; 4],       // xmin, xmax, zmin, zmax
+    pub height_range: [f32; 2], // range of |f| used by the GPU surface
+    pub phase_range: [f32; 2],  // range of arg f used for the colormap
+}
+
+// float types the CPU surfaces can be evaluated in
+pub trait Real: Float + FloatConst + Debug + 'static {
+    fn catalog_func(f: &ComplexFunction) -> fn(Complex<Self>, Self) -> Complex<Self>;
+}
+
+impl Real for f32 {
+    fn catalog_func(f: &ComplexFunction) -> fn(Complex<f32>, f32) -> Complex<f32> {
+        f.func
+    }
+}
+
+impl Real for f64 {
+    fn catalog_func(f: &ComplexFunction) -> fn(Complex<f64>, f64) -> Complex<f64> {
+        f.func64
+    }
+}
+
+impl ComplexFunction {
+    pub fn eval<T: Real>(&self, z: Complex<T>, t: T) -> Complex<T> {
+        T::catalog_func(self)(z, t)
+    }
+
+    pub fn wgsl_cfunc(&self) -> String {
+        complex_wgsl::cfunc_source(self.wgsl)
+    }
+}
+
+pub fn complex_functions() -> &'static [ComplexFunction] {
+    &CATALOG
+}
+
+pub fn get_complex_function(selection: u32) -> Option<&'static ComplexFunction> {
+    CATALOG.get(selection as usize)
+}
+
+static CATALOG: [ComplexFunction; 19] = [
+    ComplexFunction {
+        name: "rational",
+        func: rational,
+        func64: rational,
+        wgsl: "cf::cDiv(z - vec2(a, 0.0), cf::cMul(z, z) + z + vec2(a, 0.0))",
+        domain: [-3.0, 2.0, -2.0, 2.0],
+        height_range: [0.0, 45.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "sqrt_log_ratio",
+        func: sqrt_log_ratio,
+        func64: sqrt_log_ratio,
+        wgsl: "cf::cSqrt(cf::cDiv(cf::cLog(vec2(-z.y - 3.0*a, z.x)), cf::cLog(vec2(-z.y + a, z.x))))",
+        domain: [-6.0, 6.0, -6.0, 6.0],
+        height_range: [0.0, 7.0],
+        phase_range: [-PI/2.0, PI/2.0],
+    },
+    ComplexFunction {
+        name: "sin",
+        func: sin,
+        func64: sin,
+        wgsl: "a*cf::cSin(a*z)",
+        domain: [-6.0, 6.0, -6.0, 6.0],
+        height_range: [0.0, 203.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "tan_tan",
+        func: tan_tan,
+        func64: tan_tan,
+        wgsl: "(a + 0.5)*cf::cTan(cf::cTan((a + 0.5)*z))",
+        domain: [-10.0, 10.0, -1.0, 1.0],
+        height_range: [0.0, 30.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "tan_sin",
+        func: tan_sin,
+        func64: tan_sin,
+        wgsl: "a*cf::cTan(cf::cSin((a + 0.5)*z))",
+        domain: [-8.0, 8.0, -2.0, 2.0],
+        height_range: [0.0, 27.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "sqrt_sum",
+        func: sqrt_sum,
+        func64: sqrt_sum,
+        wgsl: "cf::cSqrt(vec2(a + z.x, z.y)) + cf::cSqrt(vec2(a - z.x, -z.y))",
+        domain: [-2.0, 2.0, -2.0, 2.0],
+        height_range: [1.4, 2.9],
+        phase_range: [-PI/2.0, PI/2.0],
+    },
+    ComplexFunction {
+        name: "tan_exp",
+        func: tan_exp,
+        func64: tan_exp,
+        wgsl: "cf::cDiv(cf::cTan(cf::cExp((a + 0.5)*z)), z)",
+        domain: [-1.0, 2.0, -1.0, 1.0],
+        height_range: [0.0, 120.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "sin_cos_sin",
+        func: sin_cos_sin,
+        func64: sin_cos_sin,
+        wgsl: "cf::cDiv(cf::cSin(cf::cCos(cf::cSin((a + 0.5)*z))), cf::cMul(z, z) - vec2(a, 0.0))",
+        domain: [-2.0, 2.0, -1.0, 1.0],
+        height_range: [0.0, 18.5],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "inv_pow5",
+        func: inv_pow5,
+        func64: inv_pow5,
+        wgsl: "(a + 0.5)*cf::cInv(cf::cAdd(cf::cPow((a + 0.5)*z, 5.0), 1.0))",
+        domain: [-1.0, 1.0, -1.0, 1.0],
+        height_range: [0.0, 26.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "sin_over_cos_exp",
+        func: sin_over_cos_exp,
+        func64: sin_over_cos_exp,
+        wgsl: "cf::cDiv(cf::cSin((a + 0.5)*z), \
+            cf::cMul(cf::cCos(cf::cExp((a + 0.5)*z)), cf::cMul(z, z) - vec2((a + 0.5)*(a + 0.5), 0.0)))",
+        domain: [-4.0, 6.0, -2.0, 2.0],
+        height_range: [0.0, 8.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "two_poles",
+        func: two_poles,
+        func64: two_poles,
+        wgsl: "cf::cInv(z + vec2(a, 0.0)) + cf::cInv(z - vec2(a, 0.0))",
+        domain: [-2.0, 2.0, -2.0, 2.0],
+        height_range: [0.0, 46.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "inv",
+        func: inv,
+        func64: inv,
+        wgsl: "cf::cInv(z)",
+        domain: [-2.0, 2.0, -2.0, 2.0],
+        height_range: [0.0, 10.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "identity",
+        func: identity,
+        func64: identity,
+        wgsl: "z",
+        domain: [-2.0, 2.0, -2.0, 2.0],
+        height_range: [0.0, 2.9],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "gamma",
+        func: gamma,
+        func64: gamma,
+        wgsl: "cf::cGamma(z)",
+        domain: [-4.5, 4.5, -3.0, 3.0],
+        height_range: [0.0, 8.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "zeta",
+        func: zeta,
+        func64: zeta,
+        wgsl: "cf::cZeta(z)",
+        domain: [-10.0, 10.0, -30.0, 30.0],
+        height_range: [0.0, 4.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "erf",
+        func: erf,
+        func64: erf,
+        wgsl: "cf::cErf(z)",
+        domain: [-3.0, 3.0, -3.0, 3.0],
+        height_range: [0.0, 4.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "bessel_j0",
+        func: bessel_j0,
+        func64: bessel_j0,
+        wgsl: "cf::cBesselJ(0.0, z)",
+        domain: [-12.0, 12.0, -4.0, 4.0],
+        height_range: [0.0, 8.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "airy_ai",
+        func: airy_ai,
+        func64: airy_ai,
+        wgsl: "cf::cAiryAi(z)",
+        domain: [-8.0, 4.0, -4.0, 4.0],
+        height_range: [0.0, 2.0],
+        phase_range: [-PI, PI],
+    },
+    ComplexFunction {
+        name: "lambert_w",
+        func: lambert_w,
+        func64: lambert_w,
+        wgsl: "cf::cLambertW(z)",
+        domain: [-4.0, 4.0, -4.0, 4.0],
+        height_range: [0.0, 2.5],
+        phase_range: [-PI, PI],
+    },
+];
+
+// region: CPU evaluators
+fn k<T: Float>(v: f64) -> T {
+    T::from(v).unwrap()
+}
+
+fn rational<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    (z - t)/(z*z + z + t)
+}
+
+fn sqrt_log_ratio<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    let f1 = Complex::new(-z.im - k::<T>(3.0) * t, z.re);
+    let f2 = Complex::new(-z.im + t, z.re);
+    (f1.ln()/f2.ln()).sqrt()
+}
+
+fn sin<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    (z * t).sin() * t
+}
+
+fn tan_tan<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    let s = k::<T>(0.5) + t;
+    (z * s).tan().tan() * s
+}
+
+fn tan_sin<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    (z * (k::<T>(0.5) + t)).sin().tan() * t
+}
+
+fn sqrt_sum<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    let f1 = Complex::new(t + z.re, z.im);
+    let f2 = Complex::new(t - z.re, -z.im);
+    f1.sqrt() + f2.sqrt()
+}
+
+fn tan_exp<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    (z * (k::<T>(0.5) + t)).exp().tan()/z
+}
+
+fn sin_cos_sin<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    (z * (k::<T>(0.5) + t)).sin().cos().sin()/(z*z - t)
+}
+
+fn inv_pow5<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    let s = k::<T>(0.5) + t;
+    ((z * s).powi(5) + T::one()).inv() * s
+}
+
+fn sin_over_cos_exp<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    let s = k::<T>(0.5) + t;
+    let f1 = (z * s).sin();
+    let f2 = (z * s).exp().cos() * (z * z - s*s);
+    f1/f2
+}
+
+fn two_poles<T: Float>(z: Complex<T>, t: T) -> Complex<T> {
+    (z + t).inv() + (z - t).inv()
+}
+
+fn inv<T: Float>(z: Complex<T>, _t: T) -> Complex<T> {
+    z.inv()
+}
+
+fn identity<T: Float>(z: Complex<T>, _t: T) -> Complex<T> {
+    z
+}
+
+fn gamma<T: Float>(z: Complex<T>, _t: T) -> Complex<T> {
+    special_func::apply(special_func::gamma, z)
+}
+
+fn zeta<T: Float>(z: Complex<T>, _t: T) -> Complex<T> {
+    special_func::apply(special_func::zeta, z)
+}
+
+fn erf<T: Float>(z: Complex<T>, _t: T) -> Complex<T> {
+    special_func::apply(special_func::erf, z)
+}
+
+fn bessel_j0<T: Float>(z: Complex<T>, _t: T) -> Complex<T> {
+    special_func::apply(|w| special_func::bessel_j(0, w), z)
+}
+
+fn airy_ai<T: Float>(z: Complex<T>, _t: T) -> Complex<T> {
+    special_func::apply(special_func::airy_ai, z)
+}
+
+fn lambert_w<T: Float>(z: Complex<T>, _t: T) -> Complex<T> {
+    special_func::apply(special_func::lambert_w, z)
+}
+// endregion: CPU evaluators
+
+// shorthand for the f64 test values of the other modules
+#[cfg(test)]
+pub(crate) fn c(re: f64, im: f64) -> Complex<f64> {
+    Complex::new(re, im)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex_wgsl::compose_shader;
+    use crate::complex_func::{self as cf, Vec2};
+    use crate::special_func_f32 as sf;
+    use cgmath::vec2;
+
+    // Evaluates a catalog WGSL expression with the f32 ports of the cf:: primitives, so the CPU
+    // evaluators can be compared with what the shaders compute. Covers the subset of WGSL the
+    // catalog uses: z, a, float literals, + - * /, vec2(x, y), .x/.y and cf:: calls.
+    #[derive(Debug, Clone, Copy)]
+    enum Value {
+        S(f32),
+        V(Vec2),
+    }
+
+    struct WgslMirror<'a> {
+        src: &'a [u8],
+        pos: usize,
+        z: Vec2,
+        a: f32,
+    }
+
+    impl WgslMirror<'_> {
+        fn eval(src: &str, z: Vec2, a: f32) -> Vec2 {
+            let mut m = WgslMirror { src: src.as_bytes(), pos: 0, z, a };
+            let v = m.expr();
+            m.skip_ws();
+            assert_eq!(m.pos, m.src.len(), "trailing input in '{}'", src);
+            match v {
+                Value::V(v) => v,
+                Value::S(s) => panic!("'{}' is the scalar {}", src, s),
+            }
+        }
+
+        fn skip_ws(&mut self) {
+            while self.pos < self.src.len() && self.src[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+        }
+
+        fn eat(&mut self, c: u8) -> bool {
+            self.skip_ws();
+            if self.src.get(self.pos) == Some(&c) {
+                self.pos += 1;
+                return true;
+            }
+            false
+        }
+
+        fn expect(&mut self, c: u8) {
+            assert!(self.eat(c), "expected '{}' at {}", c as char, self.pos);
+        }
+
+        fn word(&mut self) -> String {
+            self.skip_ws();
+            let start = self.pos;
+            while self.pos < self.src.len() && (self.src[self.pos].is_ascii_alphanumeric()
+                || b"_.:".contains(&self.src[self.pos])) {
+                self.pos += 1;
+            }
+            String::from_utf8(self.src[start..self.pos].to_vec()).unwrap()
+        }
+
+        fn expr(&mut self) -> Value {
+            let mut lhs = self.term();
+            loop {
+                if self.eat(b'+') {
+                    lhs = binary(lhs, self.term(), |x, y| x + y);
+                } else if self.eat(b'-') {
+                    lhs = binary(lhs, self.term(), |x, y| x - y);
+                } else {
+                    return lhs;
+                }
+            }
+        }
+
+        fn term(&mut self) -> Value {
+            let mut lhs = self.unary();
+            loop {
+                if self.eat(b'*') {
+                    lhs = binary(lhs, self.unary(), |x, y| x * y);
+                } else if self.eat(b'/') {
+                    lhs = binary(lhs, self.unary(), |x, y| x / y);
+                } else {
+                    return lhs;
+                }
+            }
+        }
+
+        fn unary(&mut self) -> Value {
+            if self.eat(b'-') {
+                return binary(Value::S(0.0), self.unary(), |x, y| x - y);
+            }
+            self.primary()
+        }
+
+        fn primary(&mut self) -> Value {
+            if self.eat(b'(') {
+                let v = self.expr();
+                self.expect(b')');
+                return v;
+            }
+            let word = self.word();
+            if let Ok(v) = word.parse::<f32>() {
+                return Value::S(v);
+            }
+            match word.as_str() {
+                "z" => Value::V(self.z),
+                "z.x" => Value::S(self.z.x),
+                "z.y" => Value::S(self.z.y),
+                "a" => Value::S(self.a),
+                _ => {
+                    self.expect(b'(');
+                    let mut args = vec![self.expr()];
+                    while self.eat(b',') {
+                        args.push(self.expr());
+                    }
+                    self.expect(b')');
+                    call(&word, &args)
+                }
+            }
+        }
+    }
+
+    // WGSL arithmetic, componentwise with scalars broadcast
+    fn binary(lhs: Value, rhs: Value, op: fn(f32, f32) -> f32) -> Value {
+        match (lhs, rhs) {
+            (Value::S(x), Value::S(y)) => Value::S(op(x, y)),
+            (Value::V(v), Value::S(s)) => Value::V(vec2(op(v.x, s), op(v.y, s))),
+            (Value::S(s), Value::V(v)) => Value::V(vec2(op(s, v.x), op(s, v.y))),
+            (Value::V(v), Value::V(w)) => Value::V(vec2(op(v.x, w.x), op(v.y, w.y))),
+        }
+    }
+
+    fn call(name: &str, args: &[Value]) -> Value {
+        let unary: Option<fn(Vec2) -> Vec2> = match name {
+            "cf::cSqrt" => Some(cf::c_sqrt),
+            "cf::cConj" => Some(cf::c_conj),
+            "cf::cInv" => Some(cf::c_inv),
+            "cf::cLog" => Some(cf::c_log),
+            "cf::cSin" => Some(cf::c_sin),
+            "cf::cCos" => Some(cf::c_cos),
+            "cf::cTan" => Some(cf::c_tan),
+            "cf::cExp2" => Some(cf::c_exp2),
+            "cf::cExp" => Some(cf::c_exp),
+            "cf::cAsinh" => Some(cf::c_asinh),
+            "cf::cGamma" => Some(sf::c_gamma),
+            "cf::cLogGamma" => Some(sf::c_log_gamma),
+            "cf::cZeta" => Some(sf::c_zeta),
+            "cf::cEta" => Some(sf::c_eta),
+            "cf::cErf" => Some(sf::c_erf),
+            "cf::cErfc" => Some(sf::c_erfc),
+            "cf::cAiryAi" => Some(sf::c_airy_ai),
+            "cf::cAiryBi" => Some(sf::c_airy_bi),
+            "cf::cLambertW" => Some(sf::c_lambert_w),
+            _ => None,
+        };
+        match (name, args) {
+            (_, [Value::V(z)]) if unary.is_some() => Value::V(unary.unwrap()(*z)),
+            ("vec2", [Value::S(x), Value::S(y)]) => Value::V(vec2(*x, *y)),
+            ("cf::cAdd", [Value::V(z), Value::S(s)]) => Value::V(cf::c_add(*z, *s)),
+            ("cf::cMul", [Value::V(a), Value::V(b)]) => Value::V(cf::c_mul(*a, *b)),
+            ("cf::cDiv", [Value::V(a), Value::V(b)]) => Value::V(cf::c_div(*a, *b)),
+            ("cf::cPow", [Value::V(z), Value::S(n)]) => Value::V(cf::c_pow(*z, *n)),
+            ("cf::cBesselJ", [Value::S(n), Value::V(z)]) => Value::V(sf::c_bessel_j(*n, *z)),
+            ("cf::cBesselY", [Value::S(n), Value::V(z)]) => Value::V(sf::c_bessel_y(*n, *z)),
+            _ => panic!("no mirror for {}{:?}", name, args),
+        }
+    }
+
+    #[test]
+    fn cpu_evaluators_match_the_wgsl_entries() {
+        for f in complex_functions() {
+            let [x0, x1, z0, z1] = f.domain;
+            let (mut compared, mut total) = (0, 0);
+            for t in [0.35f32, 0.8] {
+                // a grid off the axes, where cuts and symmetric zeros sit
+                for i in 0..9 {
+                    for j in 0..7 {
+                        let x = x0 + (x1 - x0) * (i as f32 + 0.37) / 9.0;
+                        let y = z0 + (z1 - z0) * (j as f32 + 0.61) / 7.0;
+                        let z = Complex::new(x as f64, y as f64);
+                        let expected = f.eval(z, t as f64);
+                        total += 1;
+                        // skip points where f is too large, too small or too sensitive for a comparison
+                        // in f32 (cf::cCos overflows long before cos(exp(z)) underflows, for example)
+                        let nearby = f.eval(z * (1.0 + 1e-6), t as f64);
+                        if !(expected.norm() < 1e6 && expected.norm() > 1e-30)
+                            || (nearby - expected).norm() > 1e-4 * expected.norm().max(1.0) {
+                            continue;
+                        }
+                        compared += 1;
+                        let w = WgslMirror::eval(f.wgsl, vec2(x, y), t);
+                        let w = Complex::new(w.x as f64, w.y as f64);
+                        assert!((w - expected).norm() <= 2e-3 * expected.norm().max(1.0),
+                            "{} at z = {}, t = {}: WGSL gives {}, the CPU evaluator {}", f.name, z, t, w, expected);
+                    }
+                }
+            }
+            assert!(4 * compared > 3 * total, "{}: only {} of {} points compared", f.name, compared, total);
+        }
+    }
+
+    #[test]
+    fn cpu_and_wgsl_entries_correspond() {
+        let templates = [
+            include_str!("../examples/ch02/complex3d_comp.wgsl"),
+            include_str!("../examples/ch03/domain_color_comp.wgsl"),
+        ];
+        let functions = complex_functions();
+        for (k, f) in functions.iter().enumerate() {
+            assert!(functions.iter().skip(k + 1).all(|g| g.name != f.name && g.wgsl != f.wgsl),
+                "duplicate catalog entry {}", f.name);
+            assert!(f.domain[0] < f.domain[1] && f.domain[2] < f.domain[3]);
+            assert!(f.height_range[0] < f.height_range[1] && f.phase_range[0] < f.phase_range[1]);
+
+            let fz = f.eval(Complex::new(0.3f32, 0.7), 0.5);
+            assert!(fz.re.is_finite() && fz.im.is_finite(), "{} is not finite", f.name);
+            let fz64 = f.eval(Complex::new(0.3f64, 0.7), 0.5);
+            assert!((fz64 - Complex::new(fz.re as f64, fz.im as f64)).norm() < 1e-4 * fz64.norm().max(1.0),
+                "{}: f32 and f64 evaluators disagree", f.name);
+
+            let cfunc = f.wgsl_cfunc();
+            for template in templates {
+                let source = compose_shader(template, Some(&cfunc));
+                let module = naga::front::wgsl::parse_str(&source)
+                    .unwrap_or_else(|e| panic!("{}: {}", f.name, e.emit_to_string(&source)));
+                naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+                    .validate(&module)
+                    .unwrap_or_else(|e| panic!("{}: {:?}", f.name, e));
+            }
+        }
+    }
+}
@@ -0,0 +1,568 @@
+#![allow(dead_code)]
+use std::fmt;
+use num_complex::Complex;
+use num_traits::{Float, FloatConst};
+use super::special_func;
+
+// complex expressions such as "(z - t)/(z^2 + z + t)" or "sqrt(log(i*z - 3t)/log(i*z + t))",
+// parsed at runtime into an AST that can be evaluated over num_complex::Complex
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Func {
+    Sin,
+    Cos,
+    Tan,
+    Exp,
+    Log,
+    Sqrt,
+    Asinh,
+    Conj,
+    Inv,
+    Gamma,
+    LogGamma,
+    Zeta,
+    Eta,
+    Erf,
+    Erfc,
+    AiryAi,
+    AiryBi,
+    LambertW,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Func> {
+        match name {
+            "sin" => Some(Func::Sin),
+            "cos" => Some(Func::Cos),
+            "tan" => Some(Func::Tan),
+            "exp" => Some(Func::Exp),
+            "log" | "ln" => Some(Func::Log),
+            "sqrt" => Some(Func::Sqrt),
+            "asinh" => Some(Func::Asinh),
+            "conj" => Some(Func::Conj),
+            "inv" => Some(Func::Inv),
+            "gamma" => Some(Func::Gamma),
+            "lgamma" | "loggamma" => Some(Func::LogGamma),
+            "zeta" => Some(Func::Zeta),
+            "eta" => Some(Func::Eta),
+            "erf" => Some(Func::Erf),
+            "erfc" => Some(Func::Erfc),
+            "airyai" => Some(Func::AiryAi),
+            "airybi" => Some(Func::AiryBi),
+            "lambertw" => Some(Func::LambertW),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Func::Sin => "sin",
+            Func::Cos => "cos",
+            Func::Tan => "tan",
+            Func::Exp => "exp",
+            Func::Log => "log",
+            Func::Sqrt => "sqrt",
+            Func::Asinh => "asinh",
+            Func::Conj => "conj",
+            Func::Inv => "inv",
+            Func::Gamma => "gamma",
+            Func::LogGamma => "lgamma",
+            Func::Zeta => "zeta",
+            Func::Eta => "eta",
+            Func::Erf => "erf",
+            Func::Erfc => "erfc",
+            Func::AiryAi => "airyai",
+            Func::AiryBi => "airybi",
+            Func::LambertW => "lambertw",
+        }
+    }
+}
+
+// functions of two arguments, kept apart from Func so that every call node carries exactly the
+// arguments its function takes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Func2 {
+    Pow,
+    BesselJ,
+    BesselY,
+}
+
+impl Func2 {
+    fn from_name(name: &str) -> Option<Func2> {
+        match name {
+            "pow" => Some(Func2::Pow),
+            "besselj" => Some(Func2::BesselJ),
+            "bessely" => Some(Func2::BesselY),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Func2::Pow => "pow",
+            Func2::BesselJ => "besselj",
+            Func2::BesselY => "bessely",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(f64),
+    Z,
+    T,
+    I,
+    Pi,
+    E,
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Pow(Box<Expr>, Box<Expr>),
+    Call(Func, Box<Expr>),
+    Call2(Func2, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize, // character offset into the expression string
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn parse(s: &str) -> Result<Expr, ParseError> {
+    let tokens = tokenize(s)?;
+    let mut p = Parser { tokens, pos: 0, len: s.chars().count() };
+    let expr = p.expr()?;
+    match p.peek() {
+        None => Ok(expr),
+        Some(tok) => Err(p.error_at(tok.1, "unexpected input")),
+    }
+}
+
+impl std::str::FromStr for Expr {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+impl Expr {
+    // a complex number as an expression, without subtractions so that negative parts stay literals
+    pub fn complex(c: Complex<f64>) -> Expr {
+        let im = Expr::Mul(Box::new(Expr::Num(c.im)), Box::new(Expr::I));
+        match (c.re != 0.0, c.im != 0.0) {
+            (_, false) => Expr::Num(c.re),
+            (false, true) => im,
+            (true, true) => Expr::Add(Box::new(Expr::Num(c.re)), Box::new(im)),
+        }
+    }
+
+    // evaluates in the precision of z and t, so the same expression serves f32 and f64 surfaces
+    pub fn eval<T: Float + FloatConst>(&self, z: Complex<T>, t: T) -> Complex<T> {
+        match self {
+            Expr::Num(v) => Complex::new(T::from(*v).unwrap_or_else(T::nan), T::zero()),
+            Expr::Z => z,
+            Expr::T => Complex::new(t, T::zero()),
+            Expr::I => Complex::i(),
+            Expr::Pi => Complex::new(T::PI(), T::zero()),
+            Expr::E => Complex::new(T::E(), T::zero()),
+            Expr::Neg(a) => -a.eval(z, t),
+            Expr::Add(a, b) => a.eval(z, t) + b.eval(z, t),
+            Expr::Sub(a, b) => a.eval(z, t) - b.eval(z, t),
+            Expr::Mul(a, b) => a.eval(z, t) * b.eval(z, t),
+            Expr::Div(a, b) => a.eval(z, t) / b.eval(z, t),
+            Expr::Pow(a, b) => complex_pow(a.eval(z, t), b.eval(z, t)),
+            Expr::Call(f, a) => {
+                let a = a.eval(z, t);
+                match f {
+                    Func::Sin => a.sin(),
+                    Func::Cos => a.cos(),
+                    Func::Tan => a.tan(),
+                    Func::Exp => a.exp(),
+                    Func::Log => a.ln(),
+                    Func::Sqrt => a.sqrt(),
+                    Func::Asinh => a.asinh(),
+                    Func::Conj => a.conj(),
+                    Func::Inv => a.inv(),
+                    Func::Gamma => special_func::apply(special_func::gamma, a),
+                    Func::LogGamma => special_func::apply(special_func::log_gamma, a),
+                    Func::Zeta => special_func::apply(special_func::zeta, a),
+                    Func::Eta => special_func::apply(special_func::eta, a),
+                    Func::Erf => special_func::apply(special_func::erf, a),
+                    Func::Erfc => special_func::apply(special_func::erfc, a),
+                    Func::AiryAi => special_func::apply(special_func::airy_ai, a),
+                    Func::AiryBi => special_func::apply(special_func::airy_bi, a),
+                    Func::LambertW => special_func::apply(special_func::lambert_w, a),
+                }
+            }
+            Expr::Call2(f, a, b) => {
+                let (a, b) = (a.eval(z, t), b.eval(z, t));
+                match f {
+                    Func2::Pow => complex_pow(a, b),
+                    // the order is the real part of the first argument, rounded to an integer
+                    Func2::BesselJ => special_func::apply(|w| special_func::bessel_j(bessel_order(a), w), b),
+                    Func2::BesselY => special_func::apply(|w| special_func::bessel_y(bessel_order(a), w), b),
+                }
+            }
+        }
+    }
+}
+
+// saturating, so that huge orders end up at the clamp of special_func::BESSEL_MAX_ORDER instead of 0
+fn bessel_order<T: Float>(a: Complex<T>) -> i32 {
+    a.re.round().to_f64().unwrap_or(0.0) as i32
+}
+
+fn complex_pow<T: Float>(a: Complex<T>, b: Complex<T>) -> Complex<T> {
+    if b.im != T::zero() {
+        a.powc(b)
+    } else if b.re.fract() == T::zero() && b.re.abs() <= T::from(i32::MAX).unwrap() {
+        a.powi(b.re.to_i32().unwrap_or(0))
+    } else {
+        a.powf(b.re)
+    }
+}
+
+// region: tokenizer
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(s: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && i + 1 < chars.len() && chars[i+1].is_ascii_digit()) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // scientific notation only when the exponent has digits, so "3e" still reads as 3*e
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut k = i + 1;
+                if k < chars.len() && (chars[k] == '+' || chars[k] == '-') {
+                    k += 1;
+                }
+                if k < chars.len() && chars[k].is_ascii_digit() {
+                    i = k;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let v = text.parse::<f64>().map_err(|_| ParseError {
+                position: start,
+                message: format!("invalid number '{}'", text),
+            })?;
+            // literals are kept in f64 but must also fit the f32 of the generated WGSL
+            if !(v as f32).is_finite() {
+                return Err(ParseError { position: start, message: format!("number '{}' out of range", text) });
+            }
+            tokens.push((Token::Num(v), start));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), start));
+        } else {
+            let tok = match c {
+                '+' | '-' | '*' | '/' | '^' => Token::Op(c),
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                _ => return Err(ParseError { position: i, message: format!("unexpected character '{}'", c) }),
+            };
+            tokens.push((tok, i));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+// endregion: tokenizer
+
+// region: parser
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(Token, usize)> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn error_at(&self, position: usize, message: &str) -> ParseError {
+        ParseError { position, message: message.to_string() }
+    }
+
+    fn expect(&mut self, tok: Token, what: &str) -> Result<(), ParseError> {
+        match self.next() {
+            Some((t, _)) if t == tok => Ok(()),
+            Some((_, p)) => Err(self.error_at(p, &format!("expected {}", what))),
+            None => Err(self.error_at(self.len, &format!("expected {}", what))),
+        }
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.term()?;
+        while let Some((Token::Op(op @ ('+' | '-')), _)) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.term()?;
+            lhs = if op == '+' { Expr::Add(Box::new(lhs), Box::new(rhs)) }
+                else { Expr::Sub(Box::new(lhs), Box::new(rhs)) };
+        }
+        Ok(lhs)
+    }
+
+    // term := unary (('*' | '/') unary | implicit multiplication)*
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+        loop {
+            match self.peek() {
+                Some((Token::Op(op @ ('*' | '/')), _)) => {
+                    let op = *op;
+                    self.pos += 1;
+                    let rhs = self.unary()?;
+                    lhs = if op == '*' { Expr::Mul(Box::new(lhs), Box::new(rhs)) }
+                        else { Expr::Div(Box::new(lhs), Box::new(rhs)) };
+                }
+                // "3t", "2(z+1)", "(z-1)(z+1)"
+                Some((Token::Num(_) | Token::Ident(_) | Token::LParen, _)) => {
+                    let rhs = self.power()?;
+                    lhs = Expr::Mul(Box::new(lhs), Box::new(rhs));
+                }
+                _ => break,
+            }
+        }
+        Ok(lhs)
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        match self.peek() {
+            Some((Token::Op('-'), _)) => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.unary()?)))
+            }
+            Some((Token::Op('+'), _)) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    // power := primary ('^' unary)?, right associative so that z^2^3 = z^(2^3)
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let base = self.primary()?;
+        if let Some((Token::Op('^'), _)) = self.peek() {
+            self.pos += 1;
+            let exponent = self.unary()?;
+            return Ok(Expr::Pow(Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let (tok, p) = match self.next() {
+            Some(t) => t,
+            None => return Err(self.error_at(self.len, "unexpected end of expression")),
+        };
+        match tok {
+            Token::Num(v) => Ok(Expr::Num(v)),
+            Token::LParen => {
+                let e = self.expr()?;
+                self.expect(Token::RParen, "')'")?;
+                Ok(e)
+            }
+            Token::Ident(name) => match name.as_str() {
+                "z" => Ok(Expr::Z),
+                "t" => Ok(Expr::T),
+                "i" => Ok(Expr::I),
+                "pi" => Ok(Expr::Pi),
+                "e" => Ok(Expr::E),
+                _ => {
+                    if let Some(f) = Func::from_name(&name) {
+                        let mut args = self.call(f.name(), 1, p)?;
+                        Ok(Expr::Call(f, Box::new(args.remove(0))))
+                    } else if let Some(f) = Func2::from_name(&name) {
+                        let mut args = self.call(f.name(), 2, p)?;
+                        let b = args.remove(1);
+                        Ok(Expr::Call2(f, Box::new(args.remove(0)), Box::new(b)))
+                    } else {
+                        Err(self.error_at(p, &format!("unknown identifier '{}'", name)))
+                    }
+                }
+            },
+            _ => Err(self.error_at(p, "expected a number, variable, function or '('")),
+        }
+    }
+
+    // the arguments of a call to the function name, which takes arity of them
+    fn call(&mut self, name: &str, arity: usize, p: usize) -> Result<Vec<Expr>, ParseError> {
+        self.expect(Token::LParen, &format!("'(' after '{}'", name))?;
+        let mut args = vec![self.expr()?];
+        while let Some((Token::Comma, _)) = self.peek() {
+            self.pos += 1;
+            args.push(self.expr()?);
+        }
+        self.expect(Token::RParen, "')'")?;
+        if args.len() != arity {
+            return Err(self.error_at(p, &format!("'{}' takes {} argument(s), got {}",
+                name, arity, args.len())));
+        }
+        Ok(args)
+    }
+}
+// endregion: parser
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type C64 = Complex<f64>;
+
+    fn eval(s: &str, z: C64, t: f64) -> C64 {
+        parse(s).unwrap_or_else(|e| panic!("'{}': {}", s, e)).eval(z, t)
+    }
+
+    fn assert_close(a: C64, b: C64) {
+        assert!((a - b).norm() <= 1e-12 * (1.0 + b.norm()), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn error_positions() {
+        for (s, position) in [("z +* 2", 3), ("sin(", 4), ("z)", 1), ("sin z", 4), ("foo(z)", 0),
+            ("z # 2", 2), ("pow(z)", 0), ("(z + 1", 6), ("", 0)] {
+            match parse(s) {
+                Err(e) => assert_eq!(e.position, position, "'{}': {}", s, e),
+                Ok(e) => panic!("'{}' parsed as {:?}", s, e),
+            }
+        }
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        let z = C64::new(0.7, -0.4);
+        assert_close(eval("z^2^3", z, 0.0), z.powi(8));
+        assert_close(eval("(z^2)^3", z, 0.0), z.powi(6));
+        assert_close(eval("-z^2", z, 0.0), -(z * z));
+        assert_close(eval("2^-1", z, 0.0), C64::new(0.5, 0.0));
+        assert_close(eval("1 - z - 2", z, 0.0), -1.0 - z);
+        assert_close(eval("1/z/2", z, 0.0), 0.5 / z);
+        assert_close(eval("1 + 2*z^2", z, 0.0), 1.0 + 2.0 * z * z);
+        assert_eq!(parse("-z^2"), parse("-(z^2)"));
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        let z = C64::new(0.3, 1.1);
+        assert_eq!(parse("3t"), Ok(Expr::Mul(Box::new(Expr::Num(3.0)), Box::new(Expr::T))));
+        assert_close(eval("3t", z, 0.25), C64::new(0.75, 0.0));
+        assert_close(eval("2(z+1)", z, 0.0), 2.0 * (z + 1.0));
+        assert_close(eval("(z-1)(z+1)", z, 0.0), z * z - 1.0);
+        assert_close(eval("2z^2", z, 0.0), 2.0 * z * z);
+        assert_close(eval("2pi i", z, 0.0), C64::new(0.0, 2.0 * std::f64::consts::PI));
+        // "3e" is 3 times e, not an incomplete exponent
+        assert_close(eval("3e", z, 0.0), C64::new(3.0 * std::f64::consts::E, 0.0));
+        assert_close(eval("3e2", z, 0.0), C64::new(300.0, 0.0));
+    }
+
+    #[test]
+    fn request_formulas() {
+        let i = C64::i();
+        for (z, t) in [(C64::new(0.6, -0.8), 0.3), (C64::new(-1.5, 0.2), 0.9), (C64::new(0.1, 2.0), 0.5)] {
+            let rational = (z - t) / (z * z + z + t);
+            assert_close(eval("(z - t)/(z^2 + z + t)", z, t), rational);
+            let root = ((i * z - 3.0 * t).ln() / (i * z + t).ln()).sqrt();
+            assert_close(eval("sqrt(log(i*z - 3t)/log(i*z + t))", z, t), root);
+        }
+    }
+
+    #[test]
+    fn elementary_functions() {
+        let z = C64::new(0.4, 0.9);
+        let w = C64::new(-0.3, 0.5);
+        let cases: [(&str, C64); 11] = [
+            ("sin(z)", z.sin()),
+            ("cos(z)", z.cos()),
+            ("tan(z)", z.tan()),
+            ("exp(z)", z.exp()),
+            ("log(z)", z.ln()),
+            ("ln(z)", z.ln()),
+            ("sqrt(z)", z.sqrt()),
+            ("pow(z, -0.3 + 0.5i)", z.powc(w)),
+            ("asinh(z)", z.asinh()),
+            ("conj(z)", z.conj()),
+            ("inv(z)", z.inv()),
+        ];
+        for (s, expected) in cases {
+            assert_close(eval(s, z, 0.0), expected);
+        }
+        for name in ["sin", "cos", "tan", "exp", "log", "sqrt", "asinh", "conj", "inv"] {
+            assert_eq!(Func::from_name(name).map(|f| f.name()), Some(name));
+        }
+        for name in ["pow", "besselj", "bessely"] {
+            assert_eq!(Func2::from_name(name).map(|f| f.name()), Some(name));
+            assert_eq!(Func::from_name(name), None);
+        }
+    }
+
+    #[test]
+    fn huge_bessel_orders_saturate() {
+        let z = C64::new(150.0, 1.0);
+        assert_eq!(eval("besselj(1e9, z)", z, 0.0), eval("besselj(100, z)", z, 0.0));
+        assert_eq!(eval("bessely(-1e30, z)", z, 0.0), eval("bessely(-100, z)", z, 0.0));
+    }
+
+    #[test]
+    fn out_of_range_literals() {
+        // finite in f64 but not in the f32 of the generated shaders
+        for (s, position) in [("1e39", 0), ("z + 1e400", 4), ("3.5e38z", 0)] {
+            let e = parse(s).unwrap_err();
+            assert_eq!(e.position, position, "'{}': {}", s, e);
+            assert!(e.message.contains("out of range"), "{}", e);
+        }
+        assert!(parse("3.4e38").is_ok());
+        assert!(parse("1e-50").is_ok());
+    }
+
+    #[test]
+    fn complex_constants() {
+        for c in [C64::new(1.5, -2.0), C64::new(0.0, -0.25), C64::new(-3.0, 0.0), C64::new(0.0, 0.0)] {
+            assert_eq!(Expr::complex(c).eval(C64::new(0.7, 0.1), 0.0), c);
+        }
+        assert_eq!(Expr::complex(C64::new(-3.0, 0.0)), Expr::Num(-3.0));
+        assert_eq!(Expr::complex(C64::new(0.0, -0.25)), Expr::Mul(Box::new(Expr::Num(-0.25)), Box::new(Expr::I)));
+    }
+}
@@ -0,0 +1,489 @@
+#![allow(dead_code)]
+use cgmath::{vec2, InnerSpace, Vector2};
+
+// f32 mirror of the primitives in complex_func.wgsl. Every function follows the WGSL source operation
+// by operation (including its quirks), so the GPU pictures can be checked against num_complex on the
+// CPU. hsv2_rgb, colormap2_rgb and the styles after them mirror the domain coloring functions;
+// colormap_lerp is the colormap of the fractal shaders.
+// Known deviations from the principal branch functions, measured by the tests below:
+//   - c_sqrt uses sign(z.y), which is 0 on the real axis, so c_sqrt(-4) = 0 instead of 2i
+//   - c_sqrt takes sqrt(0.5*(|z| - x)), which cancels close to the positive real axis
+//   - c_sin, c_cos and c_tan build e^y and e^-y explicitly and overflow to inf/NaN for |Im z| > ~88
+//   - c_asinh loses precision near 0 (through c_sqrt) and along the negative real axis, where
+//     z + sqrt(z^2 + 1) cancels
+//   - c_exp2 is z^2, not 2^z
+//   - hsv2_rgb wraps the hue h = arg/2pi in (-1/2, 1/2] with the truncating %, so its colors jump
+//     across the negative real axis; phase_hue and the styles built on it take fract(h) first
+
+pub type Vec2 = Vector2<f32>;
+
+// same literals as complex_func.wgsl
+#[allow(clippy::approx_constant, clippy::excessive_precision)]
+pub const PI: f32 = 3.14159265359;
+#[allow(clippy::approx_constant, clippy::excessive_precision)]
+pub const E: f32 = 2.71828182845;
+
+// WGSL sign() returns 0 for 0, unlike f32::signum
+fn sign(x: f32) -> f32 {
+    if x > 0.0 { 1.0 } else if x < 0.0 { -1.0 } else { 0.0 }
+}
+
+pub fn c_add(a: Vec2, s: f32) -> Vec2 {
+    vec2(a.x + s, a.y)
+}
+
+pub fn c_mul(a: Vec2, b: Vec2) -> Vec2 {
+    vec2(a.x*b.x - a.y*b.y, a.x*b.y + a.y*b.x)
+}
+
+pub fn c_div(a: Vec2, b: Vec2) -> Vec2 {
+    let d = b.dot(b);
+    vec2(a.dot(b)/d, (a.y*b.x - a.x*b.y)/d)
+}
+
+pub fn c_sqrt(z: Vec2) -> Vec2 {
+    let m = z.magnitude();
+    let s = vec2((0.5*(m + z.x)).sqrt(), (0.5*(m - z.x)).sqrt());
+    vec2(s.x, s.y * sign(z.y))
+}
+
+pub fn c_conj(z: Vec2) -> Vec2 {
+    vec2(z.x, -z.y)
+}
+
+pub fn c_pow(z: Vec2, n: f32) -> Vec2 {
+    let r = z.magnitude();
+    let a = z.y.atan2(z.x);
+    r.powf(n) * vec2((a*n).cos(), (a*n).sin())
+}
+
+pub fn c_inv(z: Vec2) -> Vec2 {
+    vec2(z.x/z.dot(z), -z.y/z.dot(z))
+}
+
+pub fn c_arg(z: Vec2) -> f32 {
+    z.y.atan2(z.x)
+}
+
+pub fn c_log(z: Vec2) -> Vec2 {
+    vec2(z.dot(z).sqrt().ln(), z.y.atan2(z.x))
+}
+
+pub fn c_sin(z: Vec2) -> Vec2 {
+    let a = E.powf(z.y);
+    let b = E.powf(-z.y);
+    vec2(z.x.sin()*(a + b)*0.5, z.x.cos()*(a - b)*0.5)
+}
+
+pub fn c_cos(z: Vec2) -> Vec2 {
+    let a = E.powf(z.y);
+    let b = E.powf(-z.y);
+    vec2(z.x.cos()*(a + b)*0.5, -z.x.sin()*(a - b)*0.5)
+}
+
+pub fn c_tan(z: Vec2) -> Vec2 {
+    let a = E.powf(z.y);
+    let b = E.powf(-z.y);
+    let cx = z.x.cos();
+    let ab = (a - b)*0.5;
+    vec2(z.x.sin()*cx, ab*(a + b)*0.5)/(cx*cx + ab*ab)
+}
+
+pub fn c_exp2(z: Vec2) -> Vec2 {
+    vec2(z.x*z.x - z.y*z.y, 2.0*z.x*z.y)
+}
+
+pub fn c_exp(z: Vec2) -> Vec2 {
+    vec2(z.x.exp()*z.y.cos(), z.x.exp()*z.y.sin())
+}
+
+pub fn c_asinh(z: Vec2) -> Vec2 {
+    let a = z + c_sqrt(c_mul(z, z) + vec2(1.0, 0.0));
+    c_log(a)
+}
+
+// WGSL fract, which is x - floor(x) rather than f32::fract
+fn fract(x: f32) -> f32 {
+    x - x.floor()
+}
+
+pub fn phase_hue(z: Vec2) -> [f32; 3] {
+    let h = fract(c_arg(z)/2.0/PI);
+    let mut rgb = [0.0f32; 3];
+    for (k, offset) in [0.0, 4.0, 2.0].iter().enumerate() {
+        let f = ((h*6.0 + offset) % 6.0 - 3.0).abs() - 1.0;
+        let x = f.clamp(0.0, 1.0);
+        rgb[k] = x*x*(3.0 - 2.0*x);
+    }
+    rgb
+}
+
+pub fn hsv2_rgb(z: Vec2) -> [f32; 4] {
+    hsv_grid_rgb(z, 10.0, 0.7)
+}
+
+pub fn hsv_grid_rgb(z: Vec2, sharpness: f32, rings: f32) -> [f32; 4] {
+    let len = z.magnitude();
+    let h = c_arg(z)/2.0/PI;
+    let mut fx = 2.0*(fract(z.x) - 0.5);
+    let mut fy = 2.0*(fract(z.y) - 0.5);
+    fx = fx*fx;
+    fy = fy*fy;
+    let mut g = 1.0 - (1.0 - fx)*(1.0 - fy);
+    g = g.abs().powf(sharpness);
+    let mut c = 2.0*(fract(len.log2()) - 0.5);
+    c = rings*c.abs().powf(sharpness);
+    let v = 1.0 - 0.5*g;
+    let mut rgb = [0.0f32; 3];
+    for (k, offset) in [0.0, 4.0, 2.0].iter().enumerate() {
+        let f = ((h*6.0 + offset) % 6.0 - 3.0).abs() - 1.0;
+        let x = f.clamp(0.0, 1.0);
+        let x = x*x*(3.0 - 2.0*x);
+        rgb[k] = (1.0 - c)*v*x + c;
+    }
+    [rgb[0], rgb[1], rgb[2], 1.0]
+}
+
+pub fn colormap2_rgb(z: Vec2, colormap: &[[f32; 3]; 11]) -> [f32; 4] {
+    let len = z.magnitude();
+    let mut h = z.y.atan2(z.x);
+    if h < 0.0 { h += 2.0*PI; }
+    if h >= 2.0*PI { h -= 2.0*PI; }
+    let mut v = [0.0f32; 3];
+    for i in 0..10 {
+        if h >= 0.2*PI*i as f32 && h < 0.2*PI*(i as f32 + 1.0) {
+            let s = (h - i as f32*0.2*PI)/(0.2*PI);
+            for k in 0..3 {
+                v[k] = s*colormap[i+1][k] + (1.0 - s)*colormap[i][k];
+            }
+        }
+    }
+    let b = fract(len.log2());
+    [v[0]*b, v[1]*b, v[2]*b, 1.0]
+}
+
+// linear interpolation in the colormap, t in [0, 1]
+pub fn colormap_lerp(colormap: &[[f32; 3]; 11], t: f32) -> [f32; 3] {
+    let tn = t.clamp(0.0, 1.0);
+    let indx = (10.0*tn).floor() as usize;
+    if indx as f32 == 10.0*tn { return colormap[indx]; }
+    let tn1 = (tn - 0.1*indx as f32)*10.0;
+    let (a, b) = (colormap[indx], colormap[indx + 1]);
+    [a[0] + (b[0] - a[0])*tn1, a[1] + (b[1] - a[1])*tn1, a[2] + (b[2] - a[2])*tn1]
+}
+
+// offset from the view center of pixel (x, y) in a width x height image, as the compute shaders
+// map their pixels: scale is the width of the view and y points up
+pub fn pixel_offset(scale: f32, x: u32, y: u32, width: u32, height: u32) -> Vec2 {
+    let (w, h) = (width as f32, height as f32);
+    vec2(scale*(x as f32 - 0.5*w)/w, -scale*(h/w)*(y as f32 - 0.5*h)/h)
+}
+
+// color with components in [0, 1] as RGBA8, clamped as a rgba8unorm texture store does
+pub fn rgba8(color: [f32; 4]) -> [u8; 4] {
+    color.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+// RGBA8 pixels of a width x height image, row by row from the top, with color(x, y) for each pixel
+pub fn render_rgba8<F: FnMut(u32, u32) -> [f32; 4]>(width: u32, height: u32, mut color: F) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::with_capacity((4 * width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            data.extend(rgba8(color(x, y)));
+        }
+    }
+    data
+}
+
+// WGSL smoothstep
+fn smoothstep(low: f32, high: f32, x: f32) -> f32 {
+    let t = ((x - low)/(high - low)).clamp(0.0, 1.0);
+    t*t*(3.0 - 2.0*t)
+}
+
+fn contour_line(x: f32, width: f32) -> f32 {
+    1.0 - smoothstep(0.0, width.max(1e-6), (fract(x + 0.5) - 0.5).abs())
+}
+
+fn shaded(hue: [f32; 3], v: f32) -> [f32; 4] {
+    [v*hue[0], v*hue[1], v*hue[2], 1.0]
+}
+
+pub fn phase_rgb(z: Vec2) -> [f32; 4] {
+    shaded(phase_hue(z), 1.0)
+}
+
+pub fn enhanced_phase_rgb(z: Vec2, lines: f32, shade: f32) -> [f32; 4] {
+    let sp = fract(lines*c_arg(z)/2.0/PI);
+    let sm = fract(lines*z.magnitude().ln()/2.0/PI);
+    shaded(phase_hue(z), 1.0 - shade + shade*sp*sm)
+}
+
+pub fn modulus_contour_rgb(z: Vec2, base: f32, width: f32) -> [f32; 4] {
+    let x = z.magnitude().ln()/base.ln();
+    shaded(phase_hue(z), 1.0 - contour_line(x, width))
+}
+
+pub fn phase_contour_rgb(z: Vec2, lines: f32, width: f32) -> [f32; 4] {
+    let x = lines*c_arg(z)/2.0/PI;
+    shaded(phase_hue(z), 1.0 - contour_line(x, width))
+}
+
+pub fn checkerboard_rgb(z: Vec2, size: f32, shade: f32) -> [f32; 4] {
+    let k = (z.x/size).floor() + (z.y/size).floor();
+    let odd = k - 2.0*(0.5*k).floor();
+    shaded(phase_hue(z), 1.0 - shade*odd)
+}
+
+pub fn image_uv(z: Vec2, scale: f32) -> [f32; 2] {
+    [fract(z.x/scale + 0.5), fract(0.5 - z.y/scale)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_complex::Complex;
+
+    type C64 = Complex<f64>;
+
+    struct Accuracy {
+        max_rel_error: f64,
+        worst: Vec2,
+        non_finite: usize,
+    }
+
+    // Relative error |w - w_ref|/|w_ref| of the f32 primitive against the f64 reference, evaluated
+    // at the same (f32) inputs. Below |w_ref| = 1 the error is taken as absolute, so that the zeros
+    // of a function do not dominate the report.
+    fn accuracy(f: &dyn Fn(Vec2) -> Vec2, reference: &dyn Fn(C64) -> C64, points: &[Vec2]) -> Accuracy {
+        let mut acc = Accuracy { max_rel_error: 0.0, worst: vec2(0.0, 0.0), non_finite: 0 };
+        for &p in points {
+            let w_ref = reference(C64::new(p.x as f64, p.y as f64));
+            if !w_ref.re.is_finite() || !w_ref.im.is_finite() {
+                continue;
+            }
+            let w = f(p);
+            if !w.x.is_finite() || !w.y.is_finite() {
+                acc.non_finite += 1;
+                continue;
+            }
+            let err = (C64::new(w.x as f64, w.y as f64) - w_ref).norm() / w_ref.norm().max(1.0);
+            if err > acc.max_rel_error {
+                acc.max_rel_error = err;
+                acc.worst = p;
+            }
+        }
+        acc
+    }
+
+    // dense grid over [-4, 4]^2, offset by a quarter step so that no point lies on an axis
+    fn grid() -> Vec<Vec2> {
+        let n = 161;
+        let mut pts = vec![];
+        for i in 0..n {
+            for j in 0..n {
+                let x = -4.0 + 8.0 * (i as f32 + 0.25) / (n - 1) as f32;
+                let y = -4.0 + 8.0 * (j as f32 + 0.25) / (n - 1) as f32;
+                pts.push(vec2(x, y));
+            }
+        }
+        pts
+    }
+
+    fn negative_real_axis() -> Vec<Vec2> {
+        (1..=100).map(|k| vec2(-0.1 * k as f32, 0.0)).collect()
+    }
+
+    fn large_imaginary() -> Vec<Vec2> {
+        (0..=40).flat_map(|k| {
+            let y = 10.0 + 5.0 * k as f32;
+            [vec2(0.3, y), vec2(-1.7, -y)]
+        }).collect()
+    }
+
+    // max relative error allowed on the grid, the negative real axis and at large |Im z|; c_sqrt and
+    // c_asinh suffer from the cancellation described at the top of this file. None where one of
+    // known_deviations makes a bound meaningless.
+    fn tolerance(name: &str) -> [Option<f64>; 3] {
+        match name {
+            "cSqrt" => [Some(2e-5), None, Some(1e-6)],
+            "cAsinh" => [Some(5e-4), Some(2e-5), None],
+            "cSin" | "cCos" => [Some(2e-6), Some(1e-6), Some(1e-5)],
+            _ => [Some(2e-6), Some(1e-6), Some(2e-6)],
+        }
+    }
+
+    // e^y overflows at large |Im z| (see known_deviations), which only these primitives reach
+    fn overflows_at_large_im(name: &str) -> bool {
+        matches!(name, "cSin" | "cCos" | "cTan")
+    }
+
+    type Primitive = (&'static str, Box<dyn Fn(Vec2) -> Vec2>, Box<dyn Fn(C64) -> C64>);
+
+    fn primitives() -> Vec<Primitive> {
+        let b = vec2(0.7f32, -1.3);
+        let bc = C64::new(0.7f32 as f64, -1.3f32 as f64);
+        vec![
+            ("cAdd", Box::new(|z| c_add(z, 1.5)), Box::new(|z| z + 1.5)),
+            ("cMul", Box::new(move |z| c_mul(z, b)), Box::new(move |z| z * bc)),
+            ("cDiv", Box::new(move |z| c_div(b, z)), Box::new(move |z| bc / z)),
+            ("cSqrt", Box::new(c_sqrt), Box::new(|z| z.sqrt())),
+            ("cConj", Box::new(c_conj), Box::new(|z| z.conj())),
+            ("cPow(2.5)", Box::new(|z| c_pow(z, 2.5)), Box::new(|z| z.powf(2.5))),
+            ("cPow(-3)", Box::new(|z| c_pow(z, -3.0)), Box::new(|z| z.powi(-3))),
+            ("cInv", Box::new(c_inv), Box::new(|z| z.inv())),
+            ("cArg", Box::new(|z| vec2(c_arg(z), 0.0)), Box::new(|z| C64::new(z.arg(), 0.0))),
+            ("cLog", Box::new(c_log), Box::new(|z| z.ln())),
+            ("cSin", Box::new(c_sin), Box::new(|z| z.sin())),
+            ("cCos", Box::new(c_cos), Box::new(|z| z.cos())),
+            ("cTan", Box::new(c_tan), Box::new(|z| z.tan())),
+            ("cExp2", Box::new(c_exp2), Box::new(|z| z * z)),
+            ("cExp", Box::new(c_exp), Box::new(|z| z.exp())),
+            ("cAsinh", Box::new(c_asinh), Box::new(|z| z.asinh())),
+        ]
+    }
+
+    #[test]
+    fn primitive_accuracy() {
+        let sets = [("grid", grid()), ("negative real axis", negative_real_axis()), ("large |Im z|", large_imaginary())];
+        for (name, f, reference) in primitives() {
+            for ((set, points), bound) in sets.iter().zip(tolerance(name)) {
+                let acc = accuracy(&*f, &*reference, points);
+                if let Some(bound) = bound {
+                    assert!(acc.max_rel_error < bound, "{} on the {}: max error {:e} at {:?}", name, set,
+                        acc.max_rel_error, acc.worst);
+                }
+                if !(*set == "large |Im z|" && overflows_at_large_im(name)) {
+                    assert_eq!(acc.non_finite, 0, "{} on the {}", name, set);
+                }
+            }
+        }
+    }
+
+    // the error table of every primitive; run with --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn primitive_accuracy_report() {
+        let (grid, axis, large) = (grid(), negative_real_axis(), large_imaginary());
+
+        println!("{:<10} {:>12} {:>12} {:>12} {:>10}", "primitive", "grid", "neg. real", "large |Im|", "non-finite");
+        for (name, f, reference) in primitives() {
+            let g = accuracy(&*f, &*reference, &grid);
+            let a = accuracy(&*f, &*reference, &axis);
+            let l = accuracy(&*f, &*reference, &large);
+            println!("{:<10} {:>12.3e} {:>12.3e} {:>12.3e} {:>10}", name, g.max_rel_error, a.max_rel_error,
+                l.max_rel_error, g.non_finite + a.non_finite + l.non_finite);
+        }
+    }
+
+    #[test]
+    fn known_deviations() {
+        // sign(0) = 0 drops the imaginary part of sqrt on the negative real axis
+        let s = c_sqrt(vec2(-4.0, 0.0));
+        assert_eq!((s.x, s.y), (0.0, 0.0));
+
+        // e^y overflows in f32 long before sin, cos and tan themselves do
+        let t = c_tan(vec2(0.3, 100.0));
+        assert!(!t.x.is_finite() || !t.y.is_finite());
+        assert!(C64::new(0.3, 100.0).tan().norm().is_finite());
+
+        // z + sqrt(z^2 + 1) cancels for large negative real z
+        let a = c_asinh(vec2(-1000.0, 0.0));
+        let a_ref = C64::new(-1000.0, 0.0).asinh();
+        assert!((C64::new(a.x as f64, a.y as f64) - a_ref).norm() / a_ref.norm() > 1e-3);
+        // and for large |Im z| left of the imaginary axis
+        let a = c_asinh(vec2(-1.7, -200.0));
+        let a_ref = C64::new(-1.7, -200.0).asinh();
+        assert!((C64::new(a.x as f64, a.y as f64) - a_ref).norm() / a_ref.norm() > 1e-3);
+
+        // the principal branch and the mirror agree again just above the cut
+        let s = c_sqrt(vec2(-4.0, 1e-6));
+        assert!((s.y - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn edge_cases_at_zero() {
+        let zero = vec2(0.0f32, 0.0);
+        assert_eq!(c_sqrt(zero), zero);
+        assert_eq!(c_exp(zero), vec2(1.0, 0.0));
+        assert_eq!(c_sin(zero), zero);
+        assert_eq!(c_log(zero).x, f32::NEG_INFINITY);
+        assert!(c_inv(zero).x.is_nan());
+        assert_eq!(c_pow(zero, 2.0), zero);
+    }
+
+    #[test]
+    fn domain_coloring_matches_wgsl() {
+        // half-integer coordinates are as far as possible from the grid lines, so only the hue is left
+        let c = hsv2_rgb(vec2(1.5, 0.5));
+        assert!(c[0] > 0.99 && c[1] > 0.1 && c[1] < 0.4 && c[2] < 0.01 && c[3] == 1.0, "{:?}", c);
+        let c = hsv2_rgb(vec2(-1.5, 0.5));
+        assert!(c[0] < 0.01 && c[1] > 0.99 && c[2] > 0.6, "{:?}", c);
+
+        // the 10 colormap segments cover [0, 2pi); brightness follows fract(log2|z|)
+        let cmap = crate::colormap::colormap_data("jet");
+        let c = colormap2_rgb(vec2(1.5, 0.0), &cmap);
+        let b = 1.5f32.log2();
+        assert!((0..3).all(|k| (c[k] - b * cmap[0][k]).abs() < 1e-6), "{:?}", c);
+        assert_eq!(colormap2_rgb(vec2(2.0, 0.0), &cmap), [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn pixels_and_rgba8() {
+        // a view 4 wide over an 8 x 4 image is 2 high, with pixel (0, 0) at its top left corner
+        assert_eq!(pixel_offset(4.0, 0, 0, 8, 4), vec2(-2.0, 1.0));
+        assert_eq!(pixel_offset(4.0, 4, 2, 8, 4), vec2(0.0, 0.0));
+        assert_eq!(rgba8([-0.5, 0.5, 1.5, 1.0]), [0, 128, 255, 255]);
+        let data = render_rgba8(3, 2, |x, y| [x as f32 / 2.0, y as f32, 0.0, 1.0]);
+        assert_eq!(data.len(), 4 * 3 * 2);
+        assert_eq!(&data[4 * 4..4 * 5], &[128, 255, 0, 255]);
+    }
+
+    #[test]
+    fn colormap_lerp_matches_color_lerp() {
+        let cmap = crate::colormap::colormap_data("jet");
+        for t in [-0.5f32, 0.0, 0.05, 0.1, 0.37, 0.5, 0.99, 1.0, 1.5] {
+            assert_eq!(colormap_lerp(&cmap, t), crate::colormap::color_lerp(cmap, 0.0, 1.0, t), "t = {}", t);
+        }
+        assert_eq!(colormap_lerp(&cmap, 2.0), cmap[10]);
+    }
+
+    #[test]
+    fn domain_coloring_styles() {
+        for z in [vec2(1.5, 0.5), vec2(-0.3, 2.2), vec2(0.01, -4.0)] {
+            assert_eq!(hsv_grid_rgb(z, 10.0, 0.7), hsv2_rgb(z));
+            let h = phase_hue(z);
+            assert_eq!(phase_rgb(z), [h[0], h[1], h[2], 1.0]);
+        }
+        let red = [1.0, 0.0, 0.0, 1.0];
+        assert_eq!(phase_rgb(vec2(3.0, 0.0)), red);
+        // the phase hue is continuous across the negative real axis, where it is cyan
+        let (above, below) = (phase_rgb(vec2(-1.0, 1e-4)), phase_rgb(vec2(-1.0, -1e-4)));
+        assert!((0..3).all(|k| (above[k] - below[k]).abs() < 1e-3), "{:?} {:?}", above, below);
+        assert!(above[0] < 1e-3 && above[1] > 0.999 && above[2] > 0.999, "{:?}", above);
+
+        // contour lines are black on the levels and leave the hue alone beyond their width
+        assert_eq!(modulus_contour_rgb(vec2(4.0, 0.0), 2.0, 0.1), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(modulus_contour_rgb(vec2(3.0, 0.0), 2.0, 0.1), red);
+        assert_eq!(phase_contour_rgb(vec2(0.0, -2.0), 4.0, 0.05), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(phase_contour_rgb(vec2(1.0, 1.0), 4.0, 0.05), phase_rgb(vec2(1.0, 1.0)));
+
+        // the enhanced portrait is darkest just past an isochromatic line on |z| = 1 and brightest
+        // just before the next level
+        let c = enhanced_phase_rgb(vec2(1.0, 1e-4), 8.0, 0.4);
+        assert!((c[0] - 0.6).abs() < 1e-3, "{:?}", c);
+        let r = (2.0 * PI / 8.0 - 1e-4).exp();
+        let c = enhanced_phase_rgb(vec2(r * (-1e-4f32).cos(), r * (-1e-4f32).sin()), 8.0, 0.4);
+        assert!((c[0] - 1.0).abs() < 1e-2, "{:?}", c);
+
+        assert_eq!(checkerboard_rgb(vec2(0.5, 0.0), 1.0, 0.5), red);
+        assert_eq!(checkerboard_rgb(vec2(1.5, 0.0), 1.0, 0.5), [0.5, 0.0, 0.0, 1.0]);
+        assert_eq!(checkerboard_rgb(vec2(-0.5, -0.25), 1.0, 0.5), phase_rgb(vec2(-0.5, -0.25)));
+        let c = checkerboard_rgb(vec2(0.5, -0.25), 1.0, 0.5);
+        assert!(c[0] > 0.49 && c[0] < 0.51, "{:?}", c);
+
+        assert_eq!(image_uv(vec2(0.0, 0.0), 2.0), [0.5, 0.5]);
+        assert_eq!(image_uv(vec2(-1.0, 0.5), 2.0), [0.0, 0.25]);
+        assert_eq!(image_uv(vec2(1.5, 0.0), 2.0), [0.25, 0.5]);
+    }
+}
@@ -63,8 +63,23 @@ fn cAsinh(z:vec2f) -> vec2f{
     return cLog(a);
 }
 
+// hue of the phase: red at arg 0, then yellow, green, cyan, blue and magenta counterclockwise;
+// hsv2Rgb skips the fract and jumps across the negative real axis
+fn phaseHue(z:vec2f) -> vec3f{
+    let h = fract(cArg(z)/2.0/pi);
+    let f = abs((h*6.0 + vec3(0.0,4.0,2.0))%6.0 - 3.0) - 1.0;
+    let rgb = clamp(f, vec3(0.0), vec3(1.0));
+    return rgb*rgb*(3.0 - 2.0*rgb);
+}
+
 // hsv to rgb color conversion
 fn hsv2Rgb(z:vec2f) -> vec4f{
+    return hsvGridRgb(z, 10.0, 0.7);
+}
+
+// phase hue darkened along the unit grid of Re/Im and whitened at the rings |z| = 2^k; sharpness
+// narrows both, rings is the strength of the white rings
+fn hsvGridRgb(z:vec2f, sharpness:f32, rings:f32) -> vec4f{
     let len = length(z);
     let h = cArg(z)/2.0/pi;
     var fx = 2.0*(fract(z.x) - 0.5);
@@ -72,14 +87,14 @@ fn hsv2Rgb(z:vec2f) -> vec4f{
     fx = fx*fx;
     fy = fy*fy;
     var g = 1.0 -(1.0 - fx)*(1.0 - fy);
-    g = pow(abs(g), 10.0);
+    g = pow(abs(g), sharpness);
     var c = 2.0*(fract(log2(len)) - 0.5);
-    c = 0.7*pow(abs(c), 10.0);  
+    c = rings*pow(abs(c), sharpness);
     var v = 1.0 - 0.5*g;
     let f = abs((h*6.0 + vec3(0.0,4.0,2.0))%6.0 - 3.0) - 1.0;
     var rgb = clamp(f, vec3(0.0), vec3(1.0));
     rgb = rgb*rgb*(3.0 - 2.0*rgb);
-    rgb = (1.0-c)*v*mix(vec3(1.0), rgb, 1.0);  
+    rgb = (1.0-c)*v*rgb;
     return vec4(rgb + c*vec3(1.0), 1.0);
 }
 
@@ -100,4 +115,58 @@ fn colormap2Rgb(z:vec2f, colormap:array<vec4f,11>) -> vec4f {
     }
     let b = fract(log2(len));
     return vec4(v[0]*b, v[1]*b, v[2]*b, 1.0);
-}
\ No newline at end of file
+}
+
+// linear interpolation in the colormap, t in [0, 1]
+fn colormapLerp(colormap:array<vec4f,11>, t:f32) -> vec3f {
+    var c = colormap;
+    let tn = clamp(t, 0.0, 1.0);
+    let indx = u32(floor(10.0 * tn));
+    if (f32(indx) == 10.0 * tn) { return c[indx].rgb; }
+    let tn1 = (tn - 0.1 * f32(indx)) * 10.0;
+    return c[indx].rgb + (c[indx + 1u].rgb - c[indx].rgb) * tn1;
+}
+
+// 1 on the lines where x is an integer, falling to 0 at a distance of width
+fn contourLine(x:f32, width:f32) -> f32 {
+    return 1.0 - smoothstep(0.0, max(width, 1e-6), abs(fract(x + 0.5) - 0.5));
+}
+
+// pure phase portrait
+fn phaseRgb(z:vec2f) -> vec4f {
+    return vec4(phaseHue(z), 1.0);
+}
+
+// enhanced phase portrait: the phase hue shaded by sawtooths in arg and log|z| with the same period
+// 2pi/lines, which brighten towards the isochromatic lines arg = 2pi k/lines and towards the modulus
+// levels between them, so the cells form a conformal grid
+fn enhancedPhaseRgb(z:vec2f, lines:f32, shade:f32) -> vec4f {
+    let sp = fract(lines*cArg(z)/2.0/pi);
+    let sm = fract(lines*log(length(z))/2.0/pi);
+    return vec4((1.0 - shade + shade*sp*sm)*phaseHue(z), 1.0);
+}
+
+// phase hue with dark lines at |z| = base^k
+fn modulusContourRgb(z:vec2f, base:f32, width:f32) -> vec4f {
+    let x = log(length(z))/log(base);
+    return vec4((1.0 - contourLine(x, width))*phaseHue(z), 1.0);
+}
+
+// phase hue with dark lines at arg z = 2pi k/lines
+fn phaseContourRgb(z:vec2f, lines:f32, width:f32) -> vec4f {
+    let x = lines*cArg(z)/2.0/pi;
+    return vec4((1.0 - contourLine(x, width))*phaseHue(z), 1.0);
+}
+
+// phase hue on a checkerboard of squares of the given size in Re/Im, every other one darkened
+fn checkerboardRgb(z:vec2f, size:f32, shade:f32) -> vec4f {
+    let k = floor(z.x/size) + floor(z.y/size);
+    let odd = k - 2.0*floor(0.5*k);
+    return vec4((1.0 - shade*odd)*phaseHue(z), 1.0);
+}
+
+// texture coordinates for image domain coloring: the picture covers the square of side scale
+// centered at 0, upright, and repeats
+fn imageUv(z:vec2f, scale:f32) -> vec2f {
+    return fract(vec2(z.x/scale + 0.5, 0.5 - z.y/scale));
+}
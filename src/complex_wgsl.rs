@@ -0,0 +1,234 @@
+#![allow(dead_code)]
+use super::complex_expr::{Expr, Func, Func2};
+
+// WGSL code generation for complex expressions. The generated cFunc only calls the cf:: primitives
+// from complex_func.wgsl and the special functions of special_func.wgsl, which is inlined with it, so
+// it can be spliced into any compute shader that imports complex_func.wgsl.
+
+pub const COMPLEX_FUNC_WGSL: &str = include_str!("complex_func.wgsl");
+pub const SPECIAL_FUNC_WGSL: &str = include_str!("special_func.wgsl");
+
+pub fn wgsl_cfunc(expr: &Expr) -> String {
+    cfunc_source(&wgsl_expr(expr))
+}
+
+// wraps a vec2f expression in z and a into the cFunc called by the compute shaders
+pub fn cfunc_source(wgsl: &str) -> String {
+    format!("fn cFunc(z: vec2f, a: f32) -> vec2f {{\n    return {};\n}}\n", wgsl)
+}
+
+// complex valued WGSL expression (vec2f) for expr, where z is the complex variable and a is the
+// animation parameter t
+pub fn wgsl_expr(expr: &Expr) -> String {
+    if let Some(r) = wgsl_real(expr) {
+        return format!("vec2({}, 0.0)", r);
+    }
+    match expr {
+        Expr::Z => "z".to_string(),
+        Expr::I => "vec2(0.0, 1.0)".to_string(),
+        Expr::Neg(a) => format!("(-{})", wgsl_expr(a)),
+        Expr::Add(a, b) => match (wgsl_real(a), wgsl_real(b)) {
+            (Some(r), None) => format!("cf::cAdd({}, {})", wgsl_expr(b), r),
+            (None, Some(r)) => format!("cf::cAdd({}, {})", wgsl_expr(a), r),
+            _ => format!("({} + {})", wgsl_expr(a), wgsl_expr(b)),
+        },
+        Expr::Sub(a, b) => match (wgsl_real(a), wgsl_real(b)) {
+            (Some(r), None) => format!("cf::cAdd(-{}, {})", wgsl_expr(b), r),
+            (None, Some(r)) => format!("cf::cAdd({}, -{})", wgsl_expr(a), r),
+            _ => format!("({} - {})", wgsl_expr(a), wgsl_expr(b)),
+        },
+        Expr::Mul(a, b) => match (wgsl_real(a), wgsl_real(b)) {
+            (Some(r), None) => format!("({} * {})", r, wgsl_expr(b)),
+            (None, Some(r)) => format!("({} * {})", r, wgsl_expr(a)),
+            _ => format!("cf::cMul({}, {})", wgsl_expr(a), wgsl_expr(b)),
+        },
+        Expr::Div(a, b) => match (wgsl_real(a), wgsl_real(b)) {
+            (Some(r), None) => format!("({} * cf::cInv({}))", r, wgsl_expr(b)),
+            (None, Some(r)) => format!("({} / {})", wgsl_expr(a), r),
+            _ => format!("cf::cDiv({}, {})", wgsl_expr(a), wgsl_expr(b)),
+        },
+        Expr::Pow(a, b) => wgsl_pow(a, b),
+        Expr::Call(f, a) => {
+            let a = wgsl_expr(a);
+            match f {
+                Func::Sin => format!("cf::cSin({})", a),
+                Func::Cos => format!("cf::cCos({})", a),
+                Func::Tan => format!("cf::cTan({})", a),
+                Func::Exp => format!("cf::cExp({})", a),
+                Func::Log => format!("cf::cLog({})", a),
+                Func::Sqrt => format!("cf::cSqrt({})", a),
+                Func::Asinh => format!("cf::cAsinh({})", a),
+                Func::Conj => format!("cf::cConj({})", a),
+                Func::Inv => format!("cf::cInv({})", a),
+                Func::Gamma => format!("cf::cGamma({})", a),
+                Func::LogGamma => format!("cf::cLogGamma({})", a),
+                Func::Zeta => format!("cf::cZeta({})", a),
+                Func::Eta => format!("cf::cEta({})", a),
+                Func::Erf => format!("cf::cErf({})", a),
+                Func::Erfc => format!("cf::cErfc({})", a),
+                Func::AiryAi => format!("cf::cAiryAi({})", a),
+                Func::AiryBi => format!("cf::cAiryBi({})", a),
+                Func::LambertW => format!("cf::cLambertW({})", a),
+            }
+        }
+        Expr::Call2(f, a, b) => match f {
+            Func2::Pow => wgsl_pow(a, b),
+            Func2::BesselJ => format!("cf::cBesselJ({}, {})", wgsl_order(a), wgsl_expr(b)),
+            Func2::BesselY => format!("cf::cBesselY({}, {})", wgsl_order(a), wgsl_expr(b)),
+        },
+        // real valued nodes are handled by wgsl_real above
+        _ => unreachable!(),
+    }
+}
+
+fn wgsl_pow(base: &Expr, exponent: &Expr) -> String {
+    match wgsl_real(exponent) {
+        Some(n) => format!("cf::cPow({}, {})", wgsl_expr(base), n),
+        // z^w = exp(w log z)
+        None => format!("cf::cExp(cf::cMul({}, cf::cLog({})))", wgsl_expr(exponent), wgsl_expr(base)),
+    }
+}
+
+// order of a Bessel function, the real part of the argument (rounded by the WGSL function)
+fn wgsl_order(expr: &Expr) -> String {
+    wgsl_real(expr).unwrap_or_else(|| format!("{}.x", wgsl_expr(expr)))
+}
+
+// scalar WGSL expression (f32) when expr does not depend on z or i
+fn wgsl_real(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Num(v) => Some(wgsl_float(*v)),
+        Expr::T => Some("a".to_string()),
+        Expr::Pi => Some("cf::pi".to_string()),
+        Expr::E => Some("cf::e".to_string()),
+        Expr::Neg(a) => wgsl_real(a).map(|a| format!("(-{})", a)),
+        Expr::Add(a, b) => Some(format!("({} + {})", wgsl_real(a)?, wgsl_real(b)?)),
+        Expr::Sub(a, b) => Some(format!("({} - {})", wgsl_real(a)?, wgsl_real(b)?)),
+        Expr::Mul(a, b) => Some(format!("({} * {})", wgsl_real(a)?, wgsl_real(b)?)),
+        Expr::Div(a, b) => Some(format!("({} / {})", wgsl_real(a)?, wgsl_real(b)?)),
+        Expr::Call(Func::Conj, a) => wgsl_real(a),
+        _ => None,
+    }
+}
+
+fn wgsl_float(v: f64) -> String {
+    // Debug always keeps a decimal point or an exponent, which WGSL needs for an f32 literal
+    format!("{:?}", v as f32)
+}
+
+// Resolves the "#import .../complex_func.wgsl as cf;" line of a compute shader template by inlining
+// complex_func.wgsl, and replaces the template's own cFunc with the given one when provided.
+pub fn compose_shader(template: &str, cfunc: Option<&str>) -> String {
+    let source = match cfunc {
+        Some(f) => replace_wgsl_fn(template, "cFunc", f),
+        None => template.to_string(),
+    };
+
+    let mut header = String::new();
+    let mut body = String::new();
+    let mut aliases: Vec<&str> = vec![];
+    for line in source.lines() {
+        let l = line.trim();
+        if l.starts_with("#import") {
+            if l.contains("complex_func.wgsl") {
+                header.push_str(COMPLEX_FUNC_WGSL);
+                header.push('\n');
+                header.push_str(SPECIAL_FUNC_WGSL);
+                header.push('\n');
+                aliases.push(l.trim_end_matches(';').rsplit(" as ").next().unwrap_or("cf").trim());
+            }
+            continue;
+        }
+        body.push_str(line);
+        body.push('\n');
+    }
+    for alias in aliases {
+        body = body.replace(&format!("{}::", alias), "");
+    }
+    header + &body
+}
+
+// replaces the definition "fn <name>(...) -> ... { ... }" in source with new_fn
+fn replace_wgsl_fn(source: &str, name: &str, new_fn: &str) -> String {
+    let start = match source.find(&format!("fn {}(", name)) {
+        Some(s) => s,
+        None => return format!("{}\n{}", source, new_fn),
+    };
+    let open = start + source[start..].find('{').unwrap_or(0);
+    let mut depth = 0;
+    let mut end = source.len();
+    for (k, c) in source[open..].char_indices() {
+        if c == '{' {
+            depth += 1;
+        } else if c == '}' {
+            depth -= 1;
+            if depth == 0 {
+                end = open + k + 1;
+                break;
+            }
+        }
+    }
+    format!("{}{}{}", &source[..start], new_fn.trim_end(), &source[end..])
+}
+
+// parses and validates a composed shader with naga, panicking with the source on failure
+#[cfg(test)]
+pub(crate) fn validate_wgsl(source: &str) {
+    let module = match naga::front::wgsl::parse_str(source) {
+        Ok(m) => m,
+        Err(e) => panic!("{}\n{}", e.emit_to_string(source), source),
+    };
+    let mut validator = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    );
+    if let Err(e) = validator.validate(&module) {
+        panic!("{:?}\n{}", e, source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex_expr::parse;
+
+    const TEMPLATES: [&str; 3] = [
+        include_str!("../examples/ch02/complex3d_comp.wgsl"),
+        include_str!("../examples/ch03/domain_color_comp.wgsl"),
+        include_str!("../examples/ch03/iterate_func_comp.wgsl"),
+    ];
+
+    const EXPRESSIONS: [&str; 11] = [
+        "(z - t)/(z^2 + z + t)",
+        "sqrt(log(i*z - 3t)/log(i*z + t))",
+        "t*sin(t*z)",
+        "(0.5 + t)/(1 + ((0.5 + t)*z)^5)",
+        "1/(z + t) + 1/(z - t)",
+        "asinh(conj(z)) - inv(z) + exp(tan(cos(z)))",
+        "pow(z, i + t) - 2pi*e^z",
+        "-z^-2 + 1e-3",
+        "gamma(z) + lgamma(z) - zeta(z)*eta(t*z)",
+        "erf(z)/erfc(i*z) + lambertw(z)",
+        "besselj(2, z) + bessely(t + 1, z) - besselj(z, 1) + airyai(z)*airybi(-z)",
+    ];
+
+    #[test]
+    fn templates_compose_without_cfunc() {
+        for template in TEMPLATES {
+            validate_wgsl(&compose_shader(template, None));
+        }
+    }
+
+    #[test]
+    fn generated_cfunc_validates_in_every_template() {
+        for s in EXPRESSIONS {
+            let cfunc = wgsl_cfunc(&parse(s).unwrap());
+            assert!(cfunc.starts_with("fn cFunc(z: vec2f, a: f32) -> vec2f {"));
+            for template in TEMPLATES {
+                let source = compose_shader(template, Some(&cfunc));
+                assert_eq!(source.matches("fn cFunc(").count(), 1);
+                validate_wgsl(&source);
+            }
+        }
+    }
+}
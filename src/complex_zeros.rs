@@ -0,0 +1,300 @@
+#![allow(dead_code)]
+use std::f64::consts::PI;
+use num_complex::Complex;
+
+// Zeros and poles of a meromorphic function in a rectangle [xmin, xmax, zmin, zmax] (x = Re z,
+// z = Im z, as in IComplex3D). The rectangle is subdivided into cells, the winding number of f
+// around each cell boundary counts zeros minus poles inside (argument principle), and every cell
+// left with a nonzero winding number is refined by Newton's method on f (zeros) or 1/f (poles).
+// Zeros and poles on the boundary of the rectangle are included, by widening it slightly.
+// Functions with branch cuts (sqrt, log) produce spurious windings where a cut crosses a cell.
+
+type C64 = Complex<f64>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZeroPoleKind {
+    Zero,
+    Pole,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZeroPole {
+    pub location: C64,
+    pub order: u32,
+    pub kind: ZeroPoleKind,
+    pub converged: bool, // false when Newton failed and location is the center of the cell
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ZeroPoleOptions {
+    pub min_depth: u32,        // subdivisions applied to every cell; a zero and a pole closer than the
+                               // resulting cell size may cancel out and be missed
+    pub max_depth: u32,        // subdivisions of cells with a nonzero winding number
+    pub edge_samples: u32,     // initial samples per cell edge before adaptive bisection
+    pub tolerance: f64,        // relative step size at which Newton stops
+    pub max_iterations: u32,
+}
+
+impl Default for ZeroPoleOptions {
+    fn default() -> Self {
+        Self {
+            min_depth: 4,
+            max_depth: 10,
+            edge_samples: 8,
+            tolerance: 1e-13,
+            max_iterations: 60,
+        }
+    }
+}
+
+// split ratios tried in turn when a child boundary runs through a zero or pole; slightly off center
+// so that cell edges avoid the symmetric points where zeros tend to sit
+const SPLITS: [f64; 4] = [0.4937, 0.5281, 0.4611, 0.5573];
+
+// margins, in parts of the width and height, tried in turn when the boundary of the domain itself
+// runs through a zero or pole; off-grid for the same reason
+const MARGINS: [f64; 4] = [0.0, 0.00731, 0.0193, 0.0417];
+
+struct Cell {
+    rect: [f64; 4],
+    depth: u32,
+    winding: i32,
+    variation: f64, // total |change of arg f| along the boundary
+}
+
+pub fn find_zeros_poles<F: Fn(C64) -> C64>(f: F, domain: [f64; 4], opts: &ZeroPoleOptions) -> Vec<ZeroPole> {
+    let mut result: Vec<ZeroPole> = vec![];
+    let [x0, x1, z0, z1] = domain;
+    let (w, h) = (x1 - x0, z1 - z0);
+    let root = MARGINS.iter().find_map(|&m| {
+        let rect = [x0 - m * w, x1 + m * w, z0 - m * h, z1 + m * h];
+        boundary_phase(&f, rect, opts.edge_samples).map(|(winding, variation)| Cell {
+            rect, depth: 0, winding, variation,
+        })
+    });
+    let root = match root {
+        Some(cell) => cell,
+        None => return result,
+    };
+
+    let mut stack = vec![root];
+    while let Some(cell) = stack.pop() {
+        let interesting = cell.winding != 0 || cell.variation > 2.0 * PI;
+        if cell.depth < opts.min_depth || (interesting && cell.depth < opts.max_depth) {
+            stack.extend(split(&f, &cell, opts.edge_samples));
+        } else if cell.winding != 0 {
+            let zp = refine(&f, &cell, opts);
+            let tol = 1e3 * opts.tolerance * (1.0 + zp.location.norm());
+            if !result.iter().any(|r| r.kind == zp.kind && (r.location - zp.location).norm() < tol) {
+                result.push(zp);
+            }
+        }
+    }
+    // the widened domain may take in zeros and poles just outside of it
+    let tol = 1e-9 * (w + h);
+    result.retain(|r| r.location.re >= x0 - tol && r.location.re <= x1 + tol
+        && r.location.im >= z0 - tol && r.location.im <= z1 + tol);
+    result.sort_by(|a, b| (a.location.re, a.location.im).partial_cmp(&(b.location.re, b.location.im)).unwrap());
+    result
+}
+
+fn split<F: Fn(C64) -> C64>(f: &F, cell: &Cell, samples: u32) -> Vec<Cell> {
+    let [x0, x1, z0, z1] = cell.rect;
+    for s in SPLITS {
+        let xm = x0 + s * (x1 - x0);
+        let zm = z0 + s * (z1 - z0);
+        let rects = [[x0, xm, z0, zm], [xm, x1, z0, zm], [x0, xm, zm, z1], [xm, x1, zm, z1]];
+        let children: Option<Vec<Cell>> = rects.iter().map(|&rect| {
+            boundary_phase(f, rect, samples).map(|(winding, variation)| Cell {
+                rect, depth: cell.depth + 1, winding, variation,
+            })
+        }).collect();
+        if let Some(c) = children {
+            return c;
+        }
+    }
+    vec![]
+}
+
+// winding number of f around the rectangle and the total variation of arg f, or None when the
+// boundary passes through a zero or pole
+fn boundary_phase<F: Fn(C64) -> C64>(f: &F, rect: [f64; 4], samples: u32) -> Option<(i32, f64)> {
+    let [x0, x1, z0, z1] = rect;
+    let corners = [C64::new(x0, z0), C64::new(x1, z0), C64::new(x1, z1), C64::new(x0, z1)];
+    let (mut total, mut variation) = (0.0, 0.0);
+    for k in 0..4 {
+        let (a, b) = (corners[k], corners[(k + 1) % 4]);
+        let mut p = a;
+        let mut fp = regular(f(p))?;
+        for s in 1..=samples {
+            let q = a + (b - a) * (s as f64 / samples as f64);
+            let fq = regular(f(q))?;
+            let (d, v) = phase_change(f, p, q, fp, fq, 12)?;
+            total += d;
+            variation += v;
+            (p, fp) = (q, fq);
+        }
+    }
+    Some(((total / (2.0 * PI)).round() as i32, variation))
+}
+
+fn phase_change<F: Fn(C64) -> C64>(f: &F, a: C64, b: C64, fa: C64, fb: C64, depth: u32) -> Option<(f64, f64)> {
+    let d = wrap(fb.arg() - fa.arg());
+    if d.abs() < PI / 3.0 {
+        return Some((d, d.abs()));
+    }
+    if depth == 0 {
+        // the phase still jumps at the finest resolution: a zero or pole practically on the edge
+        return if d.abs() < 0.75 * PI { Some((d, d.abs())) } else { None };
+    }
+    let m = (a + b) * 0.5;
+    let fm = regular(f(m))?;
+    let (d1, v1) = phase_change(f, a, m, fa, fm, depth - 1)?;
+    let (d2, v2) = phase_change(f, m, b, fm, fb, depth - 1)?;
+    Some((d1 + d2, v1 + v2))
+}
+
+fn regular(w: C64) -> Option<C64> {
+    if w.re.is_finite() && w.im.is_finite() && w != C64::new(0.0, 0.0) { Some(w) } else { None }
+}
+
+// angle wrapped into (-pi, pi]
+fn wrap(a: f64) -> f64 {
+    let mut a = a % (2.0 * PI);
+    if a > PI {
+        a -= 2.0 * PI;
+    } else if a <= -PI {
+        a += 2.0 * PI;
+    }
+    a
+}
+
+// Newton's method z -= m g/g' with the multiplicity m taken from the winding number, where g = f for
+// zeros and g = 1/f for poles; g' by central differences on a scale that follows the step size
+fn refine<F: Fn(C64) -> C64>(f: &F, cell: &Cell, opts: &ZeroPoleOptions) -> ZeroPole {
+    let kind = if cell.winding > 0 { ZeroPoleKind::Zero } else { ZeroPoleKind::Pole };
+    let order = cell.winding.unsigned_abs();
+    // a sample that lands exactly on the pole reads as a zero of 1/f
+    let g = |z: C64| match kind {
+        ZeroPoleKind::Zero => f(z),
+        ZeroPoleKind::Pole => regular(f(z)).map_or(C64::new(0.0, 0.0), |w| w.inv()),
+    };
+
+    let [x0, x1, z0, z1] = cell.rect;
+    let center = C64::new(0.5 * (x0 + x1), 0.5 * (z0 + z1));
+    let size = (x1 - x0).max(z1 - z0);
+    let inside = |z: C64| (z - center).re.abs() < size && (z - center).im.abs() < size;
+
+    let mut z = center;
+    let mut h = 1e-3 * size;
+    let mut converged = false;
+    for _ in 0..opts.max_iterations {
+        let gz = g(z);
+        if gz == C64::new(0.0, 0.0) {
+            converged = true;
+            break;
+        }
+        let dg = (g(z + h) - g(z - h)) / (2.0 * h);
+        let step = gz / dg * order as f64;
+        if !step.re.is_finite() || !step.im.is_finite() {
+            break;
+        }
+        z -= step;
+        if !inside(z) {
+            break;
+        }
+        if step.norm() <= opts.tolerance * (1.0 + z.norm()) {
+            converged = true;
+            break;
+        }
+        h = (1e-3 * step.norm()).max(1e-12 * (1.0 + z.norm()));
+    }
+
+    let location = if converged && inside(z) { z } else { center };
+    ZeroPole { location, order, kind, converged: converged && inside(z) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex_catalog::c;
+
+    fn assert_found(found: &[ZeroPole], location: C64, order: u32, kind: ZeroPoleKind, tol: f64) {
+        assert!(found.iter().any(|r| r.kind == kind && r.order == order && r.converged
+                && (r.location - location).norm() < tol),
+            "{:?} of order {} at {} not in {:?}", kind, order, location, found);
+    }
+
+    #[test]
+    fn rational_with_multiple_roots() {
+        // zeros 1 (simple) and -1 (double), poles i (simple) and -2i (triple)
+        let f = |z: C64| (z - 1.0) * (z + 1.0).powi(2) / ((z - c(0.0, 1.0)) * (z + c(0.0, 2.0)).powi(3));
+        let found = find_zeros_poles(f, [-3.0, 3.0, -3.0, 3.0], &ZeroPoleOptions::default());
+
+        assert_eq!(found.len(), 4, "{:?}", found);
+        assert_found(&found, c(1.0, 0.0), 1, ZeroPoleKind::Zero, 1e-10);
+        assert_found(&found, c(-1.0, 0.0), 2, ZeroPoleKind::Zero, 1e-6);
+        assert_found(&found, c(0.0, 1.0), 1, ZeroPoleKind::Pole, 1e-10);
+        assert_found(&found, c(0.0, -2.0), 3, ZeroPoleKind::Pole, 1e-4);
+    }
+
+    #[test]
+    fn close_zero_pole_pair() {
+        // the pair cancels in the winding number of every cell that contains both, and barely moves
+        // the phase on the boundary of the domain
+        let f = |z: C64| (z - c(0.3, 0.2)) / (z - c(0.5, 0.2)) * (z * z + 4.0);
+        let found = find_zeros_poles(f, [-1.0, 1.0, -1.0, 1.0], &ZeroPoleOptions::default());
+
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert_found(&found, c(0.3, 0.2), 1, ZeroPoleKind::Zero, 1e-10);
+        assert_found(&found, c(0.5, 0.2), 1, ZeroPoleKind::Pole, 1e-10);
+    }
+
+    #[test]
+    fn catalog_rational() {
+        // (z - t)/(z^2 + z + t) at t = 0.5: zero 0.5, poles -0.5 +- 0.5i
+        let f = crate::complex_catalog::get_complex_function(0).unwrap();
+        let found = find_zeros_poles(|z| f.eval(z, 0.5), [-3.0, 2.0, -2.0, 2.0], &ZeroPoleOptions::default());
+
+        assert_eq!(found.len(), 3, "{:?}", found);
+        assert_found(&found, c(0.5, 0.0), 1, ZeroPoleKind::Zero, 1e-10);
+        assert_found(&found, c(-0.5, 0.5), 1, ZeroPoleKind::Pole, 1e-10);
+        assert_found(&found, c(-0.5, -0.5), 1, ZeroPoleKind::Pole, 1e-10);
+    }
+
+    #[test]
+    fn zeros_and_poles_on_the_domain_boundary() {
+        // the right edge of [-2, 2]^2 runs through the zero at 2
+        let f = |z: C64| (z - 2.0) * (z - 0.5);
+        let found = find_zeros_poles(f, [-2.0, 2.0, -2.0, 2.0], &ZeroPoleOptions::default());
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert_found(&found, c(0.5, 0.0), 1, ZeroPoleKind::Zero, 1e-10);
+        assert_found(&found, c(2.0, 0.0), 1, ZeroPoleKind::Zero, 1e-10);
+
+        // the top and bottom edges of [-2, 2] x [-1, 1] run through the poles at +-i
+        let f = |z: C64| (z * z + 1.0).inv();
+        let found = find_zeros_poles(f, [-2.0, 2.0, -1.0, 1.0], &ZeroPoleOptions::default());
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert_found(&found, c(0.0, 1.0), 1, ZeroPoleKind::Pole, 1e-10);
+        assert_found(&found, c(0.0, -1.0), 1, ZeroPoleKind::Pole, 1e-10);
+
+        // 2.02 is inside the widened domain but not in the domain itself
+        let f = |z: C64| (z - 2.0) * (z - 2.02) * (z - 0.5);
+        let found = find_zeros_poles(f, [-2.0, 2.0, -2.0, 2.0], &ZeroPoleOptions::default());
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert_found(&found, c(0.5, 0.0), 1, ZeroPoleKind::Zero, 1e-10);
+        assert_found(&found, c(2.0, 0.0), 1, ZeroPoleKind::Zero, 1e-10);
+    }
+
+    #[test]
+    fn polynomial_roots_of_unity() {
+        let f = |z: C64| z.powi(5) - 1.0;
+        let found = find_zeros_poles(f, [-2.0, 2.0, -2.0, 2.0], &ZeroPoleOptions::default());
+
+        assert_eq!(found.len(), 5, "{:?}", found);
+        for k in 0..5 {
+            let w = C64::from_polar(1.0, 2.0 * PI * k as f64 / 5.0);
+            assert_found(&found, w, 1, ZeroPoleKind::Zero, 1e-10);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+#![allow(dead_code)]
+use std::f64::consts::PI;
+use num_complex::Complex;
+
+// Images of a coordinate grid in the z-plane under w = f(z): lines of constant Re z and Im z, or
+// circles and rays around the center of the domain, sampled densely and mapped into the w-plane.
+// Between two samples whose images are further apart than max_step the parameter is bisected, so
+// curves stay smooth where f stretches them; a step that still jumps after the bisections, or leaves
+// the clip region or meets a non-finite value, splits the curve, which keeps the segments near
+// poles from shooting off to infinity.
+
+type C64 = Complex<f64>;
+type ZCurve = Box<dyn Fn(f64) -> C64>;
+
+// bisections of a parameter step before a jump in w is taken as a break of the curve
+const MAX_BISECTIONS: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GridFamily {
+    Cartesian { x_lines: u32, z_lines: u32 }, // lines Re z = const and Im z = const, ends included
+    Polar { circles: u32, rays: u32 },        // circles up to the inscribed circle of the domain
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GridCurveKind {
+    Re(f64),     // image of Re z = x
+    Im(f64),     // image of Im z = y
+    Circle(f64), // image of |z - c| = r
+    Ray(f64),    // image of arg(z - c) = phi
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConformalGridOptions {
+    pub family: GridFamily,
+    pub samples: u32,              // samples along each z-curve before bisection
+    pub w_domain: Option<[f64; 4]>, // window of the w-plane, defaults to the z domain
+    pub max_step: f64,             // longest segment in w, as a fraction of the window width
+    pub clip: f64,                 // points further than clip window widths from the window center are dropped
+}
+
+impl Default for ConformalGridOptions {
+    fn default() -> Self {
+        Self {
+            family: GridFamily::Cartesian { x_lines: 11, z_lines: 11 },
+            samples: 200,
+            w_domain: None,
+            max_step: 0.02,
+            clip: 2.0,
+        }
+    }
+}
+
+// one piece of an image curve in normalized coordinates: w-window mapped onto [-scale, scale] in x and
+// z, with y = 0
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridCurve {
+    pub kind: GridCurveKind,
+    pub points: Vec<[f32; 3]>,
+}
+
+// the z-curves of the family over domain [xmin, xmax, zmin, zmax], as t in [0, 1] -> z
+fn family_curves(family: GridFamily, d: [f64; 4]) -> Vec<(GridCurveKind, ZCurve)> {
+    let at = |n: u32, k: u32| if n > 1 { k as f64 / (n - 1) as f64 } else { 0.5 };
+    let mut curves: Vec<(GridCurveKind, ZCurve)> = vec![];
+    match family {
+        GridFamily::Cartesian { x_lines, z_lines } => {
+            for k in 0..x_lines {
+                let x = d[0] + (d[1] - d[0]) * at(x_lines, k);
+                curves.push((GridCurveKind::Re(x), Box::new(move |t| C64::new(x, d[2] + (d[3] - d[2]) * t))));
+            }
+            for k in 0..z_lines {
+                let y = d[2] + (d[3] - d[2]) * at(z_lines, k);
+                curves.push((GridCurveKind::Im(y), Box::new(move |t| C64::new(d[0] + (d[1] - d[0]) * t, y))));
+            }
+        }
+        GridFamily::Polar { circles, rays } => {
+            let c = C64::new(0.5 * (d[0] + d[1]), 0.5 * (d[2] + d[3]));
+            let r_max = 0.5 * (d[1] - d[0]).min(d[3] - d[2]);
+            for k in 1..=circles {
+                let r = r_max * k as f64 / circles as f64;
+                curves.push((GridCurveKind::Circle(r), Box::new(move |t| c + C64::from_polar(r, 2.0 * PI * t))));
+            }
+            for k in 0..rays {
+                let phi = 2.0 * PI * k as f64 / rays as f64;
+                curves.push((GridCurveKind::Ray(phi), Box::new(move |t| c + C64::from_polar(r_max * t, phi))));
+            }
+        }
+    }
+    curves
+}
+
+pub fn map_grid<F: Fn(C64) -> C64>(f: F, z_domain: [f64; 4], scale: f32, opts: &ConformalGridOptions)
+    -> Vec<GridCurve> {
+    let wd = opts.w_domain.unwrap_or(z_domain);
+    let width = (wd[1] - wd[0]).max(wd[3] - wd[2]);
+    let center = C64::new(0.5 * (wd[0] + wd[1]), 0.5 * (wd[2] + wd[3]));
+    let visible = |w: C64| w.re.is_finite() && w.im.is_finite() && (w - center).norm() <= opts.clip * width;
+    let scale = scale as f64;
+    let normalize = |w: C64| -> [f32; 3] {
+        let x = scale * (-1.0 + 2.0 * (w.re - wd[0]) / (wd[1] - wd[0]));
+        let z = scale * (-1.0 + 2.0 * (w.im - wd[2]) / (wd[3] - wd[2]));
+        [x as f32, 0.0, z as f32]
+    };
+
+    let samples = opts.samples.max(1);
+    let mut curves: Vec<GridCurve> = vec![];
+    for (kind, curve) in family_curves(opts.family, z_domain) {
+        let mapper = Mapper { w: |t| f(curve(t)), max_step: opts.max_step * width, visible };
+        let mut pieces: Vec<Vec<C64>> = vec![vec![]];
+        let mut a = (0.0, (mapper.w)(0.0));
+        if visible(a.1) {
+            pieces[0].push(a.1);
+        }
+        for s in 1..=samples {
+            let tb = s as f64 / samples as f64;
+            let b = (tb, (mapper.w)(tb));
+            mapper.bridge(a, b, MAX_BISECTIONS, &mut pieces);
+            a = b;
+        }
+        curves.extend(pieces.into_iter().filter(|p| p.len() > 1).map(|p| GridCurve {
+            kind,
+            points: p.into_iter().map(normalize).collect(),
+        }));
+    }
+    curves
+}
+
+// one z-curve composed with f, t -> w
+struct Mapper<W, V> {
+    w: W,
+    max_step: f64,
+    visible: V,
+}
+
+impl<W: Fn(f64) -> C64, V: Fn(C64) -> bool> Mapper<W, V> {
+    // appends the image of the parameter interval (ta, tb] to the last piece, starting a new piece
+    // where the curve breaks; a and b are (t, w) pairs
+    fn bridge(&self, (ta, wa): (f64, C64), (tb, wb): (f64, C64), depth: u32, pieces: &mut Vec<Vec<C64>>) {
+        let (va, vb) = ((self.visible)(wa), (self.visible)(wb));
+        if va && vb && (wb - wa).norm() <= self.max_step {
+            pieces.last_mut().unwrap().push(wb);
+            return;
+        }
+        if depth == 0 || (!va && !vb) {
+            if !pieces.last().unwrap().is_empty() {
+                pieces.push(vec![]);
+            }
+            if vb {
+                pieces.last_mut().unwrap().push(wb);
+            }
+            return;
+        }
+        let tm = 0.5 * (ta + tb);
+        let wm = (self.w)(tm);
+        self.bridge((ta, wa), (tm, wm), depth - 1, pieces);
+        self.bridge((tm, wm), (tb, wb), depth - 1, pieces);
+    }
+}
+
+// grid curves as a line list, two points per segment
+pub fn grid_line_list(curves: &[GridCurve]) -> Vec<[f32; 3]> {
+    curves.iter().flat_map(|c| c.points.windows(2).flat_map(|w| [w[0], w[1]])).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const D: [f64; 4] = [-2.0, 2.0, -2.0, 2.0];
+
+    fn w(p: &[f32; 3]) -> C64 {
+        C64::new(2.0 * p[0] as f64, 2.0 * p[2] as f64)
+    }
+
+    #[test]
+    fn identity_keeps_the_grid() {
+        let curves = map_grid(|z| z, D, 1.0, &ConformalGridOptions::default());
+        assert_eq!(curves.len(), 22);
+        for c in &curves {
+            assert_eq!(c.points.len(), 201);
+            for p in &c.points {
+                match c.kind {
+                    GridCurveKind::Re(x) => assert!((w(p).re - x).abs() < 1e-6),
+                    GridCurveKind::Im(y) => assert!((w(p).im - y).abs() < 1e-6),
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn polar_grid_under_inversion_and_square() {
+        let opts = ConformalGridOptions { family: GridFamily::Polar { circles: 4, rays: 8 }, ..Default::default() };
+
+        // 1/z maps the circle of radius r onto the circle of radius 1/r
+        for c in map_grid(|z| z.inv(), D, 1.0, &opts) {
+            if let GridCurveKind::Circle(r) = c.kind {
+                assert!(c.points.iter().all(|p| (w(p).norm() - 1.0 / r).abs() < 1e-5), "circle {}", r);
+            }
+        }
+
+        // z^2 doubles the angle of every ray
+        let curves = map_grid(|z| z * z, D, 1.0, &opts);
+        for c in curves.iter().filter(|c| matches!(c.kind, GridCurveKind::Ray(_))) {
+            let GridCurveKind::Ray(phi) = c.kind else { unreachable!() };
+            let doubled = C64::from_polar(1.0, 2.0 * phi);
+            assert!(c.points.iter().skip(1).all(|p| (w(p) / w(p).norm() - doubled).norm() < 1e-5));
+        }
+    }
+
+    #[test]
+    fn curves_through_a_pole_are_split_and_clipped() {
+        let opts = ConformalGridOptions::default();
+        let curves = map_grid(|z| z.inv(), D, 1.0, &opts);
+
+        // Re z = 0 and Im z = 0 pass through the pole and break into two pieces each
+        for kind in [GridCurveKind::Re(0.0), GridCurveKind::Im(0.0)] {
+            assert_eq!(curves.iter().filter(|c| c.kind == kind).count(), 2);
+        }
+        for c in &curves {
+            for pair in c.points.windows(2) {
+                assert!((w(&pair[0]) - w(&pair[1])).norm() <= opts.max_step * 4.0 + 1e-9);
+            }
+            assert!(c.points.iter().all(|p| w(p).norm() <= opts.clip * 4.0 + 1e-9));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+#![allow(dead_code)]
+use std::collections::HashMap;
+
+// Marching squares on the sample grid of IComplex3D: (nx + 1) x (nz + 1) values, vertex i * (nz + 1) + j
+// at the i-th x and j-th z sample. Iso-lines are returned as polylines of points on grid edges, so the
+// caller can interpolate positions, heights or anything else stored per vertex. Edges whose end
+// values differ by more than max_jump, or that touch a non-finite value, are never crossed; this keeps
+// phase lines from running along the wrap of arg f and lines from crossing poles.
+
+// the point a + t (b - a) on the grid edge from vertex a to vertex b
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgePoint {
+    pub a: usize,
+    pub b: usize,
+    pub t: f64,
+}
+
+impl EdgePoint {
+    pub fn lerp(&self, pa: [f32; 3], pb: [f32; 3]) -> [f32; 3] {
+        let t = self.t as f32;
+        [pa[0] + t * (pb[0] - pa[0]), pa[1] + t * (pb[1] - pa[1]), pa[2] + t * (pb[2] - pa[2])]
+    }
+}
+
+pub fn marching_squares(values: &[f64], nx: u32, nz: u32, level: f64, max_jump: f64) -> Vec<Vec<EdgePoint>> {
+    let (nx, nz) = (nx as usize, nz as usize);
+    let row = nz + 1;
+    let s = |v: usize| values[v] - level;
+    let crosses = |a: usize, b: usize| {
+        let (sa, sb) = (s(a), s(b));
+        sa.is_finite() && sb.is_finite() && (sa >= 0.0) != (sb >= 0.0) && (sa - sb).abs() <= max_jump
+    };
+
+    // segments between crossed edges, each edge keyed by its vertices in increasing order
+    let mut segments: Vec<[(usize, usize); 2]> = vec![];
+    for i in 0..nx {
+        for j in 0..nz {
+            let v = [i * row + j, (i + 1) * row + j, (i + 1) * row + j + 1, i * row + j + 1];
+            let edges: Vec<usize> = (0..4).filter(|&e| crosses(v[e], v[(e + 1) % 4])).collect();
+            let key = |e: usize| {
+                let (a, b) = (v[e], v[(e + 1) % 4]);
+                (a.min(b), a.max(b))
+            };
+            match edges.len() {
+                2 => segments.push([key(edges[0]), key(edges[1])]),
+                4 => {
+                    // saddle: the center decides which corners are connected
+                    let center = (s(v[0]) + s(v[1]) + s(v[2]) + s(v[3])) / 4.0;
+                    if (center >= 0.0) == (s(v[0]) >= 0.0) {
+                        segments.push([key(0), key(1)]);
+                        segments.push([key(2), key(3)]);
+                    } else {
+                        segments.push([key(3), key(0)]);
+                        segments.push([key(1), key(2)]);
+                    }
+                }
+                // the line ends at an edge that may not be crossed
+                _ => {}
+            }
+        }
+    }
+
+    // chain the segments into polylines, open ones first
+    let mut at_edge: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (k, seg) in segments.iter().enumerate() {
+        for e in seg {
+            at_edge.entry(*e).or_default().push(k);
+        }
+    }
+    let mut used = vec![false; segments.len()];
+    let mut starts: Vec<(usize, (usize, usize))> = vec![];
+    for (k, seg) in segments.iter().enumerate() {
+        for e in seg {
+            if at_edge[e].len() == 1 {
+                starts.push((k, *e));
+            }
+        }
+    }
+    starts.extend(segments.iter().enumerate().map(|(k, seg)| (k, seg[0])));
+
+    let point = |(a, b): (usize, usize)| EdgePoint { a, b, t: s(a) / (s(a) - s(b)) };
+    let mut lines: Vec<Vec<EdgePoint>> = vec![];
+    for (first, start) in starts {
+        if used[first] {
+            continue;
+        }
+        let mut line = vec![point(start)];
+        let (mut k, mut e) = (first, start);
+        loop {
+            used[k] = true;
+            e = if segments[k][0] == e { segments[k][1] } else { segments[k][0] };
+            line.push(point(e));
+            match at_edge[&e].iter().find(|&&n| !used[n]) {
+                Some(&n) => k = n,
+                None => break,
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(n: u32, f: impl Fn(f64, f64) -> f64) -> Vec<f64> {
+        let mut values = vec![];
+        for i in 0..=n {
+            for j in 0..=n {
+                values.push(f(-1.0 + 2.0 * i as f64 / n as f64, -1.0 + 2.0 * j as f64 / n as f64));
+            }
+        }
+        values
+    }
+
+    fn xz(p: &EdgePoint, n: u32) -> (f64, f64) {
+        let row = n as usize + 1;
+        let c = |v: usize| (-1.0 + 2.0 * (v / row) as f64 / n as f64, -1.0 + 2.0 * (v % row) as f64 / n as f64);
+        let (a, b) = (c(p.a), c(p.b));
+        (a.0 + p.t * (b.0 - a.0), a.1 + p.t * (b.1 - a.1))
+    }
+
+    #[test]
+    fn circle_is_one_closed_line() {
+        let n = 40;
+        let lines = marching_squares(&grid(n, |x, z| x * x + z * z), n, n, 0.25, f64::INFINITY);
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert_eq!(line.first(), line.last());
+        assert!(line.len() > 20);
+        for p in line {
+            let (x, z) = xz(p, n);
+            assert!(((x * x + z * z).sqrt() - 0.5).abs() < 0.01, "{:?}", (x, z));
+        }
+    }
+
+    #[test]
+    fn saddle_and_blocked_edges() {
+        // x z = 0.01 has two branches in opposite quadrants
+        let n = 21;
+        let lines = marching_squares(&grid(n, |x, z| x * z), n, n, 0.01, f64::INFINITY);
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.first() != l.last()));
+
+        // a jump across x = 0 is not crossed, so x = 0 itself yields no line
+        let step = grid(n, |x, _| if x < 0.0 { -1.0 } else { 1.0 });
+        assert_eq!(marching_squares(&step, n, n, 0.0, f64::INFINITY).len(), 1);
+        assert!(marching_squares(&step, n, n, 0.0, 1.0).is_empty());
+    }
+}
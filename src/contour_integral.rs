@@ -0,0 +1,373 @@
+#![allow(dead_code)]
+use std::f64::consts::PI;
+use std::rc::Rc;
+use num_complex::Complex;
+
+// Contour integrals of f(z) dz along circles, rectangles, polygonal paths and parametric curves.
+// A contour is a chain of smooth pieces, each parametrized over s in [0, 1], and the integral of
+// f(z(s)) z'(s) over the pieces is computed either by globally adaptive 7/15-point Gauss-Kronrod
+// quadrature (the interval with the largest |K15 - G7| is bisected until the sum of the differences
+// meets the tolerance) or by trapezoidal sums with doubled point counts, which converge geometrically
+// on closed smooth curves such as circles and are Romberg extrapolated on all other pieces. Residues
+// are integrals over small circles divided by 2 pi i. A value of f that is not finite, from a pole on
+// the contour, ends the integration unconverged.
+
+type C64 = Complex<f64>;
+type PathFn = Rc<dyn Fn(f64) -> C64>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuadratureRule {
+    #[default]
+    GaussKronrod,
+    Trapezoid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntegrationOptions {
+    pub rule: QuadratureRule,
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    pub max_evaluations: usize,
+}
+
+impl Default for IntegrationOptions {
+    fn default() -> Self {
+        Self {
+            rule: QuadratureRule::GaussKronrod,
+            abs_tolerance: 1e-10,
+            rel_tolerance: 1e-10,
+            max_evaluations: 200_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContourIntegral {
+    pub value: C64,
+    pub error: f64,         // estimated absolute error of value
+    pub evaluations: usize, // evaluations of f
+    pub converged: bool,    // the error estimate met the tolerance
+}
+
+// one smooth piece of a contour, parametrized over [0, 1]
+#[derive(Clone)]
+enum Piece {
+    Line(C64, C64),
+    Arc { center: C64, radius: f64, angles: [f64; 2] },
+    Curve { z: PathFn, dz: PathFn, t_range: [f64; 2] },
+}
+
+impl Piece {
+    // z(s) and dz/ds
+    fn point(&self, s: f64) -> (C64, C64) {
+        match self {
+            Piece::Line(a, b) => (a + (b - a) * s, b - a),
+            Piece::Arc { center, radius, angles } => {
+                let w = C64::from_polar(*radius, angles[0] + s * (angles[1] - angles[0]));
+                (center + w, C64::new(0.0, angles[1] - angles[0]) * w)
+            }
+            Piece::Curve { z, dz, t_range } => {
+                let t = t_range[0] + s * (t_range[1] - t_range[0]);
+                (z(t), dz(t) * (t_range[1] - t_range[0]))
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Contour {
+    pieces: Vec<Piece>,
+}
+
+impl Contour {
+    // counterclockwise circle
+    pub fn circle(center: C64, radius: f64) -> Self {
+        Self { pieces: vec![Piece::Arc { center, radius, angles: [0.0, 2.0 * PI] }] }
+    }
+
+    // arc from angle a0 to angle a1, clockwise when a1 < a0
+    pub fn arc(center: C64, radius: f64, a0: f64, a1: f64) -> Self {
+        Self { pieces: vec![Piece::Arc { center, radius, angles: [a0, a1] }] }
+    }
+
+    // counterclockwise boundary of the rectangle with opposite corners min and max
+    pub fn rectangle(min: C64, max: C64) -> Self {
+        let corners = [min, C64::new(max.re, min.im), max, C64::new(min.re, max.im)];
+        Self::polygon(&corners, true)
+    }
+
+    // path through the vertices, back to the first one when closed
+    pub fn polygon(vertices: &[C64], closed: bool) -> Self {
+        let mut pieces: Vec<Piece> = vertices.windows(2).map(|w| Piece::Line(w[0], w[1])).collect();
+        if closed && vertices.len() > 2 {
+            pieces.push(Piece::Line(vertices[vertices.len() - 1], vertices[0]));
+        }
+        Self { pieces }
+    }
+
+    // curve z(t) for t in t_range, differentiated numerically
+    pub fn parametric<Z: Fn(f64) -> C64 + 'static>(z: Z, t_range: [f64; 2]) -> Self {
+        let z: PathFn = Rc::new(z);
+        let h = 1e-3 * (t_range[1] - t_range[0]).abs().max(f64::MIN_POSITIVE);
+        let zc = z.clone();
+        // five-point central difference, accurate to O(h^4)
+        let dz: PathFn = Rc::new(move |t| (zc(t - 2.0 * h) - 8.0 * zc(t - h) + 8.0 * zc(t + h) - zc(t + 2.0 * h)) / (12.0 * h));
+        Self { pieces: vec![Piece::Curve { z, dz, t_range }] }
+    }
+
+    // curve z(t) with its derivative dz(t)
+    pub fn parametric_with_derivative<Z, D>(z: Z, dz: D, t_range: [f64; 2]) -> Self
+    where Z: Fn(f64) -> C64 + 'static, D: Fn(f64) -> C64 + 'static {
+        Self { pieces: vec![Piece::Curve { z: Rc::new(z), dz: Rc::new(dz), t_range }] }
+    }
+
+    // the path followed by other, as one contour
+    pub fn then(mut self, other: &Contour) -> Self {
+        self.pieces.extend(other.pieces.iter().cloned());
+        self
+    }
+
+    // the contour sampled as one polyline; lines keep their two ends, curved pieces get samples + 1
+    // points
+    pub fn polyline(&self, samples: usize) -> Vec<C64> {
+        let mut points: Vec<C64> = vec![];
+        for piece in &self.pieces {
+            let n = if let Piece::Line(..) = piece { 1 } else { samples.max(1) };
+            let start = if points.is_empty() { 0 } else { 1 };
+            points.extend((start..=n).map(|k| piece.point(k as f64 / n as f64).0));
+        }
+        points
+    }
+
+    pub fn integrate<F: Fn(C64) -> C64>(&self, f: F, opts: &IntegrationOptions) -> ContourIntegral {
+        let g = |piece: &Piece, s: f64| {
+            let (z, dz) = piece.point(s);
+            f(z) * dz
+        };
+        match opts.rule {
+            QuadratureRule::GaussKronrod => gauss_kronrod(&self.pieces, &g, opts),
+            QuadratureRule::Trapezoid => trapezoid(&self.pieces, &g, opts),
+        }
+    }
+}
+
+// Gauss-Kronrod 7/15 nodes on [-1, 1] (positive half), their Kronrod weights, and the Gauss weights of
+// the odd-indexed nodes
+#[allow(clippy::excessive_precision)]
+const XGK: [f64; 8] = [0.991455371120812639206854697526329, 0.949107912342758524526189684047851,
+    0.864864423359769072789712788640926, 0.741531185599394439863864773280788, 0.586087235467691130294144845693013,
+    0.405845151377397166906606412076961, 0.207784955007898467600689403773245, 0.0];
+#[allow(clippy::excessive_precision)]
+const WGK: [f64; 8] = [0.022935322010529224963732008058970, 0.063092092629978553290700663189204,
+    0.104790010322250183839876322541518, 0.140653259715525918745189590510238, 0.169004726639267902826583426598550,
+    0.190350578064785409913256402421014, 0.204432940075298892414161999234649, 0.209482141084727828012999174891714];
+#[allow(clippy::excessive_precision)]
+const WG: [f64; 4] = [0.129484966168869693270611432679082, 0.279705391489276667901467771423780,
+    0.381830050505118944950369775488975, 0.417959183673469387755102040816327];
+
+// Kronrod estimate and |K15 - G7| over [a, b] of one piece
+fn kronrod<G: Fn(f64) -> C64>(g: &G, a: f64, b: f64) -> (C64, f64) {
+    let (c, h) = (0.5 * (a + b), 0.5 * (b - a));
+    let mid = g(c);
+    let (mut k, mut gauss) = (mid * WGK[7], mid * WG[3]);
+    for j in 0..7 {
+        let pair = g(c - h * XGK[j]) + g(c + h * XGK[j]);
+        k += pair * WGK[j];
+        if j % 2 == 1 {
+            gauss += pair * WG[j / 2];
+        }
+    }
+    (k * h, ((k - gauss) * h).norm())
+}
+
+fn gauss_kronrod<G: Fn(&Piece, f64) -> C64>(pieces: &[Piece], g: &G, opts: &IntegrationOptions) -> ContourIntegral {
+    // (piece, a, b, estimate, error)
+    let mut intervals: Vec<(usize, f64, f64, C64, f64)> = vec![];
+    for (p, piece) in pieces.iter().enumerate() {
+        // closed arcs start as quarters, so that a single panel cannot miss a feature by symmetry
+        let n = if let Piece::Arc { .. } = piece { 4 } else { 1 };
+        for k in 0..n {
+            let (a, b) = (k as f64 / n as f64, (k + 1) as f64 / n as f64);
+            let (v, e) = kronrod(&|s| g(piece, s), a, b);
+            intervals.push((p, a, b, v, e));
+        }
+    }
+    let mut evaluations = 15 * intervals.len();
+    loop {
+        let value: C64 = intervals.iter().map(|i| i.3).sum();
+        let error: f64 = intervals.iter().map(|i| i.4).sum();
+        let tolerance = opts.abs_tolerance.max(opts.rel_tolerance * value.norm());
+        if error.is_nan() || value.re.is_nan() || value.im.is_nan() {
+            return ContourIntegral { value, error: f64::NAN, evaluations, converged: false };
+        }
+        if error <= tolerance || evaluations + 30 > opts.max_evaluations {
+            return ContourIntegral { value, error, evaluations, converged: error <= tolerance };
+        }
+        let worst = (0..intervals.len()).max_by(|&i, &j| intervals[i].4.total_cmp(&intervals[j].4)).unwrap();
+        let (p, a, b, _, _) = intervals.swap_remove(worst);
+        let m = 0.5 * (a + b);
+        if m <= a || m >= b {
+            // the interval cannot be split any further in f64
+            return ContourIntegral { value, error, evaluations, converged: false };
+        }
+        for (lo, hi) in [(a, m), (m, b)] {
+            let (v, e) = kronrod(&|s| g(&pieces[p], s), lo, hi);
+            intervals.push((p, lo, hi, v, e));
+        }
+        evaluations += 30;
+    }
+}
+
+fn trapezoid<G: Fn(&Piece, f64) -> C64>(pieces: &[Piece], g: &G, opts: &IntegrationOptions) -> ContourIntegral {
+    let mut result = ContourIntegral { value: C64::new(0.0, 0.0), error: 0.0, evaluations: 0, converged: true };
+    let budget = opts.max_evaluations / pieces.len().max(1);
+    for piece in pieces {
+        let gp = |s: f64| g(piece, s);
+        // a closed arc is periodic in s, so both ends are the same point
+        let periodic = matches!(piece, Piece::Arc { angles, .. } if ((angles[1] - angles[0]).abs() - 2.0 * PI).abs() < 1e-12);
+        let mut n = 8usize;
+        let mut sum: C64 = if periodic { gp(0.0) } else { 0.5 * (gp(0.0) + gp(1.0)) };
+        sum += (1..n).map(|k| gp(k as f64 / n as f64)).sum::<C64>();
+        let mut evaluations = n + 1;
+        let mut estimate = sum / n as f64;
+        // last row of the Romberg table
+        let mut row: Vec<C64> = vec![estimate];
+        loop {
+            // the midpoints of the current panels double the point count
+            let mid: C64 = (0..n).map(|k| gp((k as f64 + 0.5) / n as f64)).sum();
+            evaluations += n;
+            sum += mid;
+            n *= 2;
+            let mut next = sum / n as f64;
+            if !periodic {
+                let mut new_row = vec![next];
+                let mut factor = 1.0;
+                for prev in &row {
+                    factor *= 4.0;
+                    let r = *new_row.last().unwrap();
+                    new_row.push(r + (r - prev) / (factor - 1.0));
+                }
+                next = *new_row.last().unwrap();
+                row = new_row;
+            }
+            let error = (next - estimate).norm();
+            estimate = next;
+            let tolerance = opts.abs_tolerance.max(opts.rel_tolerance * estimate.norm()) / pieces.len() as f64;
+            if error.is_nan() || error <= tolerance || evaluations + n > budget {
+                result.converged &= error <= tolerance;
+                result.error += error;
+                break;
+            }
+        }
+        result.value += estimate;
+        result.evaluations += evaluations;
+    }
+    result
+}
+
+// residue of f at point, from the integral over the circle of the given radius around it; the circle
+// must not enclose or touch any other singularity
+pub fn residue<F: Fn(C64) -> C64>(f: F, point: C64, radius: f64, opts: &IntegrationOptions) -> ContourIntegral {
+    let integral = Contour::circle(point, radius).integrate(f, opts);
+    ContourIntegral {
+        value: integral.value / C64::new(0.0, 2.0 * PI),
+        error: integral.error / (2.0 * PI),
+        ..integral
+    }
+}
+
+pub fn residues<F: Fn(C64) -> C64>(f: F, points: &[C64], radius: f64, opts: &IntegrationOptions) -> Vec<ContourIntegral> {
+    points.iter().map(|&p| residue(&f, p, radius, opts)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex_catalog::c;
+
+    const TWO_PI_I: C64 = C64::new(0.0, 2.0 * PI);
+
+    fn options(rule: QuadratureRule) -> IntegrationOptions {
+        IntegrationOptions { rule, ..Default::default() }
+    }
+
+    #[test]
+    fn cauchy_integrals_with_both_rules() {
+        for rule in [QuadratureRule::GaussKronrod, QuadratureRule::Trapezoid] {
+            let opts = options(rule);
+            let r = Contour::circle(c(0.0, 0.0), 1.0).integrate(|z| z.inv(), &opts);
+            assert!(r.converged && (r.value - TWO_PI_I).norm() < 1e-9, "{:?}: {:?}", rule, r);
+            assert!(r.error < 1e-8);
+
+            // the rectangle encloses the pole at 0.3i but not the one at 3
+            let f = |z: C64| (z - c(0.0, 0.3)).inv() + (z - c(3.0, 0.0)).inv();
+            let r = Contour::rectangle(c(-1.0, -1.0), c(2.0, 1.5)).integrate(f, &opts);
+            assert!(r.converged && (r.value - TWO_PI_I).norm() < 1e-8, "{:?}: {:?}", rule, r);
+
+            // entire functions integrate to zero over closed paths
+            let triangle = Contour::polygon(&[c(0.0, 0.0), c(2.0, 0.5), c(-0.5, 1.5)], true);
+            let r = triangle.integrate(|z| z.exp() * z * z, &opts);
+            assert!(r.converged && r.value.norm() < 1e-8, "{:?}: {:?}", rule, r);
+        }
+
+        // on a circle the trapezoidal sums converge geometrically and need fewer points
+        let gk = Contour::circle(c(0.5, 0.0), 2.0).integrate(|z| z.exp() / z, &options(QuadratureRule::GaussKronrod));
+        let tr = Contour::circle(c(0.5, 0.0), 2.0).integrate(|z| z.exp() / z, &options(QuadratureRule::Trapezoid));
+        assert!((gk.value - TWO_PI_I).norm() < 1e-9 && (tr.value - TWO_PI_I).norm() < 1e-9);
+        assert!(tr.evaluations < gk.evaluations, "{} {}", tr.evaluations, gk.evaluations);
+    }
+
+    #[test]
+    fn open_paths_and_parametric_curves() {
+        let opts = IntegrationOptions::default();
+        // z dz from 0 to 1 + i along two sides of the square is (1 + i)^2 / 2
+        let r = Contour::polygon(&[c(0.0, 0.0), c(1.0, 0.0), c(1.0, 1.0)], false).integrate(|z| z, &opts);
+        assert!((r.value - c(0.0, 1.0)).norm() < 1e-12);
+
+        // sqrt z has an endpoint singularity in its derivative: integral over [0, 1] is 2/3
+        let r = Contour::polygon(&[c(0.0, 0.0), c(1.0, 0.0)], false).integrate(|z| z.sqrt(), &opts);
+        assert!(r.converged && (r.value - c(2.0 / 3.0, 0.0)).norm() < 1e-9, "{:?}", r);
+
+        // an ellipse around the origin, with and without its derivative
+        let ellipse = |t: f64| c(2.0 * t.cos(), 0.5 * t.sin());
+        let numeric = Contour::parametric(ellipse, [0.0, 2.0 * PI]).integrate(|z| z.inv(), &opts);
+        let exact = Contour::parametric_with_derivative(ellipse, |t| c(-2.0 * t.sin(), 0.5 * t.cos()), [0.0, 2.0 * PI])
+            .integrate(|z| z.inv(), &opts);
+        assert!((exact.value - TWO_PI_I).norm() < 1e-9);
+        assert!((numeric.value - TWO_PI_I).norm() < 1e-7, "{:?}", numeric);
+
+        // a semicircle closed by its diameter
+        let half = Contour::arc(c(0.0, 0.0), 1.0, 0.0, PI).then(&Contour::polygon(&[c(-1.0, 0.0), c(1.0, 0.0)], false));
+        let r = half.integrate(|z| (z - c(0.0, 0.5)).inv(), &opts);
+        assert!((r.value - TWO_PI_I).norm() < 1e-9);
+    }
+
+    #[test]
+    fn residues_and_poles_on_the_contour() {
+        let opts = IntegrationOptions::default();
+        // 1/sin z has residues (-1)^k at k pi
+        let rs = residues(|z: C64| z.sin().inv(), &[c(0.0, 0.0), c(PI, 0.0), c(-PI, 0.0)], 0.5, &opts);
+        for (r, expected) in rs.iter().zip([1.0, -1.0, -1.0]) {
+            assert!(r.converged && (r.value - c(expected, 0.0)).norm() < 1e-10, "{:?}", r);
+            assert!(r.error < 1e-9);
+        }
+        // e^z / z^3 has residue 1/2 at its triple pole
+        let r = residue(|z: C64| z.exp() / (z * z * z), c(0.0, 0.0), 0.1, &opts);
+        assert!((r.value - c(0.5, 0.0)).norm() < 1e-9);
+
+        // a pole on the path is reported, not hidden
+        let r = Contour::circle(c(0.0, 0.0), 1.0).integrate(|z| (z - c(1.0, 0.0)).inv(), &opts);
+        assert!(!r.converged);
+    }
+
+    #[test]
+    fn contour_geometry() {
+        let circle = Contour::circle(c(1.0, 1.0), 0.5).polyline(64);
+        assert_eq!(circle.len(), 65);
+        assert!((circle[0] - circle[64]).norm() < 1e-12);
+        assert!(circle.iter().all(|p| ((p - c(1.0, 1.0)).norm() - 0.5).abs() < 1e-12));
+
+        let square = Contour::rectangle(c(0.0, 0.0), c(1.0, 1.0)).polyline(64);
+        assert_eq!(square, vec![c(0.0, 0.0), c(1.0, 0.0), c(1.0, 1.0), c(0.0, 1.0), c(0.0, 0.0)]);
+    }
+}
@@ -0,0 +1,431 @@
+#![allow(dead_code)]
+use bytemuck::{Pod, Zeroable};
+use cgmath::vec2;
+use image::RgbaImage;
+use num_complex::Complex;
+use super::colormap;
+use super::complex_func::{self, Vec2};
+
+// Domain coloring on the CPU, for machines without a GPU and for tests. Pixels are mapped to the plane
+// around the view center as examples/ch03/domain_color_comp.wgsl maps them, and the value is colored
+// in f32 by the ports in complex_func of hsv2Rgb, colormap2Rgb and the other styles in
+// complex_func.wgsl. f itself is evaluated in f64, where the shader has the f32 primitives of
+// complex_func.wgsl, so a single sample per pixel matches the shader only up to f32 rounding: colors
+// can differ next to grid and contour lines, where f is ill-conditioned, and where the f32 primitives
+// overflow (see complex_func.rs). Image domain coloring samples the picture bilinearly with repeat
+// addressing like the shader's sampler, which agrees up to the rounding of the GPU filter. With n
+// samples per axis each pixel averages the colors on an n x n grid of points inside it. Rows are
+// split into bands rendered on separate threads.
+
+type C64 = Complex<f64>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DomainColoring {
+    #[default]
+    Hsv,      // hsv2Rgb: hue from the phase with modulus and grid lines
+    Colormap, // colormap2Rgb: the colormap around the phase, shaded by modulus
+    // hsv2Rgb with its grid exponent (10) and ring strength (0.7) free
+    HsvGrid { sharpness: f32, rings: f32 },
+    // pure phase portrait
+    Phase,
+    // Wegert's enhanced phase portrait; shade in [0, 1] is the depth of the sawtooth shading
+    EnhancedPhase { lines: u32, shade: f32 },
+    // phase portrait with dark lines at |f| = base^k, width a fraction of the level spacing
+    ModulusContours { base: f32, width: f32 },
+    // phase portrait with dark lines at arg f = 2pi k/lines
+    PhaseContours { lines: u32, width: f32 },
+    // phase portrait on Re/Im squares of side size, every other one darkened by shade
+    Checkerboard { size: f32, shade: f32 },
+    // the picture sampled at f, covering the square of side scale around 0 and repeated
+    Image { scale: f32 },
+}
+
+// uniform layout of StyleParams in domain_color_comp.wgsl; the style itself is IntParams.colorSelect
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+pub struct StyleParams {
+    pub lines: f32,
+    pub width: f32,
+    pub shade: f32,
+    pub base: f32,
+    pub sharpness: f32,
+    pub rings: f32,
+    pub size: f32,
+    pub scale: f32,
+}
+
+impl DomainColoring {
+    // every style with default parameters, in the order of id()
+    pub fn styles() -> [DomainColoring; 9] {
+        [
+            DomainColoring::Hsv,
+            DomainColoring::Colormap,
+            DomainColoring::HsvGrid { sharpness: 4.0, rings: 0.5 },
+            DomainColoring::Phase,
+            DomainColoring::EnhancedPhase { lines: 12, shade: 0.4 },
+            DomainColoring::ModulusContours { base: 2.0, width: 0.05 },
+            DomainColoring::PhaseContours { lines: 12, width: 0.05 },
+            DomainColoring::Checkerboard { size: 0.5, shade: 0.5 },
+            DomainColoring::Image { scale: 2.0 },
+        ]
+    }
+
+    // colorSelect in domain_color_comp.wgsl
+    pub fn id(&self) -> u32 {
+        match self {
+            DomainColoring::Hsv => 0,
+            DomainColoring::Colormap => 1,
+            DomainColoring::HsvGrid { .. } => 2,
+            DomainColoring::Phase => 3,
+            DomainColoring::EnhancedPhase { .. } => 4,
+            DomainColoring::ModulusContours { .. } => 5,
+            DomainColoring::PhaseContours { .. } => 6,
+            DomainColoring::Checkerboard { .. } => 7,
+            DomainColoring::Image { .. } => 8,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DomainColoring::Hsv => "hsv",
+            DomainColoring::Colormap => "colormap",
+            DomainColoring::HsvGrid { .. } => "hsv_grid",
+            DomainColoring::Phase => "phase",
+            DomainColoring::EnhancedPhase { .. } => "enhanced_phase",
+            DomainColoring::ModulusContours { .. } => "modulus_contours",
+            DomainColoring::PhaseContours { .. } => "phase_contours",
+            DomainColoring::Checkerboard { .. } => "checkerboard",
+            DomainColoring::Image { .. } => "image",
+        }
+    }
+
+    // the style with default parameters
+    pub fn from_name(name: &str) -> Option<DomainColoring> {
+        Self::styles().into_iter().find(|s| s.name() == name)
+    }
+
+    pub fn gpu_params(&self) -> StyleParams {
+        let mut p = StyleParams::default();
+        match *self {
+            DomainColoring::Hsv | DomainColoring::Colormap | DomainColoring::Phase => {}
+            DomainColoring::HsvGrid { sharpness, rings } => (p.sharpness, p.rings) = (sharpness, rings),
+            DomainColoring::EnhancedPhase { lines, shade } => (p.lines, p.shade) = (lines as f32, shade),
+            DomainColoring::ModulusContours { base, width } => (p.base, p.width) = (base, width),
+            DomainColoring::PhaseContours { lines, width } => (p.lines, p.width) = (lines as f32, width),
+            DomainColoring::Checkerboard { size, shade } => (p.size, p.shade) = (size, shade),
+            DomainColoring::Image { scale } => p.scale = scale,
+        }
+        p
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DomainColor {
+    pub center: [f64; 2],
+    pub scale: f64,        // width of the view in the complex plane
+    pub width: u32,        // in pixels
+    pub height: u32,
+    pub coloring: DomainColoring,
+    pub colormap_name: String,  // for DomainColoring::Colormap, best a cyclic one
+    pub picture: Option<RgbaImage>, // for DomainColoring::Image; black without one
+    pub samples: u32,      // supersampling, samples per pixel along each axis
+    pub threads: usize,    // 0 uses the available parallelism
+}
+
+impl Default for DomainColor {
+    fn default() -> Self {
+        Self {
+            center: [0.0, 0.0],
+            scale: 5.0,
+            width: 512,
+            height: 512,
+            coloring: DomainColoring::Hsv,
+            colormap_name: "twilight".to_string(),
+            picture: None,
+            samples: 1,
+            threads: 0,
+        }
+    }
+}
+
+impl DomainColor {
+    pub fn new(center: [f64; 2], scale: f64, width: u32, height: u32) -> Self {
+        Self { center, scale, width, height, ..Self::default() }
+    }
+
+    // point (x, y) in pixel coordinates, with pixel centers at integers as in the shader
+    pub fn pixel_to_z(&self, x: f64, y: f64) -> C64 {
+        let (w, h, s) = (self.width as f64, self.height as f64, self.scale);
+        C64::new(self.center[0] + s*(x - 0.5*w)/w, self.center[1] - s*(h/w)*(y - 0.5*h)/h)
+    }
+
+    // color of the value f(z)
+    pub fn color(&self, fz: C64) -> [f32; 4] {
+        self.color_with(&colormap::colormap_data(&self.colormap_name), fz)
+    }
+
+    pub fn load_picture(&mut self, path: &str) -> image::ImageResult<()> {
+        self.picture = Some(image::open(path)?.to_rgba8());
+        Ok(())
+    }
+
+    fn color_with(&self, cdata: &[[f32; 3]; 11], fz: C64) -> [f32; 4] {
+        let v: Vec2 = vec2(fz.re as f32, fz.im as f32);
+        match self.coloring {
+            DomainColoring::Hsv => complex_func::hsv2_rgb(v),
+            DomainColoring::Colormap => complex_func::colormap2_rgb(v, cdata),
+            DomainColoring::HsvGrid { sharpness, rings } => complex_func::hsv_grid_rgb(v, sharpness, rings),
+            DomainColoring::Phase => complex_func::phase_rgb(v),
+            DomainColoring::EnhancedPhase { lines, shade } => complex_func::enhanced_phase_rgb(v, lines as f32, shade),
+            DomainColoring::ModulusContours { base, width } => complex_func::modulus_contour_rgb(v, base, width),
+            DomainColoring::PhaseContours { lines, width } => complex_func::phase_contour_rgb(v, lines as f32, width),
+            DomainColoring::Checkerboard { size, shade } => complex_func::checkerboard_rgb(v, size, shade),
+            DomainColoring::Image { scale } => match &self.picture {
+                Some(picture) => sample_picture(picture, complex_func::image_uv(v, scale)),
+                None => [0.0, 0.0, 0.0, 1.0],
+            },
+        }
+    }
+
+    fn thread_count(&self) -> usize {
+        let n = match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        n.clamp(1, self.height.max(1) as usize)
+    }
+
+    // RGBA8 pixels of f, row by row from the top
+    pub fn render_rgba<F: Fn(C64) -> C64 + Sync>(&self, f: F) -> Vec<u8> {
+        if self.coloring == DomainColoring::Colormap {
+            colormap::warn_if_not_cyclic(&self.colormap_name);
+        }
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let (w, h) = (self.width as usize, self.height as usize);
+        let mut data = vec![0u8; 4 * w * h];
+        if data.is_empty() {
+            return data;
+        }
+        let rows = h.div_ceil(self.thread_count());
+        let (f, cdata) = (&f, &cdata);
+        std::thread::scope(|s| {
+            for (k, band) in data.chunks_mut(4 * w * rows).enumerate() {
+                s.spawn(move || {
+                    for (i, px) in band.chunks_exact_mut(4).enumerate() {
+                        let (x, y) = (i % w, k * rows + i / w);
+                        let c = self.pixel_color(f, cdata, x as f64, y as f64);
+                        px.copy_from_slice(&complex_func::rgba8(c));
+                    }
+                });
+            }
+        });
+        data
+    }
+
+    pub fn save_png<F: Fn(C64) -> C64 + Sync>(&self, path: &str, f: F) -> image::ImageResult<()> {
+        image::save_buffer(path, &self.render_rgba(f), self.width, self.height, image::ColorType::Rgba8)
+    }
+
+    fn pixel_color<F: Fn(C64) -> C64>(&self, f: &F, cdata: &[[f32; 3]; 11], x: f64, y: f64) -> [f32; 4] {
+        let n = self.samples.max(1);
+        if n == 1 {
+            return self.color_with(cdata, f(self.pixel_to_z(x, y)));
+        }
+        let mut sum = [0.0f32; 4];
+        for j in 0..n {
+            for i in 0..n {
+                let (dx, dy) = ((i as f64 + 0.5)/n as f64 - 0.5, (j as f64 + 0.5)/n as f64 - 0.5);
+                let c = self.color_with(cdata, f(self.pixel_to_z(x + dx, y + dy)));
+                for k in 0..4 {
+                    sum[k] += c[k];
+                }
+            }
+        }
+        sum.map(|v| v / (n * n) as f32)
+    }
+}
+
+// picture for image domain coloring when none is given: one color per quadrant of the plane (red,
+// green, blue, yellow counterclockwise from Re, Im > 0) with dark lines on an 8 x 8 grid
+pub fn grid_picture(size: u32) -> RgbaImage {
+    let size = size.max(8);
+    let line = (size / 64).max(1);
+    RgbaImage::from_fn(size, size, |x, y| {
+        let quadrant = [[[60, 200, 60], [230, 50, 50]], [[50, 80, 230], [240, 220, 40]]];
+        let rgb = quadrant[(2 * y / size) as usize][(2 * x / size) as usize];
+        let cell = size / 8;
+        let dark = x % cell < line || y % cell < line;
+        let [r, g, b] = if dark { rgb.map(|v| v / 4) } else { rgb };
+        image::Rgba([r, g, b, 255])
+    })
+}
+
+// bilinear sample at texture coordinates uv with repeat addressing, as a linear filtering sampler
+// reads an Rgba8Unorm texture
+fn sample_picture(picture: &RgbaImage, uv: [f32; 2]) -> [f32; 4] {
+    let (w, h) = picture.dimensions();
+    if w == 0 || h == 0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+    let (x, y) = (uv[0] * w as f32 - 0.5, uv[1] * h as f32 - 0.5);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let texel = |i: f32, j: f32| {
+        let p = picture.get_pixel((i as i64).rem_euclid(w as i64) as u32, (j as i64).rem_euclid(h as i64) as u32);
+        p.0.map(|v| v as f32 / 255.0)
+    };
+    let (c00, c10, c01, c11) = (texel(x0, y0), texel(x0 + 1.0, y0), texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0));
+    let mut c = [0.0f32; 4];
+    for k in 0..4 {
+        let top = c00[k] + fx * (c10[k] - c00[k]);
+        let bottom = c01[k] + fx * (c11[k] - c01[k]);
+        c[k] = top + fy * (bottom - top);
+    }
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rgba: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let i = 4 * (y * width + x) as usize;
+        [rgba[i], rgba[i + 1], rgba[i + 2], rgba[i + 3]]
+    }
+
+    #[test]
+    fn pixels_follow_the_shader_mapping() {
+        let dc = DomainColor::new([1.0, -0.5], 4.0, 64, 32);
+        assert_eq!(dc.pixel_to_z(32.0, 16.0), C64::new(1.0, -0.5));
+        assert_eq!(dc.pixel_to_z(0.0, 0.0), C64::new(-1.0, 0.5));
+        // one pixel is scale/width wide in both directions
+        assert_eq!(dc.pixel_to_z(33.0, 17.0) - dc.pixel_to_z(32.0, 16.0), C64::new(0.0625, -0.0625));
+    }
+
+    #[test]
+    fn single_samples_match_the_color_functions() {
+        let f = |z: C64| (z - 1.0) / (z * z + z + 1.0);
+        for coloring in [DomainColoring::Hsv, DomainColoring::Colormap] {
+            let dc = DomainColor { coloring, colormap_name: "cool".to_string(), ..DomainColor::new([0.0, 0.0], 5.0, 40, 30) };
+            let rgba = dc.render_rgba(f);
+            assert_eq!(rgba.len(), 4 * 40 * 30);
+            let cdata = colormap::colormap_data("cool");
+            for (x, y) in [(0, 0), (20, 15), (39, 29), (7, 22)] {
+                let z = dc.pixel_to_z(x as f64, y as f64);
+                let v = vec2(f(z).re as f32, f(z).im as f32);
+                let c = match coloring {
+                    DomainColoring::Hsv => complex_func::hsv2_rgb(v),
+                    _ => complex_func::colormap2_rgb(v, &cdata),
+                };
+                assert_eq!(at(&rgba, 40, x, y), complex_func::rgba8(c));
+            }
+        }
+    }
+
+    #[test]
+    fn threads_do_not_change_the_image() {
+        let f = |z: C64| z.sin() / z;
+        let one = DomainColor { threads: 1, ..DomainColor::new([0.0, 0.0], 6.0, 37, 23) };
+        let many = DomainColor { threads: 7, ..one.clone() };
+        let all = DomainColor { threads: 0, ..one.clone() };
+        let image = one.render_rgba(f);
+        assert_eq!(many.render_rgba(f), image);
+        assert_eq!(all.render_rgba(f), image);
+        assert!(DomainColor::new([0.0, 0.0], 1.0, 0, 0).render_rgba(f).is_empty());
+    }
+
+    #[test]
+    fn supersampling_averages_inside_the_pixel() {
+        // a constant function gives the same color for any sample count
+        let dc = DomainColor { samples: 3, ..DomainColor::new([0.0, 0.0], 2.0, 16, 16) };
+        let c = complex_func::rgba8(dc.color(C64::new(0.3, 0.7)));
+        assert!(dc.render_rgba(|_| C64::new(0.3, 0.7)).chunks(4).all(|p| p == c));
+
+        // on the identity the supersampled pixel is the mean over its sample grid
+        let rgba = dc.render_rgba(|z| z);
+        let mut sum = [0.0f32; 4];
+        for j in 0..3 {
+            for i in 0..3 {
+                let z = dc.pixel_to_z(5.0 + (i as f64 - 1.0) / 3.0, 9.0 + (j as f64 - 1.0) / 3.0);
+                let c = dc.color(z);
+                for k in 0..4 {
+                    sum[k] += c[k] / 9.0;
+                }
+            }
+        }
+        let pixel = at(&rgba, 16, 5, 9);
+        for k in 0..4 {
+            assert!((pixel[k] as f32 - 255.0 * sum[k]).abs() <= 1.0, "{:?} {:?}", pixel, sum);
+        }
+    }
+
+    #[test]
+    fn styles_ids_names_and_gpu_params() {
+        for (k, style) in DomainColoring::styles().iter().enumerate() {
+            assert_eq!(style.id(), k as u32);
+            assert_eq!(DomainColoring::from_name(style.name()), Some(*style));
+        }
+        assert_eq!(DomainColoring::from_name("sepia"), None);
+
+        let p = DomainColoring::EnhancedPhase { lines: 8, shade: 0.3 }.gpu_params();
+        assert_eq!((p.lines, p.shade, p.width), (8.0, 0.3, 0.0));
+        let p = DomainColoring::HsvGrid { sharpness: 10.0, rings: 0.7 }.gpu_params();
+        assert_eq!((p.sharpness, p.rings), (10.0, 0.7));
+        assert_eq!(std::mem::size_of::<StyleParams>(), 32);
+
+        // every style renders, and the default grid parameters give back hsv2Rgb
+        let dc = DomainColor::new([0.0, 0.0], 4.0, 24, 16);
+        let f = |z: C64| z * z * z - 1.0;
+        let hsv = dc.render_rgba(f);
+        for coloring in DomainColoring::styles() {
+            assert_eq!(DomainColor { coloring, ..dc.clone() }.render_rgba(f).len(), hsv.len());
+        }
+        let grid = DomainColor { coloring: DomainColoring::HsvGrid { sharpness: 10.0, rings: 0.7 }, ..dc.clone() };
+        assert_eq!(grid.render_rgba(f), hsv);
+    }
+
+    #[test]
+    fn image_domain_coloring_samples_the_picture() {
+        // 2 x 2 picture: red, green / blue, white
+        let picture = RgbaImage::from_raw(2, 2, vec![
+            255, 0, 0, 255, 0, 255, 0, 255,
+            0, 0, 255, 255, 255, 255, 255, 255,
+        ]).unwrap();
+        assert_eq!(sample_picture(&picture, [0.25, 0.25]), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(sample_picture(&picture, [0.75, 0.75]), [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(sample_picture(&picture, [0.5, 0.25]), [0.5, 0.5, 0.0, 1.0]);
+        // repeat addressing blends across the edge
+        assert_eq!(sample_picture(&picture, [0.0, 0.25]), [0.5, 0.5, 0.0, 1.0]);
+
+        // with the identity the upper left quarter of the square of side 2 shows the red texel
+        let mut dc = DomainColor { coloring: DomainColoring::Image { scale: 2.0 }, ..DomainColor::new([0.0, 0.0], 2.0, 8, 8) };
+        assert!(dc.render_rgba(|z| z).chunks(4).all(|p| p == [0, 0, 0, 255]));
+        dc.picture = Some(picture);
+        let rgba = dc.render_rgba(|z| z);
+        assert_eq!(at(&rgba, 8, 2, 2), [255, 0, 0, 255]);
+        assert_eq!(at(&rgba, 8, 6, 6), [255, 255, 255, 255]);
+
+        let grid = grid_picture(256);
+        assert_eq!(grid.dimensions(), (256, 256));
+        assert_eq!(grid.get_pixel(200, 60).0, [230, 50, 50, 255]);
+        assert_eq!(grid.get_pixel(0, 0).0, [15, 50, 15, 255]);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let dc = DomainColor { coloring: DomainColoring::Colormap, samples: 2, ..DomainColor::new([0.0, 0.0], 4.0, 48, 32) };
+        let f = |z: C64| z * z - 1.0;
+        let path = std::env::temp_dir().join("domain_color_test.png");
+        dc.save_png(path.to_str().unwrap(), f).unwrap();
+        let image = image::open(&path).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (48, 32));
+        assert_eq!(image.into_raw(), dc.render_rgba(f));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn shader_validates() {
+        let source = crate::complex_wgsl::compose_shader(include_str!("../examples/ch03/domain_color_comp.wgsl"), None);
+        crate::complex_wgsl::validate_wgsl(&source);
+    }
+}
@@ -0,0 +1,301 @@
+#![allow(dead_code)]
+use bytemuck::{Pod, Zeroable};
+use cgmath::{vec2, InnerSpace};
+use num_complex::Complex;
+use super::colormap;
+use super::complex_func::{c_mul, c_pow, colormap_lerp, pixel_offset, render_rgba8, Vec2};
+
+// Escape-time pictures of z -> z^d + c: the Mandelbrot set (c is the pixel, z starts at c), Julia sets
+// (z starts at the pixel, c is fixed), and their multibrot versions for exponents d other than 2.
+// Escaping pixels get the normalized iteration count mu = n - log_d(ln|z_n| / ln R), which varies
+// continuously across the bands of n and depends on the bailout radius R only through the constant
+// log_d(ln R), and the distance estimate |z_n| ln|z_n| / |dz_n/dc| (dz_n/dz for Julia sets) to the
+// set. Integer exponents are applied by repeated multiplication, others through c_pow. The iteration
+// runs in f32, step by step as examples/ch03/escape_time_comp.wgsl does, so both give the same pixels.
+
+type C64 = Complex<f64>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EscapeSet {
+    Mandelbrot,
+    Julia(C64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EscapeColoring {
+    #[default]
+    Smooth,   // colormap position from the normalized iteration count
+    Distance, // the same, darkened within boundary_width pixels of the set
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Escape {
+    pub count: f32,    // normalized iteration count, >= 0
+    pub distance: f32, // estimated distance to the set
+}
+
+#[derive(Debug, Clone)]
+pub struct EscapeTime {
+    pub set: EscapeSet,
+    pub center: [f32; 2],
+    pub zoom: f32,           // the view is 4/zoom wide
+    pub max_iterations: u32,
+    pub bailout: f32,        // escape radius R
+    pub exponent: f32,       // d > 1
+    pub coloring: EscapeColoring,
+    pub boundary_width: f32, // in pixels, for EscapeColoring::Distance
+    pub colormap_name: String,
+}
+
+// uniform layout of EscapeParams in escape_time_comp.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct EscapeParams {
+    pub center: [f32; 2],
+    pub julia_c: [f32; 2],
+    pub scale: f32,
+    pub width: f32,
+    pub height: f32,
+    pub bailout: f32,
+    pub exponent: f32,
+    pub boundary_width: f32,
+    pub max_iterations: u32,
+    pub julia: u32,
+    pub coloring: u32,
+    pub pad: [u32; 3],
+}
+
+impl Default for EscapeTime {
+    fn default() -> Self {
+        Self {
+            set: EscapeSet::Mandelbrot,
+            center: [-0.5, 0.0],
+            zoom: 1.0,
+            max_iterations: 256,
+            bailout: 256.0,
+            exponent: 2.0,
+            coloring: EscapeColoring::Smooth,
+            boundary_width: 1.0,
+            colormap_name: "jet".to_string(),
+        }
+    }
+}
+
+impl EscapeTime {
+    pub fn mandelbrot() -> Self {
+        Self::default()
+    }
+
+    pub fn julia(c: C64) -> Self {
+        Self { set: EscapeSet::Julia(c), center: [0.0, 0.0], ..Self::default() }
+    }
+
+    // width of the view in the complex plane
+    pub fn scale(&self) -> f32 {
+        4.0 / self.zoom
+    }
+
+    // the exponent the iteration actually uses
+    fn degree(&self) -> f32 {
+        self.exponent.max(1.0 + 1e-3)
+    }
+
+    // z^(d-1), by repeated multiplication for integer d
+    fn pow_d1(&self, z: Vec2) -> Vec2 {
+        let d = self.degree();
+        if d.fract() == 0.0 && d <= 64.0 {
+            let mut p = z;
+            for _ in 2..d as u32 {
+                p = c_mul(p, z);
+            }
+            p
+        } else {
+            c_pow(z, d - 1.0)
+        }
+    }
+
+    // normalized count and distance estimate of the point p, or None inside the set
+    pub fn iterate(&self, p: Vec2) -> Option<Escape> {
+        let d = self.degree();
+        let (c, mandelbrot) = match self.set {
+            EscapeSet::Mandelbrot => (p, 1.0),
+            EscapeSet::Julia(c) => (vec2(c.re as f32, c.im as f32), 0.0),
+        };
+        let (mut z, mut dz) = (p, vec2(1.0, 0.0));
+        let r2 = self.bailout * self.bailout;
+        for i in 0..self.max_iterations {
+            let m2 = z.magnitude2();
+            if m2 > r2 {
+                let m = m2.sqrt();
+                let count = i as f32 - ((m.ln() / self.bailout.ln()).ln() / d.ln());
+                return Some(Escape { count: count.max(0.0), distance: m * m.ln() / dz.magnitude() });
+            }
+            let zd1 = self.pow_d1(z);
+            dz = d * c_mul(zd1, dz) + vec2(mandelbrot, 0.0);
+            z = c_mul(zd1, z) + c;
+        }
+        None
+    }
+
+    // pixel (x, y) of a width x height image, mapped like the compute shaders map theirs
+    pub fn pixel_to_z(&self, x: u32, y: u32, width: u32, height: u32) -> Vec2 {
+        vec2(self.center[0], self.center[1]) + pixel_offset(self.scale(), x, y, width, height)
+    }
+
+    // color of a point, with pixel the width of one pixel in the complex plane
+    pub fn color(&self, p: Vec2, pixel: f32) -> [f32; 4] {
+        self.color_with(&colormap::colormap_data(&self.colormap_name), p, pixel)
+    }
+
+    fn color_with(&self, cdata: &[[f32; 3]; 11], p: Vec2, pixel: f32) -> [f32; 4] {
+        match self.iterate(p) {
+            Some(e) => {
+                let t = (1.0 + e.count).ln() / (1.0 + self.max_iterations as f32).ln();
+                let rgb = colormap_lerp(cdata, t);
+                let b = match self.coloring {
+                    EscapeColoring::Smooth => 1.0,
+                    EscapeColoring::Distance => (e.distance / (self.boundary_width * pixel)).clamp(0.0, 1.0),
+                };
+                [rgb[0] * b, rgb[1] * b, rgb[2] * b, 1.0]
+            }
+            None => [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    // RGBA8 pixels, row by row from the top
+    pub fn render_rgba(&self, width: u32, height: u32) -> Vec<u8> {
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let pixel = self.scale() / width as f32;
+        render_rgba8(width, height, |x, y| self.color_with(&cdata, self.pixel_to_z(x, y, width, height), pixel))
+    }
+
+    pub fn save_png(&self, path: &str, width: u32, height: u32) -> image::ImageResult<()> {
+        image::save_buffer(path, &self.render_rgba(width, height), width, height, image::ColorType::Rgba8)
+    }
+
+    pub fn gpu_params(&self, width: u32, height: u32) -> EscapeParams {
+        let (julia, c) = match self.set {
+            EscapeSet::Mandelbrot => (0, [0.0, 0.0]),
+            EscapeSet::Julia(c) => (1, [c.re as f32, c.im as f32]),
+        };
+        EscapeParams {
+            center: self.center,
+            julia_c: c,
+            scale: self.scale(),
+            width: width as f32,
+            height: height as f32,
+            bailout: self.bailout,
+            exponent: self.degree(),
+            boundary_width: self.boundary_width,
+            max_iterations: self.max_iterations,
+            julia,
+            coloring: self.coloring as u32,
+            pad: [0; 3],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex_wgsl::{compose_shader, validate_wgsl};
+
+    #[test]
+    fn inside_and_outside_the_mandelbrot_set() {
+        let m = EscapeTime::mandelbrot();
+        for p in [vec2(0.0, 0.0), vec2(-1.0, 0.0), vec2(-0.1, 0.6), vec2(0.25, 0.0)] {
+            assert!(m.iterate(p).is_none(), "{:?}", p);
+        }
+        for p in [vec2(0.5, 0.0), vec2(-2.1, 0.0), vec2(0.0, 1.1), vec2(300.0, 0.0)] {
+            assert!(m.iterate(p).is_some(), "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn normalized_count_is_continuous() {
+        let m = EscapeTime::mandelbrot();
+        let count = |x: f32| m.iterate(vec2(x, 0.0)).unwrap().count;
+        let mut prev = count(0.5);
+        for k in 1..=1500 {
+            let next = count(0.5 + k as f32 * 1e-3);
+            assert!(next <= prev + 1e-3 && prev - next < 0.05, "{} {} at {}", prev, next, k);
+            prev = next;
+        }
+
+        // a larger bailout only shifts it by log_2(ln R' / ln R)
+        let mut big = EscapeTime { bailout: 1.0e4, ..EscapeTime::mandelbrot() };
+        for p in [vec2(0.4, 0.3), vec2(-0.8, 0.2), vec2(-1.3, 0.3)] {
+            let (a, b) = (m.iterate(p).unwrap().count, big.iterate(p).unwrap().count);
+            let shift = (1.0e4f32.ln() / 256f32.ln()).ln() / 2f32.ln();
+            assert!((b - a - shift).abs() < 0.05, "{:?}: {} {}", p, a, b);
+        }
+        big.exponent = 3.0;
+        assert!(big.iterate(vec2(0.7, 0.0)).unwrap().count > 0.0);
+    }
+
+    #[test]
+    fn distance_estimates() {
+        // on the real axis beyond the cusp the distance to the Mandelbrot set is c - 1/4
+        let m = EscapeTime::mandelbrot();
+        for x in [0.5f32, 1.0, 2.0] {
+            let d = m.iterate(vec2(x, 0.0)).unwrap().distance;
+            let ratio = d / (x - 0.25);
+            assert!(ratio > 0.25 && ratio < 4.0, "{} at {}", ratio, x);
+        }
+
+        // the Julia set of z^2 is the unit circle, where the estimate is exactly |z| ln|z|
+        let j = EscapeTime::julia(C64::new(0.0, 0.0));
+        for p in [vec2(1.5, 0.0), vec2(0.3, -1.2), vec2(-1.05, 0.1)] {
+            let m = p.magnitude();
+            let d = j.iterate(p).unwrap().distance;
+            assert!((d / (m * m.ln()) - 1.0).abs() < 1e-3, "{:?}: {}", p, d);
+            assert!(j.iterate(0.9 * p / m).is_none());
+        }
+    }
+
+    #[test]
+    fn multibrot_symmetry_and_fractional_exponents() {
+        // the cubic multibrot set is symmetric under c -> -c
+        let m = EscapeTime { exponent: 3.0, ..EscapeTime::mandelbrot() };
+        for p in [vec2(0.6, 0.5), vec2(0.1, 1.2), vec2(1.1, 0.0)] {
+            let (a, b) = (m.iterate(p).map(|e| e.count), m.iterate(-p).map(|e| e.count));
+            match (a, b) {
+                (Some(a), Some(b)) => assert!((a - b).abs() < 1e-3, "{:?}: {} {}", p, a, b),
+                _ => assert_eq!(a, b),
+            }
+        }
+
+        // a fractional exponent close to 2 goes through c_pow and nearly agrees with the quadratic set
+        let q = EscapeTime::mandelbrot();
+        let f = EscapeTime { exponent: 2.0001, ..EscapeTime::mandelbrot() };
+        for p in [vec2(0.5, 0.0), vec2(0.3, 0.8), vec2(-2.2, 0.1)] {
+            let (a, b) = (q.iterate(p).unwrap().count, f.iterate(p).unwrap().count);
+            assert!((a - b).abs() < 0.05, "{:?}: {} {}", p, a, b);
+        }
+    }
+
+    #[test]
+    fn pixels_and_png() {
+        let mut m = EscapeTime::mandelbrot();
+        m.coloring = EscapeColoring::Distance;
+        let rgba = m.render_rgba(64, 48);
+        assert_eq!(rgba.len(), 4 * 64 * 48);
+        // the center pixel (-0.5, 0) is inside, the corner is not
+        let at = |x: usize, y: usize| &rgba[4 * (y * 64 + x)..4 * (y * 64 + x) + 4];
+        assert_eq!(at(32, 24), &[0, 0, 0, 255]);
+        assert_ne!(at(0, 0), &[0, 0, 0, 255]);
+
+        let path = std::env::temp_dir().join("escape_time_test.png");
+        m.save_png(path.to_str().unwrap(), 64, 48).unwrap();
+        let image = image::open(&path).unwrap().to_rgba8();
+        assert_eq!(image.into_raw(), rgba);
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(std::mem::size_of::<EscapeParams>(), 64);
+    }
+
+    #[test]
+    fn shader_validates() {
+        validate_wgsl(&compose_shader(include_str!("../examples/ch03/escape_time_comp.wgsl"), None));
+    }
+}
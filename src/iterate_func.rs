@@ -0,0 +1,308 @@
+#![allow(dead_code)]
+use bytemuck::{Pod, Zeroable};
+use cgmath::{vec2, InnerSpace};
+use super::colormap;
+use super::complex_func::*;
+
+// CPU reference of examples/ch03/iterate_func_comp.wgsl: z -> f(z, a) applied a given number of times
+// to every pixel. An orbit stops early when the next value leaves the bailout radius or is not finite
+// (tested on the exponent bits, as notFinite in the shader does), and keeps its last value inside;
+// along the way it records the smallest distance to an orbit trap. The
+// result is colored by its final z (hsv2Rgb or colormap2Rgb), by escape time, or by the trap
+// distance. builtin_func mirrors builtinFunc in the shader, primitive by primitive.
+
+// number of built-in functions of the shader
+pub const BUILTIN_COUNT: u32 = 11;
+
+// iterations that show each built-in function best; these used to be hard-coded in the shader
+pub const DEFAULT_ITERATIONS: [u32; BUILTIN_COUNT as usize] = [4, 3, 4, 2, 2, 5, 4, 10, 6, 9, 4];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IterateColoring {
+    #[default]
+    Hsv,        // hsv2Rgb of the final z
+    Colormap,   // colormap2Rgb of the final z
+    EscapeTime, // colormap position from the step at which the orbit escaped, black if it did not
+    OrbitTrap,  // colormap position from the closest approach of the orbit to the trap
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrbitTrap {
+    Point { center: [f32; 2] },
+    Line { point: [f32; 2], angle: f32 },
+    Circle { center: [f32; 2], radius: f32 },
+}
+
+impl OrbitTrap {
+    pub fn distance(&self, z: Vec2) -> f32 {
+        let (_, v) = self.gpu_data();
+        let p = vec2(v[0], v[1]);
+        match self {
+            OrbitTrap::Point { .. } => (z - p).magnitude(),
+            OrbitTrap::Line { .. } => ((z.x - p.x) * v[3] - (z.y - p.y) * v[2]).abs(),
+            OrbitTrap::Circle { .. } => ((z - p).magnitude() - v[2]).abs(),
+        }
+    }
+
+    // trap type and data as the shader reads them: center or point in xy, then the line direction
+    // or the circle radius
+    fn gpu_data(&self) -> (u32, [f32; 4]) {
+        match *self {
+            OrbitTrap::Point { center } => (0, [center[0], center[1], 0.0, 0.0]),
+            OrbitTrap::Line { point, angle } => (1, [point[0], point[1], angle.cos(), angle.sin()]),
+            OrbitTrap::Circle { center, radius } => (2, [center[0], center[1], radius, 0.0]),
+        }
+    }
+}
+
+// the end of an orbit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orbit {
+    pub z: Vec2,       // last value inside the bailout radius
+    pub steps: u32,    // steps taken before escaping, or all of them
+    pub escaped: bool,
+    pub trap: f32,     // smallest trap distance along the orbit, starting point included
+}
+
+#[derive(Debug, Clone)]
+pub struct IterateFunc {
+    pub function: u32,
+    pub iterations: u32,
+    pub bailout: f32,
+    pub coloring: IterateColoring,
+    pub trap: OrbitTrap,
+    pub trap_width: f32, // trap distance mapped onto the end of the colormap
+    pub animate: f32,    // the parameter a of the functions
+    pub scale: f32,
+    pub colormap_name: String,
+}
+
+// uniform layout of IterateParams in iterate_func_comp.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct IterateParams {
+    pub trap: [f32; 4],
+    pub animate: f32,
+    pub width: f32,
+    pub height: f32,
+    pub scale: f32,
+    pub bailout: f32,
+    pub trap_width: f32,
+    pub pad0: [f32; 2],
+    pub function: u32,
+    pub coloring: u32,
+    pub iterations: u32,
+    pub trap_type: u32,
+}
+
+impl Default for IterateFunc {
+    fn default() -> Self {
+        Self {
+            function: 0,
+            iterations: DEFAULT_ITERATIONS[0],
+            bailout: 1.0e10,
+            coloring: IterateColoring::Hsv,
+            trap: OrbitTrap::Point { center: [0.0, 0.0] },
+            trap_width: 1.0,
+            animate: 1.0,
+            scale: 5.0,
+            colormap_name: "jet".to_string(),
+        }
+    }
+}
+
+impl IterateFunc {
+    // built-in function with its default iterations, None beyond the functions of the shader
+    pub fn builtin(function: u32) -> Option<Self> {
+        let iterations = *DEFAULT_ITERATIONS.get(function as usize)?;
+        Some(Self { function, iterations, ..Self::default() })
+    }
+
+    // an unknown function escapes at once, as in the shader
+    pub fn iterate(&self, z: Vec2) -> Orbit {
+        if self.function >= BUILTIN_COUNT {
+            return Orbit { z, steps: 0, escaped: true, trap: self.trap.distance(z) };
+        }
+        self.iterate_with(z, |z| builtin_func(z, self.animate, self.function))
+    }
+
+    // the same iteration for any f, such as one generated from a formula
+    pub fn iterate_with<F: Fn(Vec2) -> Vec2>(&self, mut z: Vec2, f: F) -> Orbit {
+        let r2 = self.bailout * self.bailout;
+        let mut trap = self.trap.distance(z);
+        for i in 0..self.iterations {
+            let next = f(z);
+            if not_finite(next.x) || not_finite(next.y) || next.magnitude2() > r2 {
+                return Orbit { z, steps: i, escaped: true, trap };
+            }
+            z = next;
+            trap = trap.min(self.trap.distance(z));
+        }
+        Orbit { z, steps: self.iterations, escaped: false, trap }
+    }
+
+    pub fn color(&self, cdata: &[[f32; 3]; 11], orbit: &Orbit) -> [f32; 4] {
+        match self.coloring {
+            IterateColoring::Hsv => hsv2_rgb(orbit.z),
+            IterateColoring::Colormap => colormap2_rgb(orbit.z, cdata),
+            IterateColoring::EscapeTime => {
+                if orbit.escaped {
+                    let t = (orbit.steps as f32 + 1.0) / self.iterations as f32;
+                    let rgb = colormap_lerp(cdata, t);
+                    [rgb[0], rgb[1], rgb[2], 1.0]
+                } else {
+                    [0.0, 0.0, 0.0, 1.0]
+                }
+            }
+            IterateColoring::OrbitTrap => {
+                let rgb = colormap_lerp(cdata, orbit.trap / self.trap_width);
+                [rgb[0], rgb[1], rgb[2], 1.0]
+            }
+        }
+    }
+
+    // pixel (x, y) of a width x height image, mapped like the compute shader maps its own
+    pub fn pixel_to_z(&self, x: u32, y: u32, width: u32, height: u32) -> Vec2 {
+        pixel_offset(self.scale, x, y, width, height)
+    }
+
+    // RGBA8 pixels, row by row from the top
+    pub fn render_rgba(&self, width: u32, height: u32) -> Vec<u8> {
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        render_rgba8(width, height, |x, y| self.color(&cdata, &self.iterate(self.pixel_to_z(x, y, width, height))))
+    }
+
+    pub fn gpu_params(&self, width: u32, height: u32) -> IterateParams {
+        let (trap_type, trap) = self.trap.gpu_data();
+        IterateParams {
+            trap,
+            animate: self.animate,
+            width: width as f32,
+            height: height as f32,
+            scale: self.scale,
+            bailout: self.bailout,
+            trap_width: self.trap_width,
+            pad0: [0.0; 2],
+            function: self.function,
+            coloring: self.coloring as u32,
+            iterations: self.iterations,
+            trap_type,
+        }
+    }
+}
+
+// builtinFunc of iterate_func_comp.wgsl; ids from BUILTIN_COUNT on leave z as it is there too, and
+// are rejected before iterating
+pub fn builtin_func(z: Vec2, a: f32, id: u32) -> Vec2 {
+    let zz = c_mul(z, z);
+    match id {
+        0 => c_mul(vec2(a, a), c_log(zz)),
+        1 => c_div(c_log(zz - vec2(0.0, a)), c_exp(zz) - vec2(a, 0.0)),
+        2 => c_div(c_cos(z), c_sin(zz - vec2(0.5*a, 0.0))),
+        3 => {
+            let f1 = c_inv(c_pow(z, 4.0) + vec2(0.0, 0.1*a));
+            c_asinh(c_sin(f1))
+        }
+        4 => {
+            let f1 = c_inv(c_pow(z, 6.0) + vec2(0.0, 0.5*a));
+            c_log(c_sin(f1))
+        }
+        5 => c_div(c_mul(vec2(0.0, 1.0), c_cos(z)), c_sin(zz - vec2(a, 0.0))),
+        6 => c_div(c_cos(c_mul(vec2(0.0, 1.0), z)), c_sin(zz - vec2(a, 0.0))),
+        7 => c_div(c_tan(z), c_sin(c_pow(z, 8.0) - vec2(0.5*a, 0.0))),
+        8 => c_inv(z) + c_div(zz, c_sin(c_pow(z, 2.0) - vec2(a, 0.0))),
+        9 => c_conj(z) + c_div(zz, c_sin(c_pow(z, 2.0) - vec2(2.0*a, 0.0))),
+        10 => c_sqrt(c_mul(vec2(0.0, 1.0), z)) + c_div(zz, c_sin(c_pow(z, 2.0) - vec2(2.0*a, 0.0))),
+        _ => z,
+    }
+}
+
+// NaN or inf, by the exponent bits as notFinite in the shader
+fn not_finite(v: f32) -> bool {
+    v.to_bits() & 0x7f80_0000 == 0x7f80_0000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex_wgsl::{compose_shader, validate_wgsl};
+
+    #[test]
+    fn bailout_stops_the_orbit() {
+        // z -> z^2 from 1.5 reaches 1.5^16 > 1e2 on its fourth step
+        let f = IterateFunc { iterations: 10, bailout: 100.0, ..IterateFunc::default() };
+        let orbit = f.iterate_with(vec2(1.5, 0.0), |z| c_mul(z, z));
+        assert!(orbit.escaped);
+        assert_eq!(orbit.steps, 3);
+        assert!((orbit.z.x - 1.5f32.powi(8)).abs() < 1e-3);
+
+        // inside the unit circle it never escapes
+        let orbit = f.iterate_with(vec2(0.5, 0.5), |z| c_mul(z, z));
+        assert!(!orbit.escaped);
+        assert_eq!(orbit.steps, 10);
+
+        // a NaN ends the orbit at the last finite value instead of a black pixel
+        let orbit = f.iterate_with(vec2(0.0, 0.0), |z| c_div(z, z));
+        assert!(orbit.escaped && orbit.steps == 0 && orbit.z == vec2(0.0, 0.0));
+
+        // so does an infinite component, even with an infinite bailout
+        let f = IterateFunc { bailout: f32::INFINITY, ..f };
+        let orbit = f.iterate_with(vec2(1.0, 0.0), |z| vec2(z.x * 1e30, 0.0));
+        assert!(orbit.escaped && orbit.steps == 1 && orbit.z == vec2(1e30, 0.0));
+        assert!(not_finite(f32::NAN) && not_finite(-f32::INFINITY) && !not_finite(f32::MAX));
+    }
+
+    #[test]
+    fn orbit_traps() {
+        let point = OrbitTrap::Point { center: [1.0, 1.0] };
+        let line = OrbitTrap::Line { point: [0.0, 1.0], angle: std::f32::consts::FRAC_PI_4 };
+        let circle = OrbitTrap::Circle { center: [0.0, 0.0], radius: 2.0 };
+        let z = vec2(1.0, -1.0);
+        assert!((point.distance(z) - 2.0).abs() < 1e-6);
+        assert!((line.distance(z) - 3.0 / 2f32.sqrt()).abs() < 1e-6);
+        assert!((circle.distance(z) - (2.0 - 2f32.sqrt())).abs() < 1e-6);
+
+        // the orbit of z -> i z circles through 1, i, -1, -i and passes the point trap at -1
+        let f = IterateFunc { iterations: 4, trap: OrbitTrap::Point { center: [-1.0, 0.0] }, ..IterateFunc::default() };
+        let orbit = f.iterate_with(vec2(1.0, 0.0), |z| c_mul(vec2(0.0, 1.0), z));
+        assert!(orbit.trap < 1e-6);
+    }
+
+    #[test]
+    fn builtin_functions_and_colors() {
+        assert!(IterateFunc::builtin(BUILTIN_COUNT).is_none());
+        let cdata = colormap::colormap_data("jet");
+        for id in 0..BUILTIN_COUNT {
+            let f = IterateFunc::builtin(id).unwrap();
+            assert_eq!(f.iterations, DEFAULT_ITERATIONS[id as usize]);
+            for coloring in [IterateColoring::Hsv, IterateColoring::EscapeTime, IterateColoring::OrbitTrap] {
+                let f = IterateFunc { coloring, ..f.clone() };
+                for p in [vec2(0.3, 0.7), vec2(-1.9, 0.2), vec2(2.2, -2.1)] {
+                    let c = f.color(&cdata, &f.iterate(p));
+                    assert!(c.iter().all(|v| v.is_finite()), "function {} at {:?}: {:?}", id, p, c);
+                }
+            }
+        }
+        // function 0 is a log(z^2) with a = 1, twice from 2
+        let f = IterateFunc { iterations: 2, ..IterateFunc::default() };
+        let w = c_mul(vec2(1.0, 1.0), c_log(c_exp2(vec2(2.0, 0.0))));
+        assert_eq!(f.iterate(vec2(2.0, 0.0)).z, c_mul(vec2(1.0, 1.0), c_log(c_exp2(w))));
+
+        let rgba = f.render_rgba(32, 24);
+        assert_eq!(rgba.len(), 4 * 32 * 24);
+        assert_eq!(std::mem::size_of::<IterateParams>(), 64);
+    }
+
+    #[test]
+    fn unknown_function_escapes_at_once() {
+        let f = IterateFunc { function: BUILTIN_COUNT, coloring: IterateColoring::EscapeTime, ..IterateFunc::default() };
+        let orbit = f.iterate(vec2(0.3, 0.7));
+        assert!(orbit.escaped && orbit.steps == 0 && orbit.z == vec2(0.3, 0.7));
+        assert_eq!(builtin_func(vec2(0.3, 0.7), 1.0, u32::MAX), vec2(0.3, 0.7));
+    }
+
+    #[test]
+    fn shader_validates() {
+        validate_wgsl(&compose_shader(include_str!("../examples/ch03/iterate_func_comp.wgsl"), None));
+    }
+}
@@ -0,0 +1,307 @@
+#![allow(dead_code)]
+use std::f64::consts::PI;
+use num_complex::Complex;
+use super::complex_expr::{Expr, Func};
+
+// Taylor and Laurent coefficients from samples on a circle. With N samples of f on |z - c| = r the
+// FFT gives a_k r^k up to aliasing with a_(k +- N) r^(k +- N), so N/2 - 1 powers on either side are
+// kept. A Laurent series over the annulus r0 < |z - c| < r1 is sampled on the geometric mean radius,
+// where the two halves of the series converge equally fast. The radii of convergence are read off
+// the geometric decay of |a_k| r^k: a least squares fit of its logarithm over the upper half of the
+// coefficients that stand above rounding level. Coefficients that reach rounding level within a few
+// terms (polynomials, finite principal parts) give an infinite outer or a zero inner radius; for an
+// entire function the estimate only bounds the radius from below. The rounding noise of the samples
+// puts an error of about noise/r^k on a_k, which grows without bound away from a small circle, so
+// taylor_series_within samples on half the estimated radius of convergence and truncated drops the
+// coefficients that are at the noise level.
+
+type C64 = Complex<f64>;
+
+// samples below this fraction of max |f| on the circle are rounding noise
+const NOISE_LEVEL: f64 = 1e-13;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaurentSeries {
+    pub center: C64,
+    pub radius: f64,            // radius of the sampling circle
+    pub min_power: i32,
+    pub coefficients: Vec<C64>, // coefficients of (z - center)^(min_power + k)
+    pub noise: f64,             // rounding level of |a_k| r^k
+}
+
+// Taylor coefficients a_0 .. a_(N/2 - 1) around center, sampled on a circle of the given radius
+// inside the disk of convergence; samples is rounded up to a power of two of at least 8
+pub fn taylor_series<F: Fn(C64) -> C64>(f: F, center: C64, radius: f64, samples: usize) -> LaurentSeries {
+    let mut s = circle_series(f, center, radius, samples);
+    s.coefficients.drain(..(-s.min_power) as usize);
+    s.min_power = 0;
+    s
+}
+
+// Taylor coefficients sampled on the largest circle the estimated radius of convergence R allows: a
+// first series on max_radius/10 estimates R, the second is sampled on min(R/2, max_radius)
+pub fn taylor_series_within<F: Fn(C64) -> C64>(f: F, center: C64, max_radius: f64, samples: usize)
+-> LaurentSeries {
+    let estimate = taylor_series(&f, center, 0.1 * max_radius, samples).convergence_radius();
+    taylor_series(f, center, (0.5 * estimate).min(max_radius), samples)
+}
+
+// Laurent coefficients a_(-N/2 + 1) .. a_(N/2 - 1) for the annulus r0 < |z - center| < r1
+pub fn laurent_series<F: Fn(C64) -> C64>(f: F, center: C64, annulus: [f64; 2], samples: usize) -> LaurentSeries {
+    let radius = if annulus[0] > 0.0 { (annulus[0] * annulus[1]).sqrt() } else { 0.5 * annulus[1] };
+    circle_series(f, center, radius, samples)
+}
+
+fn circle_series<F: Fn(C64) -> C64>(f: F, center: C64, radius: f64, samples: usize) -> LaurentSeries {
+    let n = samples.max(8).next_power_of_two();
+    let mut data: Vec<C64> = (0..n).map(|j| f(center + C64::from_polar(radius, 2.0 * PI * j as f64 / n as f64)))
+        .collect();
+    let max_f = data.iter().map(|v| v.norm()).filter(|v| v.is_finite()).fold(0.0, f64::max);
+    fft(&mut data);
+
+    // a_k = F_k / (N r^k), a_-k = F_(N-k) r^k / N, for k < N/2
+    let half = (n / 2) as i32;
+    let coefficients = (-half + 1..half).map(|k| {
+        let v = data[k.rem_euclid(n as i32) as usize] / n as f64;
+        v / radius.powi(k)
+    }).collect();
+    LaurentSeries { center, radius, min_power: -half + 1, coefficients, noise: NOISE_LEVEL * max_f }
+}
+
+// in-place radix-2 FFT, F_k = sum_j f_j e^(-2 pi i j k / N), for a power-of-two length
+fn fft(data: &mut [C64]) {
+    let n = data.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let w = C64::from_polar(1.0, -2.0 * PI / len as f64);
+        for start in (0..n).step_by(len) {
+            let mut wk = C64::new(1.0, 0.0);
+            for k in 0..len / 2 {
+                let a = data[start + k];
+                let b = data[start + k + len / 2] * wk;
+                data[start + k] = a + b;
+                data[start + k + len / 2] = a - b;
+                wk *= w;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+// ratio q with b_k ~ q^k for the scaled magnitudes b_1, b_2, ...; 0 when they fall to the noise level
+// too soon to tell
+fn decay_ratio(b: &[f64], noise: f64) -> f64 {
+    let valid: Vec<(f64, f64)> = b.iter().enumerate()
+        .filter(|(_, &v)| v > noise && v.is_finite())
+        .map(|(i, &v)| ((i + 1) as f64, v.ln()))
+        .collect();
+    let k_max = match valid.last() {
+        Some(&(k, _)) => k,
+        None => return 0.0,
+    };
+    let window: Vec<(f64, f64)> = valid.into_iter().filter(|&(k, _)| k >= 0.5 * k_max).collect();
+    if window.len() < 3 || k_max < 6.0 {
+        return 0.0;
+    }
+    let m = window.len() as f64;
+    let (sk, sl) = window.iter().fold((0.0, 0.0), |acc, &(k, l)| (acc.0 + k, acc.1 + l));
+    let (mk, ml) = (sk / m, sl / m);
+    let (num, den) = window.iter().fold((0.0, 0.0), |acc, &(k, l)| (acc.0 + (k - mk) * (l - ml), acc.1 + (k - mk) * (k - mk)));
+    (num / den).exp()
+}
+
+impl LaurentSeries {
+    pub fn max_power(&self) -> i32 {
+        self.min_power + self.coefficients.len() as i32 - 1
+    }
+
+    // coefficient of (z - center)^n, zero outside the stored powers
+    pub fn coefficient(&self, n: i32) -> C64 {
+        usize::try_from(n - self.min_power).ok()
+            .and_then(|k| self.coefficients.get(k).copied())
+            .unwrap_or(C64::new(0.0, 0.0))
+    }
+
+    // |a_n| r^n for n = 1, 2, ... (sign 1) or n = -1, -2, ... (sign -1)
+    fn scaled(&self, sign: i32) -> Vec<f64> {
+        let n_max = if sign > 0 { self.max_power() } else { -self.min_power };
+        (1..=n_max).map(|k| self.coefficient(sign * k).norm() * self.radius.powi(sign * k)).collect()
+    }
+
+    // outer radius of convergence, infinite when the coefficients vanish
+    pub fn convergence_radius(&self) -> f64 {
+        self.radius / decay_ratio(&self.scaled(1), self.noise)
+    }
+
+    // inner radius of the annulus of convergence, zero for a finite principal part
+    pub fn inner_radius(&self) -> f64 {
+        self.radius * decay_ratio(&self.scaled(-1), self.noise)
+    }
+
+    // the series restricted to the powers min_power ..= max_power, without the coefficients at the
+    // noise level, whose error would be amplified by r^-n away from the sampling circle
+    pub fn truncated(&self, min_power: i32, max_power: i32) -> LaurentSeries {
+        let (lo, hi) = (min_power.max(self.min_power), max_power.min(self.max_power()));
+        let coefficient = |n: i32| {
+            let a = self.coefficient(n);
+            if a.norm() * self.radius.powi(n) > self.noise { a } else { C64::new(0.0, 0.0) }
+        };
+        LaurentSeries {
+            min_power: lo,
+            coefficients: (lo..=hi).map(coefficient).collect(),
+            ..self.clone()
+        }
+    }
+
+    pub fn eval(&self, z: C64) -> C64 {
+        let w = z - self.center;
+        let positive = (0..=self.max_power().max(0)).rev().fold(C64::new(0.0, 0.0), |acc, n| acc * w + self.coefficient(n));
+        let u = w.inv();
+        let negative = (1..=(-self.min_power).max(0)).rev().fold(C64::new(0.0, 0.0), |acc, n| (acc + self.coefficient(-n)) * u);
+        positive + negative
+    }
+
+    // the truncated series as an expression in z, in Horner form, so that IComplex3D and the
+    // compute shaders can plot it like any other formula
+    pub fn to_expr(&self) -> Expr {
+        let w = if self.center == C64::new(0.0, 0.0) {
+            Expr::Z
+        } else {
+            Expr::Add(Box::new(Expr::Z), Box::new(Expr::complex(-self.center)))
+        };
+        let mut expr = Expr::complex(self.coefficient(self.max_power().max(0)));
+        for n in (0..self.max_power().max(0)).rev() {
+            let term = Expr::Mul(Box::new(expr), Box::new(w.clone()));
+            expr = Expr::Add(Box::new(term), Box::new(Expr::complex(self.coefficient(n))));
+        }
+        if self.min_power < 0 {
+            let u = Expr::Call(Func::Inv, Box::new(w));
+            let mut principal = Expr::complex(self.coefficient(self.min_power));
+            for n in self.min_power + 1..0 {
+                let term = Expr::Mul(Box::new(principal), Box::new(u.clone()));
+                principal = Expr::Add(Box::new(term), Box::new(Expr::complex(self.coefficient(n))));
+            }
+            expr = Expr::Add(Box::new(expr), Box::new(Expr::Mul(Box::new(principal), Box::new(u))));
+        }
+        expr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex_catalog::c;
+    use crate::complex_wgsl;
+
+    #[test]
+    fn fft_matches_the_dft() {
+        let data: Vec<C64> = (0..16).map(|j| c((j as f64).sin(), (j * j) as f64 * 0.1)).collect();
+        let mut out = data.clone();
+        fft(&mut out);
+        for (k, v) in out.iter().enumerate() {
+            let dft: C64 = data.iter().enumerate()
+                .map(|(j, &f)| f * C64::from_polar(1.0, -2.0 * PI * (j * k) as f64 / 16.0)).sum();
+            assert!((v - dft).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn taylor_coefficients_and_radii() {
+        // 1/(1 - z) = sum z^k
+        let s = taylor_series(|z| (c(1.0, 0.0) - z).inv(), c(0.0, 0.0), 0.5, 128);
+        assert_eq!((s.min_power, s.max_power()), (0, 63));
+        // rounding errors of the samples are amplified by r^-k
+        for k in 0..30 {
+            assert!((s.coefficient(k) - c(1.0, 0.0)).norm() < 1e-14 * 2f64.powi(k), "a_{} = {}", k, s.coefficient(k));
+        }
+        assert!((s.convergence_radius() - 1.0).abs() < 1e-3);
+
+        // 1/(1 + z^2) around 1/2 reaches to the poles at +-i
+        let s = taylor_series(|z| (c(1.0, 0.0) + z * z).inv(), c(0.5, 0.0), 0.6, 128);
+        assert!((s.convergence_radius() - 1.25f64.sqrt()).abs() < 1e-2, "{}", s.convergence_radius());
+
+        // tan has only odd powers, and poles at +-pi/2
+        let s = taylor_series(|z| z.tan(), c(0.0, 0.0), 1.0, 128);
+        assert!((s.coefficient(3) - c(1.0 / 3.0, 0.0)).norm() < 1e-10);
+        assert!(s.coefficient(2).norm() < 1e-12);
+        assert!((s.convergence_radius() - 0.5 * PI).abs() < 1e-2, "{}", s.convergence_radius());
+
+        // a polynomial has no finite radius
+        let s = taylor_series(|z| z * z * z - z, c(0.3, 0.1), 1.0, 64);
+        assert!(s.convergence_radius().is_infinite());
+        // beyond the sampling circle the rounding noise of the high powers would take over
+        let cubic = c(8.0, 0.0) * c(1.0, -0.5).powi(3) - c(2.0, -1.0);
+        assert!((s.truncated(0, 3).eval(c(2.0, -1.0)) - cubic).norm() < 1e-10);
+        assert!((s.truncated(0, 63).eval(c(2.0, -1.0)) - cubic).norm() < 1e-10);
+    }
+
+    #[test]
+    fn noise_does_not_grow_away_from_the_circle() {
+        // exp is entire; its truncations converge at 2 + 2i instead of blowing up the noise in a_k
+        let z = c(2.0, 2.0);
+        let error = |s: &LaurentSeries, n: i32| (s.truncated(0, n).eval(z) - z.exp()).norm();
+        let small = taylor_series(|z| z.exp(), c(0.0, 0.0), 0.2, 128);
+        for n in [8, 16, 32] {
+            assert!(error(&small, n) < 0.05, "order {}: {}", n, error(&small, n));
+        }
+
+        // sampled on the largest circle inside the domain the high orders are accurate as well
+        let s = taylor_series_within(|z| z.exp(), c(0.0, 0.0), 2.0, 128);
+        assert_eq!(s.radius, 2.0);
+        assert!((error(&s, 8) - error(&small, 8)).abs() < 1e-6);
+        assert!(error(&s, 16) < 1e-6 && error(&s, 32) < 1e-9, "{} {}", error(&s, 16), error(&s, 32));
+
+        // with a pole at 1 the circle stays at half the distance to it
+        let s = taylor_series_within(|z| (c(1.0, 0.0) - z).inv(), c(0.0, 0.0), 2.0, 128);
+        assert!((s.radius - 0.5).abs() < 1e-3, "{}", s.radius);
+        assert!((s.truncated(0, 32).eval(c(0.5, 0.0)) - c(2.0, 0.0)).norm() < 1e-8);
+    }
+
+    #[test]
+    fn laurent_coefficients_on_annuli() {
+        // 1/(z (z - 2)) = -1/(2z) - sum z^k / 2^(k + 2) for 0 < |z| < 2
+        let s = laurent_series(|z| (z * (z - c(2.0, 0.0))).inv(), c(0.0, 0.0), [0.5, 1.5], 64);
+        assert!((s.coefficient(-1) - c(-0.5, 0.0)).norm() < 1e-12);
+        assert!(s.coefficient(-2).norm() < 1e-12);
+        for k in 0..20 {
+            assert!((s.coefficient(k) + c(0.5f64.powi(k + 2), 0.0)).norm() < 1e-12);
+        }
+        assert_eq!(s.inner_radius(), 0.0);
+        assert!((s.convergence_radius() - 2.0).abs() < 1e-2);
+
+        // 1/((z - 1/2)(z - 3)) converges on 1/2 < |z| < 3
+        let s = laurent_series(|z| ((z - c(0.5, 0.0)) * (z - c(3.0, 0.0))).inv(), c(0.0, 0.0), [0.5, 3.0], 128);
+        assert!((s.inner_radius() - 0.5).abs() < 1e-2, "{}", s.inner_radius());
+        assert!((s.convergence_radius() - 3.0).abs() < 5e-2, "{}", s.convergence_radius());
+        let z = c(0.2, 1.1);
+        assert!((s.eval(z) - ((z - c(0.5, 0.0)) * (z - c(3.0, 0.0))).inv()).norm() < 1e-10);
+    }
+
+    #[test]
+    fn truncated_series_as_expression() {
+        let s = laurent_series(|z| z.exp() / z, c(0.0, 0.0), [0.2, 1.0], 64).truncated(-3, 12);
+        let expr = s.to_expr();
+        for z in [c(0.9, 0.1), c(0.1, -0.4), c(-1.5, 2.0)] {
+            assert!((expr.eval(z, 0.0) - s.eval(z)).norm() < 1e-9 * s.eval(z).norm().max(1.0));
+        }
+        // close to the center the series follows f, far away it does not
+        assert!((s.eval(c(0.9, 0.1)) - c(0.9, 0.1).exp() / c(0.9, 0.1)).norm() < 1e-6);
+        assert!((s.eval(c(8.0, 0.0)) - 8f64.exp() / 8.0).norm() > 1.0);
+
+        let cfunc = complex_wgsl::wgsl_cfunc(&expr);
+        let source = complex_wgsl::compose_shader(include_str!("../examples/ch03/domain_color_comp.wgsl"), Some(&cfunc));
+        complex_wgsl::validate_wgsl(&source);
+    }
+}
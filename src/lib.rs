@@ -2,4 +2,23 @@ pub mod vertex_data;
 pub mod colormap;
 pub mod math_func;
 pub mod surface_data;
-pub mod complex3d_data;
\ No newline at end of file
+pub mod complex3d_data;
+pub mod complex_expr;
+pub mod complex_wgsl;
+pub mod complex_catalog;
+pub mod complex_func;
+pub mod complex_zeros;
+pub mod branch_cut;
+pub mod riemann_surface;
+pub mod contour;
+pub mod conformal_grid;
+pub mod polya_field;
+pub mod newton_fractal;
+pub mod escape_time;
+pub mod iterate_func;
+pub mod laurent_series;
+pub mod contour_integral;
+pub mod special_func;
+pub mod mobius;
+pub mod domain_color;
+pub mod special_func_f32;
\ No newline at end of file
@@ -0,0 +1,376 @@
+#![allow(dead_code)]
+use num_complex::Complex;
+use super::complex_expr::{Expr, Func};
+
+// Mobius transformations f(z) = (a z + b)/(c z + d) stored as the matrix [[a, b], [c, d]].
+// Composition is the matrix product and the inverse is the adjugate, so both hold up to the scalar
+// factor that leaves the map unchanged. The point at infinity is any complex value with an infinite
+// part, and apply() returns C64::new(inf, 0) for it. Classification and animation work on the
+// normalized matrix with det = 1 and Re(trace) >= 0: its eigenvalues are lambda and 1/lambda, and
+// M^t = [sinh(t mu) M + sinh((1 - t) mu) I]/sinh(mu) with mu = log(lambda) is the matrix power along
+// the logarithm, going from the identity at t = 0 to M at t = 1. Choosing the trace with
+// non-negative real part picks the shorter rotation, so elliptic maps turn by at most pi.
+
+type C64 = Complex<f64>;
+
+// relative tolerance for the classification and for c = 0
+const TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mobius {
+    pub a: C64,
+    pub b: C64,
+    pub c: C64,
+    pub d: C64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MobiusKind {
+    Identity,
+    Elliptic,   // trace^2 real in [0, 4): rotation about two fixed points
+    Parabolic,  // trace^2 = 4: a single fixed point
+    Hyperbolic, // trace^2 real > 4: pure dilation between two fixed points
+    Loxodromic, // otherwise: dilation combined with rotation
+}
+
+fn infinity() -> C64 {
+    C64::new(f64::INFINITY, 0.0)
+}
+
+impl Default for Mobius {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mobius {
+    // None for a singular matrix, which maps every z to the same point and has no normalized form
+    pub fn new(a: C64, b: C64, c: C64, d: C64) -> Option<Self> {
+        let m = Self::matrix(a, b, c, d);
+        let det = m.det();
+        (det.norm() != 0.0 && det.is_finite()).then_some(m)
+    }
+
+    // products and scalings of regular matrices, which stay regular
+    fn matrix(a: C64, b: C64, c: C64, d: C64) -> Self {
+        Self { a, b, c, d }
+    }
+
+    pub fn identity() -> Self {
+        let (zero, one) = (C64::new(0.0, 0.0), C64::new(1.0, 0.0));
+        Self::matrix(one, zero, zero, one)
+    }
+
+    // the map sending z[k] to w[k]; None unless both triples are distinct points
+    pub fn from_points(z: [C64; 3], w: [C64; 3]) -> Option<Self> {
+        let s = to_zero_one_infinity(z)?;
+        let t = to_zero_one_infinity(w)?;
+        Some(t.inverse().compose(&s))
+    }
+
+    pub fn det(&self) -> C64 {
+        self.a * self.d - self.b * self.c
+    }
+
+    pub fn trace(&self) -> C64 {
+        self.a + self.d
+    }
+
+    // the same map scaled to det = 1 with Re(trace) >= 0
+    pub fn normalized(&self) -> Self {
+        let s = self.det().sqrt();
+        let mut m = Self::matrix(self.a / s, self.b / s, self.c / s, self.d / s);
+        let tr = m.trace();
+        if tr.re < 0.0 || (tr.re == 0.0 && tr.im < 0.0) {
+            m = Self::matrix(-m.a, -m.b, -m.c, -m.d);
+        }
+        m
+    }
+
+    // self after other, z -> self(other(z))
+    pub fn compose(&self, other: &Self) -> Self {
+        Self::matrix(
+            self.a * other.a + self.b * other.c,
+            self.a * other.b + self.b * other.d,
+            self.c * other.a + self.d * other.c,
+            self.c * other.b + self.d * other.d,
+        )
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self::matrix(self.d, -self.b, -self.c, self.a)
+    }
+
+    pub fn apply(&self, z: C64) -> C64 {
+        if z.is_infinite() {
+            return if self.c == C64::new(0.0, 0.0) { infinity() } else { self.a / self.c };
+        }
+        let den = self.c * z + self.d;
+        if den == C64::new(0.0, 0.0) {
+            return infinity();
+        }
+        (self.a * z + self.b) / den
+    }
+
+    pub fn kind(&self) -> MobiusKind {
+        let m = self.normalized();
+        let scale = m.a.norm().max(m.b.norm()).max(m.c.norm()).max(m.d.norm());
+        if m.b.norm() <= TOLERANCE * scale && m.c.norm() <= TOLERANCE * scale
+            && (m.a - m.d).norm() <= TOLERANCE * scale {
+            return MobiusKind::Identity;
+        }
+        let tr2 = m.trace() * m.trace();
+        let tol = TOLERANCE * scale * scale;
+        if (tr2 - 4.0).norm() <= tol {
+            MobiusKind::Parabolic
+        } else if tr2.im.abs() > tol || tr2.re < -tol {
+            MobiusKind::Loxodromic
+        } else if tr2.re < 4.0 {
+            MobiusKind::Elliptic
+        } else {
+            MobiusKind::Hyperbolic
+        }
+    }
+
+    // the solutions of c z^2 + (d - a) z - b = 0, with infinity when c = 0; both are equal for
+    // parabolic maps and arbitrary for the identity
+    pub fn fixed_points(&self) -> [C64; 2] {
+        let m = self.normalized();
+        let scale = m.a.norm().max(m.b.norm()).max(m.d.norm());
+        if m.c.norm() <= TOLERANCE * scale {
+            let da = m.d - m.a;
+            if da.norm() <= TOLERANCE * scale {
+                return [infinity(), infinity()];
+            }
+            return [m.b / da, infinity()];
+        }
+        let amd = m.a - m.d;
+        let root = (amd * amd + 4.0 * m.b * m.c).sqrt();
+        [(amd + root) / (2.0 * m.c), (amd - root) / (2.0 * m.c)]
+    }
+
+    // the multiplier k = lambda^2 with |k| >= 1, so that f is conjugate to z -> k z
+    pub fn multiplier(&self) -> C64 {
+        let lambda = self.eigenvalue();
+        lambda * lambda
+    }
+
+    // M^t along the matrix logarithm, normalized; power(0) is the identity and power(1) is the map
+    pub fn power(&self, t: f64) -> Self {
+        let m = self.normalized();
+        let mu = self.eigenvalue().ln();
+        let (alpha, beta) = if mu.norm() < TOLERANCE {
+            (C64::new(t, 0.0), C64::new(1.0 - t, 0.0))
+        } else {
+            let s = mu.sinh();
+            ((t * mu).sinh() / s, ((1.0 - t) * mu).sinh() / s)
+        };
+        Self::matrix(alpha * m.a + beta, alpha * m.b, alpha * m.c, alpha * m.d + beta)
+    }
+
+    // (a z + b)/(c z + d) as an expression for IComplex3D::func_expr or complex_wgsl::wgsl_cfunc
+    pub fn to_expr(&self) -> Expr {
+        div(linear(self.a, self.b), linear(self.c, self.d))
+    }
+
+    // the map raised to the power t, so that the existing animation parameter moves from the
+    // identity at t = 0 to the map at t = 1; the factor 1/sinh(mu) cancels between the numerator
+    // and denominator, leaving alpha = 2 sinh(t mu) and beta = 2 sinh((1 - t) mu)
+    pub fn animated_expr(&self) -> Expr {
+        let m = self.normalized();
+        let mu = self.eigenvalue().ln();
+        let (alpha, beta) = if mu.norm() < TOLERANCE {
+            (Expr::T, Expr::Sub(Box::new(Expr::Num(1.0)), Box::new(Expr::T)))
+        } else {
+            let one_minus_t = Expr::Sub(Box::new(Expr::Num(1.0)), Box::new(Expr::T));
+            (two_sinh(mu, Expr::T), two_sinh(mu, one_minus_t))
+        };
+        // (alpha a + beta) z + alpha b over alpha c z + (alpha d + beta)
+        let num = add(mul(add(scaled(&alpha, m.a), Some(beta.clone())), Some(Expr::Z)), scaled(&alpha, m.b));
+        let den = add(mul(scaled(&alpha, m.c), Some(Expr::Z)), add(scaled(&alpha, m.d), Some(beta)));
+        div(num, den)
+    }
+
+    // eigenvalue of the normalized matrix with |lambda| >= 1
+    fn eigenvalue(&self) -> C64 {
+        let tr = self.normalized().trace();
+        let root = (tr * tr - 4.0).sqrt();
+        let (l1, l2) = (0.5 * (tr + root), 0.5 * (tr - root));
+        if l1.norm() >= l2.norm() { l1 } else { l2 }
+    }
+}
+
+// the map sending z[0], z[1], z[2] to 0, 1, infinity
+fn to_zero_one_infinity(z: [C64; 3]) -> Option<Mobius> {
+    let one = C64::new(1.0, 0.0);
+    let zero = C64::new(0.0, 0.0);
+    match (z[0].is_infinite(), z[1].is_infinite(), z[2].is_infinite()) {
+        (false, false, false) => Mobius::new(z[1] - z[2], -z[0] * (z[1] - z[2]), z[1] - z[0], -z[2] * (z[1] - z[0])),
+        (true, false, false) => Mobius::new(zero, z[1] - z[2], one, -z[2]),
+        (false, true, false) => Mobius::new(one, -z[0], one, -z[2]),
+        (false, false, true) => Mobius::new(one, -z[0], zero, z[1] - z[0]),
+        _ => None,
+    }
+}
+
+// exp(mu x) - exp(-mu x)
+fn two_sinh(mu: C64, x: Expr) -> Expr {
+    let exp = |k: C64| Expr::Call(Func::Exp, Box::new(Expr::Mul(Box::new(Expr::complex(k)), Box::new(x.clone()))));
+    Expr::Sub(Box::new(exp(mu)), Box::new(exp(-mu)))
+}
+
+// p z + q, dropping zero terms
+fn linear(p: C64, q: C64) -> Option<Expr> {
+    add(mul(nonzero(p), Some(Expr::Z)), nonzero(q))
+}
+
+fn div(num: Option<Expr>, den: Option<Expr>) -> Expr {
+    Expr::Div(Box::new(num.unwrap_or(Expr::Num(0.0))), Box::new(den.unwrap_or(Expr::Num(0.0))))
+}
+
+// x c, or None when c = 0
+fn scaled(x: &Expr, c: C64) -> Option<Expr> {
+    mul(Some(x.clone()), nonzero(c))
+}
+
+fn nonzero(c: C64) -> Option<Expr> {
+    (c != C64::new(0.0, 0.0)).then(|| Expr::complex(c))
+}
+
+// None stands for zero in add and mul
+fn add(x: Option<Expr>, y: Option<Expr>) -> Option<Expr> {
+    match (x, y) {
+        (Some(x), Some(y)) => Some(Expr::Add(Box::new(x), Box::new(y))),
+        (x, None) => x,
+        (None, y) => y,
+    }
+}
+
+fn mul(x: Option<Expr>, y: Option<Expr>) -> Option<Expr> {
+    Some(Expr::Mul(Box::new(x?), Box::new(y?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex_catalog::c;
+    use crate::complex_wgsl;
+
+    fn assert_close(a: C64, b: C64, tol: f64) {
+        assert!((a - b).norm() <= tol * (1.0 + b.norm()), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn compose_and_inverse_match_the_maps() {
+        let f = Mobius::new(c(1.0, 2.0), c(-0.5, 0.0), c(0.3, -1.0), c(2.0, 0.5)).unwrap();
+        let g = Mobius::new(c(0.0, 1.0), c(1.0, 1.0), c(1.0, 0.0), c(-2.0, 0.0)).unwrap();
+        let z = c(0.7, -0.4);
+        assert_close(f.compose(&g).apply(z), f.apply(g.apply(z)), 1e-14);
+        assert_close(f.inverse().apply(f.apply(z)), z, 1e-14);
+        assert_close(f.compose(&f.inverse()).normalized().a, c(1.0, 0.0), 1e-14);
+
+        // the pole goes to infinity and infinity to a/c
+        assert!(g.apply(c(2.0, 0.0)).is_infinite());
+        assert_close(f.apply(infinity()), f.a / f.c, 1e-15);
+    }
+
+    #[test]
+    fn from_points_maps_three_points_including_infinity() {
+        let z = [c(0.0, 0.0), c(1.0, 1.0), c(-2.0, 0.5)];
+        let w = [c(3.0, 0.0), c(0.0, -1.0), c(1.0, 1.0)];
+        let m = Mobius::from_points(z, w).unwrap();
+        for k in 0..3 {
+            assert_close(m.apply(z[k]), w[k], 1e-13);
+        }
+
+        // the Cayley transform sends 0, 1, infinity to -1, -i, 1
+        let w = [c(-1.0, 0.0), c(0.0, -1.0), c(1.0, 0.0)];
+        let m = Mobius::from_points([c(0.0, 0.0), c(1.0, 0.0), infinity()], w).unwrap();
+        let inv = m.inverse();
+        assert!(inv.apply(w[2]).is_infinite());
+        assert_close(m.apply(c(0.0, 0.0)), w[0], 1e-15);
+        assert_close(m.apply(c(1.0, 0.0)), w[1], 1e-15);
+        assert_close(m.apply(infinity()), w[2], 1e-15);
+
+        assert!(Mobius::from_points([c(1.0, 0.0), c(1.0, 0.0), c(2.0, 0.0)], w).is_none());
+        assert!(Mobius::from_points([infinity(), c(1.0, 0.0), infinity()], w).is_none());
+    }
+
+    #[test]
+    fn singular_matrices_are_rejected() {
+        // (2z + 1)/(4z + 2) is the constant 1/2
+        assert!(Mobius::new(c(2.0, 0.0), c(1.0, 0.0), c(4.0, 0.0), c(2.0, 0.0)).is_none());
+        assert!(Mobius::new(c(0.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(0.0, 0.0)).is_none());
+        assert!(Mobius::new(c(f64::INFINITY, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)).is_none());
+        let m = Mobius::new(c(2.0, 0.0), c(1.0, 0.0), c(4.0, 0.0), c(2.5, 0.0)).unwrap();
+        assert_close(m.normalized().det(), c(1.0, 0.0), 1e-15);
+        assert_eq!(m.kind(), MobiusKind::Hyperbolic);
+    }
+
+    #[test]
+    fn kinds_and_fixed_points() {
+        assert_eq!(Mobius::identity().kind(), MobiusKind::Identity);
+        assert_eq!(Mobius::new(c(2.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(2.0, 0.0)).unwrap().kind(), MobiusKind::Identity);
+
+        // rotation, translation, dilation and spiral, conjugated so that the fixed points are finite
+        let s = Mobius::new(c(1.0, 0.0), c(-1.0, 0.5), c(1.0, 0.0), c(2.0, 0.0)).unwrap();
+        let cases = [
+            (Mobius::new(C64::from_polar(1.0, 0.4), c(0.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)).unwrap(), MobiusKind::Elliptic),
+            (Mobius::new(c(1.0, 0.0), c(1.0, 1.0), c(0.0, 0.0), c(1.0, 0.0)).unwrap(), MobiusKind::Parabolic),
+            (Mobius::new(c(3.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)).unwrap(), MobiusKind::Hyperbolic),
+            (Mobius::new(c(2.0, 1.0), c(0.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)).unwrap(), MobiusKind::Loxodromic),
+        ];
+        for (m, kind) in cases {
+            assert_eq!(m.kind(), kind);
+            let f = s.inverse().compose(&m).compose(&s);
+            assert_eq!(f.kind(), kind);
+            for p in f.fixed_points() {
+                assert_close(f.apply(p), p, 1e-12);
+            }
+            // conjugation keeps the multiplier
+            assert_close(f.multiplier(), m.multiplier(), 1e-12);
+        }
+
+        let [p, q] = cases[0].0.fixed_points();
+        assert_close(p, c(0.0, 0.0), 1e-15);
+        assert!(q.is_infinite());
+        assert!(cases[1].0.fixed_points().iter().all(|p| p.is_infinite()));
+        assert_close(cases[2].0.multiplier(), c(3.0, 0.0), 1e-14);
+    }
+
+    #[test]
+    fn power_goes_from_identity_to_the_map() {
+        let maps = [
+            Mobius::new(c(1.0, 2.0), c(-0.5, 0.0), c(0.3, -1.0), c(2.0, 0.5)).unwrap(),
+            Mobius::new(c(1.0, 0.0), c(1.0, 1.0), c(0.0, 0.0), c(1.0, 0.0)).unwrap(),
+            Mobius::new(c(0.0, 1.0), c(0.0, 0.0), c(0.0, 0.0), c(0.0, -1.0)).unwrap(),
+        ];
+        let z = c(0.3, 0.8);
+        for m in maps {
+            assert_close(m.power(0.0).apply(z), z, 1e-14);
+            assert_close(m.power(1.0).apply(z), m.apply(z), 1e-13);
+            let h = m.power(0.5);
+            assert_close(h.compose(&h).apply(z), m.apply(z), 1e-13);
+            let (p, q) = (m.power(0.3), m.power(0.45));
+            assert_close(p.compose(&q).apply(z), m.power(0.75).apply(z), 1e-13);
+        }
+    }
+
+    #[test]
+    fn expressions_evaluate_the_map_and_compile_to_wgsl() {
+        let m = Mobius::new(c(1.0, 2.0), c(-0.5, 0.0), c(0.3, -1.0), c(2.0, 0.5)).unwrap();
+        let parabolic = Mobius::new(c(1.0, 0.0), c(1.0, 1.0), c(0.0, 0.0), c(1.0, 0.0)).unwrap();
+        let z = c(-0.6, 0.2);
+        assert_close(m.to_expr().eval(z, 0.0), m.apply(z), 1e-14);
+        for f in [m, parabolic] {
+            let expr = f.animated_expr();
+            for t in [0.0, 0.25, 0.6, 1.0] {
+                assert_close(expr.eval(z, t), f.power(t).apply(z), 1e-13);
+            }
+        }
+
+        let cfunc = complex_wgsl::wgsl_cfunc(&m.animated_expr());
+        let source = complex_wgsl::compose_shader(include_str!("../examples/ch03/domain_color_comp.wgsl"), Some(&cfunc));
+        complex_wgsl::validate_wgsl(&source);
+    }
+}
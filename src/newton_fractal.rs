@@ -0,0 +1,369 @@
+#![allow(dead_code)]
+use bytemuck::{Pod, Zeroable};
+use cgmath::{vec2, InnerSpace};
+use num_complex::Complex;
+use super::colormap;
+use super::complex_func::{c_div, c_mul, colormap_lerp, pixel_offset, render_rgba8, Vec2};
+
+// Newton fractals of f = p/q for polynomials p and q (q = 1 for a polynomial). Every pixel starts
+// Newton's method z -= a f/f' (or Halley's z -= a 2ff'/(2f'^2 - ff'')) with relaxation a, and is
+// colored by the zero of p it converges to, darkened by its smoothly interpolated iteration count;
+// pixels that do not converge, or converge elsewhere, are black. The CPU iteration runs in f32 with the
+// complex_func.rs mirrors of cMul and cDiv, step by step as examples/ch03/newton_comp.wgsl does.
+
+type C64 = Complex<f64>;
+
+// highest degree of p and q the shader holds; the constructors reject higher ones
+pub const MAX_DEGREE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NewtonMethod {
+    #[default]
+    Newton,
+    Halley,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewtonFractal {
+    pub numerator: Vec<C64>,   // coefficients of p, constant term first
+    pub denominator: Vec<C64>, // coefficients of q
+    pub roots: Vec<C64>,       // zeros of p, one color each
+    pub method: NewtonMethod,
+    pub relaxation: C64,
+    pub center: [f32; 2],
+    pub scale: f32,            // width of the view in the z-plane
+    pub max_iterations: u32,
+    pub tolerance: f32,        // |step| at which a pixel has converged
+    pub shading: f32,          // brightness exp(-shading * iterations)
+    pub colormap_name: String,
+}
+
+// uniform layout of NewtonParams in newton_comp.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct NewtonParams {
+    pub center: [f32; 2],
+    pub relaxation: [f32; 2],
+    pub scale: f32,
+    pub width: f32,
+    pub height: f32,
+    pub tolerance: f32,
+    pub shading: f32,
+    pub max_iterations: u32,
+    pub method: u32,
+    pub num_degree: u32,
+    pub den_degree: u32,
+    pub root_count: u32,
+    pub pad: [u32; 2],
+}
+
+// uniform layout of Polynomials in newton_comp.wgsl, complex values in xy
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct NewtonPolynomials {
+    pub num: [[f32; 4]; MAX_DEGREE + 1],
+    pub den: [[f32; 4]; MAX_DEGREE + 1],
+    pub roots: [[f32; 4]; MAX_DEGREE],
+}
+
+impl Default for NewtonFractal {
+    fn default() -> Self {
+        // z^3 - 1
+        let third = 2.0 * std::f64::consts::PI / 3.0;
+        Self::from_roots(&[C64::new(1.0, 0.0), C64::from_polar(1.0, third), C64::from_polar(1.0, -third)]).unwrap()
+    }
+}
+
+impl NewtonFractal {
+    // None for more than MAX_DEGREE roots
+    pub fn from_roots(roots: &[C64]) -> Option<Self> {
+        if roots.len() > MAX_DEGREE {
+            return None;
+        }
+        let mut numerator = vec![C64::new(1.0, 0.0)];
+        for &r in roots {
+            // multiply by (z - r)
+            let mut next = vec![C64::new(0.0, 0.0); numerator.len() + 1];
+            for (k, &c) in numerator.iter().enumerate() {
+                next[k + 1] += c;
+                next[k] -= c * r;
+            }
+            numerator = next;
+        }
+        Some(Self {
+            numerator,
+            denominator: vec![C64::new(1.0, 0.0)],
+            roots: roots.to_vec(),
+            method: NewtonMethod::Newton,
+            relaxation: C64::new(1.0, 0.0),
+            center: [0.0, 0.0],
+            scale: 4.0,
+            max_iterations: 64,
+            tolerance: 1e-5,
+            shading: 0.08,
+            colormap_name: "jet".to_string(),
+        })
+    }
+
+    // the zeros are found by Durand-Kerner iteration; None above MAX_DEGREE
+    pub fn from_coefficients(coefficients: &[C64]) -> Option<Self> {
+        let numerator = trim(coefficients);
+        if numerator.len() > MAX_DEGREE + 1 {
+            return None;
+        }
+        let mut f = Self::from_roots(&polynomial_roots(&numerator))?;
+        f.numerator = numerator;
+        Some(f)
+    }
+
+    // None when p or q is above MAX_DEGREE
+    pub fn rational(numerator: &[C64], denominator: &[C64]) -> Option<Self> {
+        let denominator = trim(denominator);
+        if denominator.len() > MAX_DEGREE + 1 {
+            return None;
+        }
+        let mut f = Self::from_coefficients(numerator)?;
+        f.denominator = denominator;
+        Some(f)
+    }
+
+    fn step(&self, z: Vec2) -> Vec2 {
+        let (p, q) = (horner(&self.numerator, z), horner(&self.denominator, z));
+        let f = c_div(p[0], q[0]);
+        let df = c_div(p[1] - c_mul(f, q[1]), q[0]);
+        let s = match self.method {
+            NewtonMethod::Newton => c_div(f, df),
+            NewtonMethod::Halley => {
+                let ddf = c_div(p[2] - 2.0 * c_mul(df, q[1]) - c_mul(f, q[2]), q[0]);
+                c_div(2.0 * c_mul(f, df), 2.0 * c_mul(df, df) - c_mul(f, ddf))
+            }
+        };
+        c_mul(vec2(self.relaxation.re as f32, self.relaxation.im as f32), s)
+    }
+
+    // index of the root reached from z and the smooth iteration count, or None
+    pub fn iterate(&self, mut z: Vec2) -> Option<(usize, f32)> {
+        let mut d_prev = 0.0f32;
+        for i in 0..self.max_iterations {
+            let s = self.step(z);
+            let d = s.magnitude();
+            if d.is_nan() || d > 3.0e38 {
+                return None;
+            }
+            z -= s;
+            if d < self.tolerance {
+                // iteration count interpolated in log |step| between the last two steps
+                let n = if i > 0 {
+                    i as f32 + (d_prev.ln() - self.tolerance.ln()) / (d_prev.ln() - d.ln())
+                } else {
+                    (i + 1) as f32
+                };
+                return self.roots.iter().take(MAX_DEGREE).position(|r| {
+                    let r = vec2(r.re as f32, r.im as f32);
+                    (z - r).magnitude() < 1e-3 * (1.0 + r.magnitude())
+                }).map(|k| (k, n));
+            }
+            d_prev = d;
+        }
+        None
+    }
+
+    // pixel (x, y) of a width x height image, mapped like the compute shaders map theirs
+    pub fn pixel_to_z(&self, x: u32, y: u32, width: u32, height: u32) -> Vec2 {
+        vec2(self.center[0], self.center[1]) + pixel_offset(self.scale, x, y, width, height)
+    }
+
+    pub fn color(&self, z: Vec2) -> [f32; 4] {
+        self.color_with(&colormap::colormap_data(&self.colormap_name), z)
+    }
+
+    fn color_with(&self, cdata: &[[f32; 3]; 11], z: Vec2) -> [f32; 4] {
+        let n_roots = self.roots.len().min(MAX_DEGREE) as f32;
+        match self.iterate(z) {
+            Some((k, n)) => {
+                let rgb = colormap_lerp(cdata, (k as f32 + 0.5) / n_roots);
+                let b = (-self.shading * n).exp();
+                [rgb[0] * b, rgb[1] * b, rgb[2] * b, 1.0]
+            }
+            None => [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    // RGBA8 pixels, row by row from the top
+    pub fn render_rgba(&self, width: u32, height: u32) -> Vec<u8> {
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        render_rgba8(width, height, |x, y| self.color_with(&cdata, self.pixel_to_z(x, y, width, height)))
+    }
+
+    pub fn gpu_params(&self, width: u32, height: u32) -> NewtonParams {
+        NewtonParams {
+            center: self.center,
+            relaxation: [self.relaxation.re as f32, self.relaxation.im as f32],
+            scale: self.scale,
+            width: width as f32,
+            height: height as f32,
+            tolerance: self.tolerance,
+            shading: self.shading,
+            max_iterations: self.max_iterations,
+            method: self.method as u32,
+            num_degree: self.numerator.len().saturating_sub(1).min(MAX_DEGREE) as u32,
+            den_degree: self.denominator.len().saturating_sub(1).min(MAX_DEGREE) as u32,
+            root_count: self.roots.len().min(MAX_DEGREE) as u32,
+            pad: [0; 2],
+        }
+    }
+
+    pub fn gpu_polynomials(&self) -> NewtonPolynomials {
+        let mut polys = NewtonPolynomials::zeroed();
+        let pack = |c: &C64| [c.re as f32, c.im as f32, 0.0, 0.0];
+        for (k, c) in self.numerator.iter().take(MAX_DEGREE + 1).enumerate() {
+            polys.num[k] = pack(c);
+        }
+        for (k, c) in self.denominator.iter().take(MAX_DEGREE + 1).enumerate() {
+            polys.den[k] = pack(c);
+        }
+        for (k, r) in self.roots.iter().take(MAX_DEGREE).enumerate() {
+            polys.roots[k] = pack(r);
+        }
+        polys
+    }
+}
+
+// p, p' and p'' at z, as evalNum in the shader; no coefficients make the zero polynomial, whose
+// zeroed uniform the shader reads as well
+fn horner(coefficients: &[C64], z: Vec2) -> [Vec2; 3] {
+    let c = |k: usize| vec2(coefficients[k].re as f32, coefficients[k].im as f32);
+    let Some(n) = coefficients.len().min(MAX_DEGREE + 1).checked_sub(1) else {
+        return [vec2(0.0, 0.0); 3];
+    };
+    let (mut p, mut dp, mut ddp) = (c(n), vec2(0.0, 0.0), vec2(0.0, 0.0));
+    for k in (0..n).rev() {
+        ddp = c_mul(ddp, z) + dp;
+        dp = c_mul(dp, z) + p;
+        p = c_mul(p, z) + c(k);
+    }
+    [p, dp, 2.0 * ddp]
+}
+
+// drops vanishing leading coefficients; a polynomial without any nonzero coefficient becomes 1
+fn trim(coefficients: &[C64]) -> Vec<C64> {
+    match coefficients.iter().rposition(|c| c.norm() > 0.0) {
+        Some(n) => coefficients[..=n].to_vec(),
+        None => vec![C64::new(1.0, 0.0)],
+    }
+}
+
+// all zeros of the polynomial with the given coefficients (constant term first), by Durand-Kerner
+pub fn polynomial_roots(coefficients: &[C64]) -> Vec<C64> {
+    let c = trim(coefficients);
+    let n = c.len() - 1;
+    if n == 0 {
+        return vec![];
+    }
+    let lead = c[n];
+    let monic: Vec<C64> = c.iter().map(|&a| a / lead).collect();
+    let eval = |z: C64| monic.iter().rev().fold(C64::new(0.0, 0.0), |acc, &a| acc * z + a);
+
+    // starting points on a circle enclosing all zeros (Cauchy bound), off the symmetry axes
+    let radius = 1.0 + monic[..n].iter().map(|a| a.norm()).fold(0.0, f64::max);
+    let mut z: Vec<C64> = (0..n).map(|k| C64::from_polar(radius, 0.4 + 2.0 * std::f64::consts::PI * k as f64 / n as f64))
+        .collect();
+    for _ in 0..500 {
+        let mut change = 0.0f64;
+        for k in 0..n {
+            let denom = (0..n).filter(|&j| j != k).fold(C64::new(1.0, 0.0), |acc, j| acc * (z[k] - z[j]));
+            let dz = eval(z[k]) / denom;
+            if dz.re.is_finite() && dz.im.is_finite() {
+                z[k] -= dz;
+                change = change.max(dz.norm());
+            }
+        }
+        if change < 1e-14 * radius {
+            break;
+        }
+    }
+    z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex_catalog::c;
+    use crate::complex_wgsl::{compose_shader, validate_wgsl};
+
+    #[test]
+    fn basins_of_the_cube_roots_of_unity() {
+        let mut f = NewtonFractal::default();
+        for (k, r) in f.roots.clone().iter().enumerate() {
+            let (root, n) = f.iterate(vec2(1.2 * r.re as f32, 1.2 * r.im as f32)).unwrap();
+            assert_eq!(root, k);
+            assert!(n > 1.0 && n < 10.0, "{}", n);
+        }
+        // Halley converges cubically
+        let newton = f.iterate(vec2(3.0, 0.5)).unwrap();
+        f.method = NewtonMethod::Halley;
+        let halley = f.iterate(vec2(3.0, 0.5)).unwrap();
+        assert_eq!(newton.0, halley.0);
+        assert!(halley.1 < newton.1, "{:?} {:?}", halley, newton);
+
+        let rgba = f.render_rgba(40, 30);
+        assert_eq!(rgba.len(), 4 * 40 * 30);
+        assert!(rgba.chunks(4).all(|p| p[3] == 255));
+    }
+
+    #[test]
+    fn relaxation_for_a_double_root() {
+        // (z - 1)^2 (z + 1): plain Newton converges only linearly to the double root 1
+        let mut f = NewtonFractal::from_roots(&[c(1.0, 0.0), c(1.0, 0.0), c(-1.0, 0.0)]).unwrap();
+        f.tolerance = 1e-4;
+        let plain = f.iterate(vec2(1.3, 0.1)).unwrap().1;
+        f.relaxation = c(2.0, 0.0);
+        let relaxed = f.iterate(vec2(1.3, 0.1)).unwrap().1;
+        assert!(relaxed < plain, "{} {}", relaxed, plain);
+    }
+
+    #[test]
+    fn coefficients_and_rational_functions() {
+        // z^4 - 1 from its coefficients
+        let f = NewtonFractal::from_coefficients(&[c(-1.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)]).unwrap();
+        assert_eq!(f.roots.len(), 4);
+        for r in [c(1.0, 0.0), c(-1.0, 0.0), c(0.0, 1.0), c(0.0, -1.0)] {
+            assert!(f.roots.iter().any(|&q| (q - r).norm() < 1e-10), "{} not in {:?}", r, f.roots);
+        }
+
+        // (z^2 - 1)/(z^2 + 4) has the zeros of its numerator
+        let f = NewtonFractal::rational(&[c(-1.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)], &[c(4.0, 0.0), c(0.0, 0.0), c(1.0, 0.0)])
+            .unwrap();
+        let (k, _) = f.iterate(vec2(0.8, 0.3)).unwrap();
+        assert!((f.roots[k] - c(1.0, 0.0)).norm() < 1e-10);
+        let params = f.gpu_params(64, 48);
+        assert_eq!((params.num_degree, params.den_degree, params.root_count), (2, 2, 2));
+        assert_eq!(std::mem::size_of::<NewtonParams>(), 64);
+    }
+
+    #[test]
+    fn degree_limit_and_empty_polynomials() {
+        let roots: Vec<C64> = (0..=MAX_DEGREE).map(|k| C64::from_polar(1.0, k as f64)).collect();
+        assert!(NewtonFractal::from_roots(&roots[..MAX_DEGREE]).is_some());
+        assert!(NewtonFractal::from_roots(&roots).is_none());
+
+        // trailing zeros do not count towards the degree
+        let mut coefficients = vec![c(0.0, 0.0); MAX_DEGREE + 3];
+        (coefficients[0], coefficients[MAX_DEGREE]) = (c(-1.0, 0.0), c(1.0, 0.0));
+        assert!(NewtonFractal::from_coefficients(&coefficients).is_some());
+        coefficients[MAX_DEGREE + 1] = c(1.0, 0.0);
+        assert!(NewtonFractal::from_coefficients(&coefficients).is_none());
+        assert!(NewtonFractal::rational(&[c(1.0, 0.0), c(1.0, 0.0)], &coefficients).is_none());
+
+        let mut f = NewtonFractal::default();
+        (f.numerator, f.denominator) = (vec![], vec![]);
+        assert_eq!(f.iterate(vec2(0.5, 0.5)), None);
+        let params = f.gpu_params(8, 8);
+        assert_eq!((params.num_degree, params.den_degree), (0, 0));
+    }
+
+    #[test]
+    fn shader_validates() {
+        validate_wgsl(&compose_shader(include_str!("../examples/ch03/newton_comp.wgsl"), None));
+    }
+}
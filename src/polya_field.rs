@@ -0,0 +1,290 @@
+#![allow(dead_code)]
+use num_complex::Complex;
+use super::colormap;
+use super::complex3d_data::RangeStrategy;
+
+// The Polya vector field of f, V(x, z) = conj f(x + iz) = (Re f, -Im f), as arrow glyphs on a regular
+// grid and as streamlines. The field is divergence and curl free wherever f is analytic, and its
+// flux and circulation around a closed curve give the contour integral of f. Geometry lies in the
+// plane y = 0 in the normalized coordinates of IComplex3D (domain mapped onto [-scale, scale]) and
+// is colored by |f| through a colormap; like vertex_data it is returned as positions, colors and
+// indices, triangles for the arrows and a line list for the streamlines.
+
+type C64 = Complex<f64>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrowLength {
+    #[default]
+    Uniform,   // every arrow fills its cell; only direction and color vary
+    Magnitude, // proportional to |f|, reaching the full cell at the upper end of the color range
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArrowOptions {
+    pub nx: u32, // arrows along x
+    pub nz: u32, // arrows along z
+    pub length: ArrowLength,
+    pub fill: f32, // longest arrow as a fraction of the cell size
+    pub color_strategy: RangeStrategy,
+}
+
+impl Default for ArrowOptions {
+    fn default() -> Self {
+        Self { nx: 24, nz: 24, length: ArrowLength::Uniform, fill: 0.8, color_strategy: RangeStrategy::Percentile(1.0, 99.0) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamlineOptions {
+    pub seeds_x: u32,     // seed grid; a seed is skipped when a streamline already passes nearby
+    pub seeds_z: u32,
+    pub step: f64,        // RK4 step along the unit field, as a fraction of the domain width
+    pub max_steps: u32,   // per direction from the seed
+    pub separation: f64,  // streamlines stop this close to another one, fraction of the domain width
+    pub singular: f64,    // stop where |f| leaves [singular, 1/singular] times the median |f|
+    pub color_strategy: RangeStrategy,
+}
+
+impl Default for StreamlineOptions {
+    fn default() -> Self {
+        Self {
+            seeds_x: 16,
+            seeds_z: 16,
+            step: 0.005,
+            max_steps: 2000,
+            separation: 0.02,
+            singular: 1e-3,
+            color_strategy: RangeStrategy::Percentile(1.0, 99.0),
+        }
+    }
+}
+
+fn polya(w: C64) -> C64 {
+    w.conj()
+}
+
+fn finite(w: C64) -> bool {
+    w.re.is_finite() && w.im.is_finite()
+}
+
+// maps between the domain and normalized coordinates
+struct Frame {
+    d: [f64; 4],
+    scale: f64,
+}
+
+impl Frame {
+    fn point(&self, z: C64) -> [f32; 3] {
+        let x = self.scale * (-1.0 + 2.0 * (z.re - self.d[0]) / (self.d[1] - self.d[0]));
+        let y = self.scale * (-1.0 + 2.0 * (z.im - self.d[2]) / (self.d[3] - self.d[2]));
+        [x as f32, 0.0, y as f32]
+    }
+
+    // a direction in the domain as a unit vector in normalized coordinates
+    fn direction(&self, v: C64) -> C64 {
+        let v = C64::new(v.re / (self.d[1] - self.d[0]), v.im / (self.d[3] - self.d[2]));
+        v / v.norm()
+    }
+
+    fn contains(&self, z: C64) -> bool {
+        z.re >= self.d[0] && z.re <= self.d[1] && z.im >= self.d[2] && z.im <= self.d[3]
+    }
+}
+
+// Arrows at the cell centers of an nx x nz grid over domain: a shaft quad and a head triangle each,
+// 7 vertices and 9 indices per arrow. Samples where f is not finite get no arrow.
+pub fn create_arrow_data<F: Fn(C64) -> C64>(f: F, domain: [f64; 4], scale: f32, colormap_name: &str,
+    opts: &ArrowOptions) -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u32>) {
+    let frame = Frame { d: domain, scale: scale as f64 };
+    let (nx, nz) = (opts.nx.max(1), opts.nz.max(1));
+    let mut samples: Vec<(C64, C64)> = vec![];
+    for i in 0..nx {
+        for j in 0..nz {
+            let z = C64::new(
+                domain[0] + (domain[1] - domain[0]) * (i as f64 + 0.5) / nx as f64,
+                domain[2] + (domain[3] - domain[2]) * (j as f64 + 0.5) / nz as f64,
+            );
+            let v = polya(f(z));
+            if finite(v) {
+                samples.push((z, v));
+            }
+        }
+    }
+    let magnitudes: Vec<f64> = samples.iter().map(|s| s.1.norm()).collect();
+    let range = opts.color_strategy.range(&magnitudes);
+    let cdata = colormap::colormap_data(colormap_name);
+
+    let cell = 2.0 * scale * (1.0 / nx as f32).min(1.0 / nz as f32);
+    let mut positions: Vec<[f32; 3]> = vec![];
+    let mut colors: Vec<[f32; 3]> = vec![];
+    let mut indices: Vec<u32> = vec![];
+    for &(z, v) in &samples {
+        let m = v.norm();
+        if m == 0.0 {
+            continue;
+        }
+        let len = match opts.length {
+            ArrowLength::Uniform => 1.0,
+            ArrowLength::Magnitude => if range[1] > 0.0 { (m / range[1]).min(1.0) as f32 } else { 1.0 },
+        } * opts.fill * cell;
+        let dir = frame.direction(v);
+        let (dx, dz) = (dir.re as f32, dir.im as f32);
+        let c = frame.point(z);
+        let at = |s: f32, w: f32| [c[0] + s * len * dx - w * len * dz, 0.0, c[2] + s * len * dz + w * len * dx];
+
+        // tail, neck and tip along the arrow at -1/2, 1/6 and 1/2 of its length
+        let base = positions.len() as u32;
+        positions.extend([at(-0.5, -0.06), at(-0.5, 0.06), at(1.0 / 6.0, 0.06), at(1.0 / 6.0, -0.06),
+            at(1.0 / 6.0, -0.2), at(1.0 / 6.0, 0.2), at(0.5, 0.0)]);
+        colors.extend([colormap::color_lerp(cdata, range[0], range[1], m); 7]);
+        indices.extend([base, base + 1, base + 2, base + 2, base + 3, base, base + 4, base + 5, base + 6]);
+    }
+    (positions, colors, indices)
+}
+
+// Streamlines of the Polya field, integrated by RK4 along the unit field V/|V| in both directions
+// from every seed, so that the speed of f does not matter. A line stops when it leaves the domain,
+// runs into a zero or pole of f (|f| far from its median, or V turning around within one step),
+// comes close to another streamline, or closes up on itself. Returns positions, colors and line
+// list indices.
+pub fn create_streamline_data<F: Fn(C64) -> C64>(f: F, domain: [f64; 4], scale: f32, colormap_name: &str,
+    opts: &StreamlineOptions) -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u32>) {
+    let frame = Frame { d: domain, scale: scale as f64 };
+    let width = (domain[1] - domain[0]).max(domain[3] - domain[2]);
+    let h = opts.step * width;
+
+    // median |f| over the seed grid sets the scale for zeros and poles
+    let seeds: Vec<C64> = (0..opts.seeds_x).flat_map(|i| (0..opts.seeds_z).map(move |j| C64::new(
+        domain[0] + (domain[1] - domain[0]) * (i as f64 + 0.5) / opts.seeds_x as f64,
+        domain[2] + (domain[3] - domain[2]) * (j as f64 + 0.5) / opts.seeds_z as f64,
+    ))).collect();
+    let mut magnitudes: Vec<f64> = seeds.iter().map(|&z| f(z).norm()).filter(|m| m.is_finite()).collect();
+    magnitudes.sort_by(|a, b| a.total_cmp(b));
+    let median = magnitudes.get(magnitudes.len() / 2).copied().unwrap_or(1.0);
+    let regular = |w: C64| finite(w) && w.norm() > opts.singular * median && w.norm() < median / opts.singular;
+    let field = |z: C64| -> Option<C64> {
+        let v = polya(f(z));
+        if regular(v) { Some(v / v.norm()) } else { None }
+    };
+    let rk4 = |z: C64, h: f64| -> Option<C64> {
+        let k1 = field(z)?;
+        let k2 = field(z + k1 * (0.5 * h))?;
+        let k3 = field(z + k2 * (0.5 * h))?;
+        let k4 = field(z + k3 * h)?;
+        // a field that turns around within one step has a zero or pole in between
+        if (k1 * k4.conj()).re < 0.0 {
+            return None;
+        }
+        Some(z + (k1 + k2 * 2.0 + k3 * 2.0 + k4) * (h / 6.0))
+    };
+
+    // occupancy grid of cells of size separation, each holding the streamline that passed there
+    let sep = (opts.separation * width).max(h);
+    let (gx, gz) = (((domain[1] - domain[0]) / sep).ceil() as usize + 1, ((domain[3] - domain[2]) / sep).ceil() as usize + 1);
+    let mut occupied: Vec<Option<usize>> = vec![None; gx * gz];
+    let cell = |z: C64| ((z.re - domain[0]) / sep) as usize * gz + ((z.im - domain[2]) / sep) as usize;
+
+    let mut lines: Vec<Vec<C64>> = vec![];
+    for &seed in &seeds {
+        if occupied[cell(seed)].is_some() || field(seed).is_none() {
+            continue;
+        }
+        let id = lines.len();
+        let mut halves: Vec<Vec<C64>> = vec![];
+        for dir in [1.0, -1.0] {
+            let mut half = vec![seed];
+            let mut z = seed;
+            for n in 0..opts.max_steps {
+                let Some(next) = rk4(z, dir * h) else { break };
+                if !frame.contains(next) || occupied[cell(next)].is_some_and(|o| o != id) {
+                    break;
+                }
+                if n > 10 && (next - seed).norm() < h {
+                    half.push(seed);
+                    break;
+                }
+                half.push(next);
+                z = next;
+            }
+            halves.push(half);
+            if halves[0].last() == Some(&seed) && halves[0].len() > 1 {
+                break; // closed orbit, nothing left for the backward direction
+            }
+        }
+        let mut line: Vec<C64> = halves.get(1).map_or(vec![], |b| b.iter().skip(1).rev().copied().collect());
+        line.extend(&halves[0]);
+        if line.len() > 1 {
+            for &z in &line {
+                occupied[cell(z)] = Some(id);
+            }
+            lines.push(line);
+        }
+    }
+
+    let magnitudes: Vec<f64> = lines.iter().flatten().map(|&z| f(z).norm()).collect();
+    let range = opts.color_strategy.range(&magnitudes);
+    let cdata = colormap::colormap_data(colormap_name);
+    let mut positions: Vec<[f32; 3]> = vec![];
+    let mut colors: Vec<[f32; 3]> = vec![];
+    let mut indices: Vec<u32> = vec![];
+    for (line, ms) in lines.iter().zip(lines.iter().scan(0, |k, l| {
+        *k += l.len();
+        Some(&magnitudes[*k - l.len()..*k])
+    })) {
+        let base = positions.len() as u32;
+        positions.extend(line.iter().map(|&z| frame.point(z)));
+        colors.extend(ms.iter().map(|&m| colormap::color_lerp(cdata, range[0], range[1], m)));
+        indices.extend((0..line.len() as u32 - 1).flat_map(|k| [base + k, base + k + 1]));
+    }
+    (positions, colors, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const D: [f64; 4] = [-2.0, 2.0, -2.0, 2.0];
+
+    fn z(p: &[f32; 3]) -> C64 {
+        C64::new(2.0 * p[0] as f64, 2.0 * p[2] as f64)
+    }
+
+    #[test]
+    fn arrows_follow_the_conjugate() {
+        // f = i: the Polya field conj(i) = -i points towards -z
+        let (positions, colors, indices) = create_arrow_data(|_| C64::new(0.0, 1.0), D, 1.0, "jet",
+            &ArrowOptions { nx: 4, nz: 3, ..Default::default() });
+        assert_eq!((positions.len(), colors.len(), indices.len()), (7 * 12, 7 * 12, 9 * 12));
+        for arrow in positions.chunks(7) {
+            let (tail, tip) = (z(&arrow[0]) + z(&arrow[1]), z(&arrow[6]) * 2.0);
+            let dir = (tip - tail) / (tip - tail).norm();
+            assert!((dir - C64::new(0.0, -1.0)).norm() < 1e-5, "{:?}", dir);
+        }
+    }
+
+    #[test]
+    fn hyperbolic_streamlines_of_the_identity() {
+        // conj z = (x, -y) keeps x y constant along every streamline
+        let (positions, colors, indices) = create_streamline_data(|z| z, D, 1.0, "jet", &StreamlineOptions::default());
+        assert_eq!(positions.len(), colors.len());
+        assert!(indices.len() > 100 && indices.len().is_multiple_of(2));
+        for seg in indices.chunks(2) {
+            let (a, b) = (z(&positions[seg[0] as usize]), z(&positions[seg[1] as usize]));
+            assert!((a.re * a.im - b.re * b.im).abs() < 1e-4, "{} -> {}", a, b);
+        }
+    }
+
+    #[test]
+    fn streamlines_stop_at_a_pole() {
+        // conj(1/z) = z/|z|^2 flows radially out of the pole at 0
+        let opts = StreamlineOptions::default();
+        let (positions, _, indices) = create_streamline_data(|z| z.inv(), D, 1.0, "jet", &opts);
+        assert!(!indices.is_empty());
+        let closest = positions.iter().map(|p| z(p).norm()).fold(f64::MAX, f64::min);
+        assert!(closest > 1e-3, "{}", closest);
+        for seg in indices.chunks(2) {
+            let (a, b) = (z(&positions[seg[0] as usize]), z(&positions[seg[1] as usize]));
+            assert!((a / b).arg().abs() < 1e-6, "{} -> {}", a, b);
+        }
+    }
+}
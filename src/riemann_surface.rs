@@ -0,0 +1,271 @@
+#![allow(dead_code)]
+use std::f64::consts::PI;
+use num_complex::Complex;
+use super::colormap;
+use super::complex3d_data::{IComplex3DOutput, RangeStrategy};
+
+// Riemann surfaces of w = g(z)^(1/n) and w = log g(z), built from several sheets of the principal
+// branch that are glued along the cuts. Each sheet samples the same grid over the domain (x = Re z,
+// z = Im z, as in IComplex3D); sheet k carries the value whose lifted angle is (Arg g + 2 pi k)/n for
+// the root and Arg g + 2 pi k for the logarithm. A triangle of the grid starts on sheet k at its
+// first vertex and continues to the sheet of its other vertices whose angle is closest, so a triangle
+// that crosses a cut of Arg g lands on the neighbouring sheet instead of jumping back. Triangles
+// around a branch point, where the continuation does not close, are left out, and so are triangles
+// that would leave the first or last sheet of the logarithm.
+
+type C64 = Complex<f64>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multivalued {
+    Root(u32), // n-th root of g, n sheets
+    Log(u32),  // log g, the given number of sheets around the principal one
+}
+
+// height of a sheet above the z-plane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SheetHeight {
+    #[default]
+    Im,  // Im w, the usual picture of the square root; the sheets of roots cross each other
+    Arg, // lifted angle of w, a helicoid; for roots the last sheet drops back to the first
+}
+
+impl Multivalued {
+    pub fn sheets(&self) -> u32 {
+        match *self {
+            Multivalued::Root(n) | Multivalued::Log(n) => n.max(1),
+        }
+    }
+
+    // lifted angle of w on sheet k, given Arg g
+    fn angle(&self, arg: f64, k: u32) -> f64 {
+        match *self {
+            Multivalued::Root(_) => (arg + 2.0 * PI * k as f64) / self.sheets() as f64,
+            Multivalued::Log(_) => arg + 2.0 * PI * (k as f64 - (self.sheets() / 2) as f64),
+        }
+    }
+
+    fn value(&self, g: C64, k: u32) -> C64 {
+        let theta = self.angle(g.arg(), k);
+        match *self {
+            Multivalued::Root(_) => C64::from_polar(g.norm().powf(1.0 / self.sheets() as f64), theta),
+            Multivalued::Log(_) => C64::new(g.norm().ln(), theta),
+        }
+    }
+
+    // distance between lifted angles; the sheets of a root close up after a full turn of w
+    fn angle_distance(&self, a: f64, b: f64) -> f64 {
+        match *self {
+            Multivalued::Root(_) => {
+                let d = (a - b).rem_euclid(2.0 * PI);
+                d.min(2.0 * PI - d)
+            }
+            Multivalued::Log(_) => (a - b).abs(),
+        }
+    }
+}
+
+pub struct RiemannSurface {
+    pub branch: Multivalued,
+    pub height: SheetHeight,
+    pub domain: [f64; 4],
+    pub x_resolution: u32,
+    pub z_resolution: u32,
+    pub scale: f32,
+    pub aspect_ratio: f32,
+    pub colormap_name: String,
+}
+
+impl Default for RiemannSurface {
+    fn default() -> Self {
+        Self {
+            branch: Multivalued::Root(2),
+            height: SheetHeight::Im,
+            domain: [-2.0, 2.0, -2.0, 2.0],
+            x_resolution: 64,
+            z_resolution: 64,
+            scale: 1.0,
+            aspect_ratio: 1.0,
+            colormap_name: "jet".to_string(),
+        }
+    }
+}
+
+impl RiemannSurface {
+    pub fn new(branch: Multivalued) -> Self {
+        Self { branch, ..Default::default() }
+    }
+
+    // one connected mesh over all sheets, vertex sheet * (x_resolution + 1) * (z_resolution + 1) + grid
+    // index, colored by arg w on the colormap
+    pub fn create_surface_data<F: Fn(C64) -> C64>(&self, g: F) -> IComplex3DOutput {
+        let d = self.domain;
+        let (nx, nz) = (self.x_resolution, self.z_resolution);
+        let sheets = self.branch.sheets();
+        let vertices_per_row = nz + 1;
+        let vertices_per_sheet = (nx + 1) * vertices_per_row;
+
+        let mut gs: Vec<C64> = vec![];
+        let mut heights: Vec<f64> = vec![];
+        let mut phases: Vec<f64> = vec![];
+        let mut grid: Vec<[f64; 2]> = vec![];
+        for i in 0..=nx {
+            let x = d[0] + (d[1] - d[0]) * i as f64 / nx as f64;
+            for j in 0..=nz {
+                let z = d[2] + (d[3] - d[2]) * j as f64 / nz as f64;
+                gs.push(g(C64::new(x, z)));
+                grid.push([x, z]);
+            }
+        }
+        let args: Vec<f64> = gs.iter().map(|g| g.arg()).collect();
+        for k in 0..sheets {
+            for (idx, &gz) in gs.iter().enumerate() {
+                let w = self.branch.value(gz, k);
+                heights.push(match self.height {
+                    SheetHeight::Im => w.im,
+                    SheetHeight::Arg => self.branch.angle(args[idx], k),
+                });
+                phases.push(w.arg());
+            }
+        }
+
+        let yr = RangeStrategy::Raw.range(&heights);
+        colormap::warn_if_not_cyclic(&self.colormap_name);
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let scale = self.scale as f64;
+        let mut positions: Vec<[f32; 3]> = vec![];
+        let mut colors: Vec<[f32; 3]> = vec![];
+        for (v, (&y, &phase)) in heights.iter().zip(&phases).enumerate() {
+            let [x, z] = grid[v % vertices_per_sheet as usize];
+            let y = if y.is_finite() { y.max(yr[0]).min(yr[1]) } else { yr[0] };
+            let yn = if yr[1] > yr[0] { (y - yr[0]) / (yr[1] - yr[0]) } else { 0.0 };
+            positions.push([
+                (scale * (-1.0 + 2.0 * (x - d[0]) / (d[1] - d[0]))) as f32,
+                (scale * (-1.0 + 2.0 * yn) * self.aspect_ratio as f64) as f32,
+                (scale * (-1.0 + 2.0 * (z - d[2]) / (d[3] - d[2]))) as f32,
+            ]);
+            colors.push(colormap::color_lerp(cdata, -PI, PI, phase));
+        }
+
+        // sheet at grid vertex b continuing sheet k at grid vertex a
+        let continuation = |a: u32, k: u32, b: u32| -> Option<u32> {
+            let theta = self.branch.angle(args[a as usize], k);
+            let (dist, kb) = (0..sheets)
+                .map(|kb| (self.branch.angle_distance(self.branch.angle(args[b as usize], kb), theta), kb))
+                .min_by(|p, q| p.0.total_cmp(&q.0))?;
+            if dist < PI { Some(kb) } else { None }
+        };
+
+        let mut indices: Vec<u32> = vec![];
+        for i in 0..nx {
+            for j in 0..nz {
+                let idx0 = j + i * vertices_per_row;
+                let idx1 = j + 1 + i * vertices_per_row;
+                let idx2 = j + 1 + (i + 1) * vertices_per_row;
+                let idx3 = j + (i + 1) * vertices_per_row;
+                for [a, b, c] in [[idx0, idx1, idx2], [idx2, idx3, idx0]] {
+                    for k in 0..sheets {
+                        let kb = continuation(a, k, b);
+                        let kc = continuation(a, k, c);
+                        if let (Some(kb), Some(kc)) = (kb, kc) {
+                            if continuation(b, kb, c) == Some(kc) {
+                                indices.extend([
+                                    a + k * vertices_per_sheet,
+                                    b + kb * vertices_per_sheet,
+                                    c + kc * vertices_per_sheet,
+                                ]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        IComplex3DOutput {
+            positions,
+            colors,
+            indices,
+            height_strategy: RangeStrategy::Raw,
+            height_range: yr,
+            color_strategy: RangeStrategy::Fixed(-PI, PI),
+            color_range: [-PI, PI],
+            cut_lines: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // number of connected components among the vertices used by triangles
+    fn components(out: &IComplex3DOutput) -> usize {
+        let mut parent: Vec<usize> = (0..out.positions.len()).collect();
+        fn root(parent: &mut [usize], mut v: usize) -> usize {
+            while parent[v] != v {
+                parent[v] = parent[parent[v]];
+                v = parent[v];
+            }
+            v
+        }
+        for tri in out.indices.chunks(3) {
+            for e in [(tri[0], tri[1]), (tri[1], tri[2])] {
+                let (a, b) = (root(&mut parent, e.0 as usize), root(&mut parent, e.1 as usize));
+                parent[a] = b;
+            }
+        }
+        let mut roots: Vec<usize> = out.indices.iter().map(|&v| root(&mut parent, v as usize)).collect();
+        roots.sort();
+        roots.dedup();
+        roots.len()
+    }
+
+    #[test]
+    fn single_sheet_is_the_plain_grid() {
+        let mut rs = RiemannSurface::new(Multivalued::Root(1));
+        (rs.x_resolution, rs.z_resolution) = (8, 6);
+        let out = rs.create_surface_data(|z| z + 3.0);
+        assert_eq!(out.positions.len(), 9 * 7);
+        assert_eq!(out.indices.len(), 6 * 8 * 6);
+    }
+
+    #[test]
+    fn square_root_sheets_are_glued() {
+        let mut rs = RiemannSurface::new(Multivalued::Root(2));
+        (rs.x_resolution, rs.z_resolution) = (40, 41);
+        let out = rs.create_surface_data(|z| z);
+        assert_eq!(out.positions.len(), 2 * 41 * 42);
+        assert_eq!(components(&out), 1);
+
+        // Im sqrt(z) is continuous on the surface: no triangle jumps across the cut
+        for tri in out.indices.chunks(3) {
+            for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+                let dy = (out.positions[a as usize][1] - out.positions[b as usize][1]).abs();
+                assert!(dy < 0.3, "edge {}-{} jumps by {}", a, b, dy);
+            }
+        }
+        // only the triangles around the branch point are missing
+        let full = 2 * 6 * 40 * 41;
+        assert!(out.indices.len() < full && out.indices.len() >= full - 2 * 6 * 4);
+    }
+
+    #[test]
+    fn logarithm_is_a_helicoid() {
+        let mut rs = RiemannSurface::new(Multivalued::Log(3));
+        rs.height = SheetHeight::Arg;
+        (rs.x_resolution, rs.z_resolution) = (30, 31);
+        let out = rs.create_surface_data(|z| (z - 0.5) * 2.0);
+        assert_eq!(components(&out), 1);
+        assert!((out.height_range[0] + 3.0 * PI).abs() < 0.2 && (out.height_range[1] - 3.0 * PI).abs() < 0.2,
+            "{:?}", out.height_range);
+
+        // the lifted angle is continuous, and the cut is only bridged between neighbouring sheets
+        let span = 2.0 * rs.scale * rs.aspect_ratio;
+        let step = span * (2.0 * PI / (out.height_range[1] - out.height_range[0])) as f32;
+        for tri in out.indices.chunks(3) {
+            for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+                let dy = (out.positions[a as usize][1] - out.positions[b as usize][1]).abs();
+                assert!(dy < 0.5 * step, "edge {}-{} jumps by {}", a, b, dy);
+            }
+        }
+    }
+}
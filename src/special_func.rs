@@ -0,0 +1,625 @@
+#![allow(dead_code)]
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_3, LN_2, PI};
+use num_complex::Complex;
+use num_traits::Float;
+
+// Special functions of a complex variable in f64, mirrored in f32 by special_func.wgsl (and on the CPU by
+// special_func_f32.rs):
+//   - gamma by the Lanczos approximation (g = 7, 9 terms) and the reflection formula for Re z < 1/2;
+//     log_gamma, the principal branch with its cut along the negative real axis, by Stirling's series
+//     after shifting z to |z| >= 10 and the reflection formula for Re z < 1/2
+//   - eta by Borwein's alternating series for Re s >= 0, zeta from eta, and both from the
+//     functional equation of zeta on the left half plane
+//   - erfc from the Faddeeva function w(z) = exp(-z^2) erfc(-iz), computed by Gautschi's algorithm
+//     with the parameters of Poppe and Wijers, erf by its Taylor series near 0 and 1 - erfc elsewhere
+//   - bessel_j and bessel_y of integer order by power series for |z| <= 17 and Hankel's expansion
+//     beyond, with three-term recurrences in the order (forward for Y always, for J when n < |z|);
+//     the order is clamped to |n| <= BESSEL_MAX_ORDER, since the work grows in proportion to it
+//   - airy_ai and airy_bi by Maclaurin series for small |z|, the asymptotic expansion of Ai for large
+//     |z| and its connection formulas elsewhere; between |z| = 4 and 9 around the positive real axis,
+//     where the series cancels and the expansion has not converged, Airy's equation is integrated
+//     inwards by Taylor steps from |z| = 9
+//   - lambert_w, the principal branch, by Halley's iteration from a series at the branch point -1/e,
+//     log(1 + z) or log z - log log z
+// The accuracy against mpmath is about 1e-13 relative or better, down to 1e-10 for Bessel functions of
+// real part near 17, where the series lose digits to cancellation.
+
+type C64 = Complex<f64>;
+
+// f evaluated in f64 for an argument of any precision, so that the f32 surfaces and expressions can
+// call the functions below
+pub fn apply<T: Float, F: Fn(C64) -> C64>(f: F, z: Complex<T>) -> Complex<T> {
+    let w = f(C64::new(z.re.to_f64().unwrap_or(f64::NAN), z.im.to_f64().unwrap_or(f64::NAN)));
+    Complex::new(T::from(w.re).unwrap_or_else(T::nan), T::from(w.im).unwrap_or_else(T::nan))
+}
+
+const LANCZOS_G: f64 = 7.0;
+#[allow(clippy::excessive_precision, clippy::inconsistent_digit_grouping)]
+const LANCZOS: [f64; 9] = [
+    0.99999999999980993, 676.5203681218851, -1259.1392167224028, 771.32342877765313, -176.61502916214059,
+    12.507343278686905, -0.13857109526572012, 9.9843695780195716e-6, 1.5056327351493116e-7,
+];
+
+// B_2k/(2k(2k - 1)) for k = 1..8
+const STIRLING: [f64; 8] = [
+    1.0/12.0, -1.0/360.0, 1.0/1260.0, -1.0/1680.0, 1.0/1188.0, -691.0/360360.0, 1.0/156.0, -3617.0/122400.0,
+];
+
+pub fn gamma(z: C64) -> C64 {
+    if z.re < 0.5 {
+        PI / ((z * PI).sin() * gamma_lanczos(1.0 - z))
+    } else {
+        gamma_lanczos(z)
+    }
+}
+
+fn gamma_lanczos(z: C64) -> C64 {
+    let z = z - 1.0;
+    let mut x = C64::new(LANCZOS[0], 0.0);
+    for (i, p) in LANCZOS.iter().enumerate().skip(1) {
+        x += p / (z + i as f64);
+    }
+    let t = z + LANCZOS_G + 0.5;
+    ((z + 0.5) * t.ln() - t).exp() * x * (2.0 * PI).sqrt()
+}
+
+pub fn log_gamma(z: C64) -> C64 {
+    if z.re >= 0.5 {
+        return log_gamma_stirling(z);
+    }
+    // the lower half plane, and the lower side of the cut, by symmetry
+    if z.im.is_sign_negative() {
+        return log_gamma(z.conj()).conj();
+    }
+    // reflection, with log sin(pi z) continued over the upper half plane from
+    // sin(pi z) = i/2 exp(-i pi z) (1 - exp(2 pi i z)), where 1 - exp(2 pi i z) has Re >= 0; this
+    // adds the 2 pi i k that the principal log of sin(pi z) would lose for Re z < -1/2
+    let iz = C64::new(-z.im, z.re);
+    let log_sin = C64::new(-LN_2, FRAC_PI_2) - iz * PI + (1.0 - (iz * (2.0 * PI)).exp()).ln();
+    PI.ln() - log_sin - log_gamma_stirling(1.0 - z)
+}
+
+// Stirling's series for Re z >= 1/2, after shifting z to |z| >= 10 in at most 10 steps
+fn log_gamma_stirling(z: C64) -> C64 {
+    let mut w = z;
+    let mut shift = C64::new(0.0, 0.0);
+    while w.norm() < 10.0 {
+        shift += w.ln();
+        w += 1.0;
+    }
+    let w2 = (w * w).inv();
+    let mut series = C64::new(0.0, 0.0);
+    for c in STIRLING.iter().rev() {
+        series = series * w2 + c;
+    }
+    (w - 0.5) * w.ln() - w + 0.5 * (2.0 * PI).ln() + series / w - shift
+}
+
+// Dirichlet eta (alternating zeta) function
+pub fn eta(s: C64) -> C64 {
+    if s.re >= 0.0 {
+        eta_borwein(s)
+    } else {
+        (1.0 - pow2(1.0 - s)) * zeta(s)
+    }
+}
+
+pub fn zeta(s: C64) -> C64 {
+    if s == C64::new(1.0, 0.0) {
+        return C64::new(f64::INFINITY, 0.0);
+    }
+    if s.re >= 0.0 {
+        return eta_borwein(s) / (1.0 - pow2(1.0 - s));
+    }
+    // zeta(s) = (2 pi)^s / pi sin(pi s/2) Gamma(1 - s) zeta(1 - s)
+    let a = 1.0 - s;
+    let scale = (s * (2.0 * PI).ln() - PI.ln() + log_gamma(a)).exp();
+    scale * (s * (0.5 * PI)).sin() * eta_borwein(a) / (1.0 - pow2(1.0 - a))
+}
+
+fn pow2(s: C64) -> C64 {
+    (s * 2f64.ln()).exp()
+}
+
+// Borwein's algorithm 2 with n = 64, accurate for Re s >= 0 and |Im s| up to several hundred
+fn eta_borwein(s: C64) -> C64 {
+    const N: usize = 64;
+    let n = N as f64;
+    let mut d = [0.0; N + 1];
+    let mut term = 1.0;
+    d[0] = 1.0;
+    for i in 1..=N {
+        let k = i as f64;
+        term *= 4.0 * (n + k - 1.0) * (n - k + 1.0) / ((2.0 * k) * (2.0 * k - 1.0));
+        d[i] = d[i - 1] + term;
+    }
+    let mut sum = C64::new(0.0, 0.0);
+    for k in (0..N).rev() {
+        let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+        sum += (-s * ((k + 1) as f64).ln()).exp() * (sign * (d[k] - d[N]));
+    }
+    -sum / d[N]
+}
+
+pub fn erf(z: C64) -> C64 {
+    if z.norm() < 0.5 {
+        // 2/sqrt(pi) sum (-1)^n z^(2n+1)/(n! (2n+1))
+        let z2 = -z * z;
+        let mut term = z;
+        let mut sum = z;
+        for n in 1..30 {
+            term = term * z2 / n as f64;
+            sum += term / (2 * n + 1) as f64;
+        }
+        sum * (2.0 / PI.sqrt())
+    } else if z.re >= 0.0 {
+        1.0 - erfc(z)
+    } else {
+        erfc(-z) - 1.0
+    }
+}
+
+pub fn erfc(z: C64) -> C64 {
+    if z.re >= 0.0 {
+        (-z * z).exp() * faddeeva(C64::i() * z)
+    } else {
+        2.0 - erfc(-z)
+    }
+}
+
+// w(z) = exp(-z^2) erfc(-iz), from the first quadrant by w(-conj z) = conj w(z) and
+// w(-z) = 2 exp(-z^2) - w(z)
+pub fn faddeeva(z: C64) -> C64 {
+    if z.im < 0.0 {
+        return 2.0 * (-z * z).exp() - faddeeva(-z);
+    }
+    if z.re < 0.0 {
+        faddeeva_quadrant(-z.re, z.im).conj()
+    } else {
+        faddeeva_quadrant(z.re, z.im)
+    }
+}
+
+// Gautschi's continued fraction, combined with a truncated Taylor series (h > 0) inside the ellipse
+// (x/6.3)^2 + (y/4.4)^2 < 1
+fn faddeeva_quadrant(x: f64, y: f64) -> C64 {
+    let q = (x / 6.3).powi(2) + (y / 4.4).powi(2);
+    let (h, capn, nu) = if q < 1.0 {
+        let s = (1.0 - y / 4.4) * (1.0 - (x / 6.3).powi(2)).sqrt();
+        (1.88 * s, 7 + (34.0 * s) as i32, 16 + (26.0 * s) as i32)
+    } else {
+        (0.0, 0, 3 + (1442.0 / (26.0 * q.sqrt() + 77.0)) as i32)
+    };
+    let h2 = 2.0 * h;
+    let mut lambda = if h > 0.0 { h2.powi(capn) } else { 0.0 };
+    let continued = h == 0.0 || lambda == 0.0;
+    let mut r = C64::new(0.0, 0.0);
+    let mut s = C64::new(0.0, 0.0);
+    for n in (0..=nu).rev() {
+        let np1 = (n + 1) as f64;
+        let t = C64::new(y + h + np1 * r.re, x - np1 * r.im);
+        r = t * (0.5 / t.norm_sqr());
+        if h > 0.0 && n <= capn {
+            s = r * (s + lambda);
+            lambda /= h2;
+        }
+    }
+    let mut w = if continued { r } else { s } * (2.0 / PI.sqrt());
+    if y == 0.0 {
+        w.re = (-x * x).exp();
+    }
+    w
+}
+
+pub const BESSEL_MAX_ORDER: i32 = 100;
+
+// radius below which the Bessel functions of order 0 and 1 are summed as power series
+const BESSEL_SERIES_RADIUS: f64 = 17.0;
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+// Bessel function of the first kind J_n(z)
+pub fn bessel_j(n: i32, z: C64) -> C64 {
+    let m = n.clamp(-BESSEL_MAX_ORDER, BESSEL_MAX_ORDER).unsigned_abs();
+    let mut sign = if n < 0 && m % 2 == 1 { -1.0 } else { 1.0 };
+    let z = if z.re < 0.0 {
+        if m % 2 == 1 {
+            sign = -sign;
+        }
+        -z
+    } else {
+        z
+    };
+    if m <= 1 {
+        return bessel_j01(m, z) * sign;
+    }
+    if z.re.abs() <= 12.0 || m as f64 >= z.norm() {
+        return bessel_j_series(m, z) * sign;
+    }
+    let (mut j0, mut j1) = (bessel_j01(0, z), bessel_j01(1, z));
+    for k in 1..m {
+        (j0, j1) = (j1, j1 * (2.0 * k as f64) / z - j0);
+    }
+    j1 * sign
+}
+
+// Bessel function of the second kind Y_n(z), with its cut along the negative real axis
+pub fn bessel_y(n: i32, z: C64) -> C64 {
+    let m = n.clamp(-BESSEL_MAX_ORDER, BESSEL_MAX_ORDER).unsigned_abs();
+    let sign = if n < 0 && m % 2 == 1 { -1.0 } else { 1.0 };
+    if z.re < 0.0 {
+        // Y_m(-w) = (-1)^m (Y_m(w) +- 2i J_m(w)), + above the cut
+        let w = -z;
+        let j = bessel_j(m as i32, w);
+        let i2 = if z.im >= 0.0 { C64::new(0.0, 2.0) } else { C64::new(0.0, -2.0) };
+        let parity = if m % 2 == 1 { -1.0 } else { 1.0 };
+        return (bessel_y(m as i32, w) + i2 * j) * (sign * parity);
+    }
+    let (mut y0, mut y1) = (bessel_y01(0, z), bessel_y01(1, z));
+    if m == 0 {
+        return y0 * sign;
+    }
+    for k in 1..m {
+        (y0, y1) = (y1, y1 * (2.0 * k as f64) / z - y0);
+    }
+    y1 * sign
+}
+
+fn bessel_j01(n: u32, z: C64) -> C64 {
+    if z.norm() <= BESSEL_SERIES_RADIUS {
+        bessel_j_series(n, z)
+    } else {
+        bessel_hankel(n, z).0
+    }
+}
+
+fn bessel_y01(n: u32, z: C64) -> C64 {
+    if z.norm() <= BESSEL_SERIES_RADIUS {
+        bessel_y_series(n, z)
+    } else {
+        bessel_hankel(n, z).1
+    }
+}
+
+// (z/2)^n sum (-z^2/4)^k/(k! (n+k)!)
+fn bessel_j_series(n: u32, z: C64) -> C64 {
+    let half = z * 0.5;
+    let mut term = C64::new(1.0, 0.0);
+    for k in 1..=n {
+        term = term * half / k as f64;
+    }
+    let q = -half * half;
+    let mut sum = term;
+    for k in 1..500 {
+        term = term * q / (k as f64 * (n + k) as f64);
+        sum += term;
+        if k > 2 && term.norm() <= 1e-17 * sum.norm() {
+            break;
+        }
+    }
+    sum
+}
+
+// DLMF 10.8.1
+fn bessel_y_series(n: u32, z: C64) -> C64 {
+    let half = z * 0.5;
+    let q = half * half;
+    let mut finite = C64::new(0.0, 0.0);
+    let mut qk = C64::new(1.0, 0.0);
+    for k in 0..n {
+        finite += qk * (factorial(n - k - 1) / factorial(k));
+        qk *= q;
+    }
+    let finite = -finite * half.powi(-(n as i32)) / PI;
+
+    let mut psi1 = -EULER_GAMMA;
+    let mut psi2 = -EULER_GAMMA + (1..=n).map(|j| 1.0 / j as f64).sum::<f64>();
+    let mut term = half.powi(n as i32) / factorial(n);
+    let mut sum = term * (psi1 + psi2);
+    for k in 1..500 {
+        psi1 += 1.0 / k as f64;
+        psi2 += 1.0 / (n + k) as f64;
+        term = term * (-q) / (k as f64 * (n + k) as f64);
+        let d = term * (psi1 + psi2);
+        sum += d;
+        if k > 2 && d.norm() <= 1e-17 * sum.norm() {
+            break;
+        }
+    }
+    finite + 2.0 / PI * half.ln() * bessel_j_series(n, z) - sum / PI
+}
+
+fn factorial(n: u32) -> f64 {
+    (1..=n).map(|k| k as f64).product()
+}
+
+// (J_n(z), Y_n(z)) from Hankel's expansion, summed up to its smallest term
+fn bessel_hankel(n: u32, z: C64) -> (C64, C64) {
+    let mu = 4.0 * (n * n) as f64;
+    let mut p = C64::new(1.0, 0.0);
+    let mut q = C64::new(0.0, 0.0);
+    let mut term = C64::new(1.0, 0.0);
+    let mut last = f64::INFINITY;
+    for k in 1..60 {
+        term = term * (mu - ((2 * k - 1) * (2 * k - 1)) as f64) / (z * (8 * k) as f64);
+        let size = term.norm();
+        if size > last || size < 1e-17 {
+            break;
+        }
+        last = size;
+        let sign = if (k / 2) % 2 == 0 { 1.0 } else { -1.0 };
+        if k % 2 == 1 {
+            q += term * sign;
+        } else {
+            p += term * sign;
+        }
+    }
+    let chi = z - (0.5 * n as f64 + 0.25) * PI;
+    let a = (2.0 / (PI * z)).sqrt();
+    (a * (p * chi.cos() - q * chi.sin()), a * (p * chi.sin() + q * chi.cos()))
+}
+
+// Ai(0) and -Ai'(0)
+#[allow(clippy::excessive_precision)]
+const AIRY_C1: f64 = 0.355028053887817239;
+#[allow(clippy::excessive_precision)]
+const AIRY_C2: f64 = 0.258819403792806798;
+const AIRY_SERIES_RADIUS: f64 = 7.0;
+const AIRY_INNER_RADIUS: f64 = 4.0;
+const AIRY_OUTER_RADIUS: f64 = 9.0;
+
+pub fn airy_ai(z: C64) -> C64 {
+    if z.norm() <= AIRY_SERIES_RADIUS || z.arg().abs() <= 2.0 * FRAC_PI_3 {
+        return airy_ai_sector(z);
+    }
+    // Ai(z) + w Ai(w z) + w^2 Ai(w^2 z) = 0 with w = exp(2 pi i/3)
+    let w = C64::from_polar(1.0, 2.0 * FRAC_PI_3);
+    -w * airy_ai_sector(w * z) - w * w * airy_ai_sector(w * w * z)
+}
+
+pub fn airy_bi(z: C64) -> C64 {
+    if z.norm() <= AIRY_SERIES_RADIUS {
+        let (f, g) = airy_series(z);
+        return (f * AIRY_C1 + g * AIRY_C2) * 3f64.sqrt();
+    }
+    // DLMF 9.2.10
+    let w = C64::from_polar(1.0, 2.0 * FRAC_PI_3);
+    C64::from_polar(1.0, PI / 6.0) * airy_ai(w * z) + C64::from_polar(1.0, -PI / 6.0) * airy_ai(z / w)
+}
+
+// Ai for |z| <= 7 or |arg z| <= 2 pi/3
+fn airy_ai_sector(z: C64) -> C64 {
+    let r = z.norm();
+    if r <= AIRY_INNER_RADIUS || (r <= AIRY_SERIES_RADIUS && z.arg().abs() > FRAC_PI_3) {
+        let (f, g) = airy_series(z);
+        return f * AIRY_C1 - g * AIRY_C2;
+    }
+    if r >= AIRY_OUTER_RADIUS {
+        return airy_asymptotic(z).0;
+    }
+    // Ai grows inwards, faster than Bi, so the integration is stable
+    let mut z0 = z * (AIRY_OUTER_RADIUS / r);
+    let (mut y, mut dy) = airy_asymptotic(z0);
+    let steps = ((AIRY_OUTER_RADIUS - r) / 0.5).ceil();
+    let h = (z - z0) / steps;
+    for _ in 0..steps as usize {
+        (y, dy) = airy_taylor_step(z0, y, dy, h);
+        z0 += h;
+    }
+    y
+}
+
+// f = sum 3^k (1/3)_k z^3k/(3k)! and g = sum 3^k (2/3)_k z^(3k+1)/(3k+1)!
+fn airy_series(z: C64) -> (C64, C64) {
+    let z3 = z * z * z;
+    let (mut f, mut g) = (C64::new(1.0, 0.0), z);
+    let (mut tf, mut tg) = (f, g);
+    for k in 1..300 {
+        let k = k as f64;
+        tf = tf * z3 / ((3.0 * k - 1.0) * (3.0 * k));
+        tg = tg * z3 / ((3.0 * k) * (3.0 * k + 1.0));
+        f += tf;
+        g += tg;
+        if tf.norm() + tg.norm() <= 1e-17 * (f.norm() + g.norm()) {
+            break;
+        }
+    }
+    (f, g)
+}
+
+// (Ai(z), Ai'(z)) for large |z| with |arg z| < pi, summed up to the smallest term
+fn airy_asymptotic(z: C64) -> (C64, C64) {
+    let zeta = z * z.sqrt() * (2.0 / 3.0);
+    let x = -zeta.inv();
+    let (mut s, mut sd) = (C64::new(1.0, 0.0), C64::new(1.0, 0.0));
+    let (mut u, mut xk) = (1.0, C64::new(1.0, 0.0));
+    let mut last = f64::INFINITY;
+    for k in 1..60 {
+        let kf = k as f64;
+        u *= (6.0 * kf - 5.0) * (6.0 * kf - 3.0) * (6.0 * kf - 1.0) / ((2.0 * kf - 1.0) * 216.0 * kf);
+        xk *= x;
+        let term = xk * u;
+        if term.norm() > last {
+            break;
+        }
+        last = term.norm();
+        s += term;
+        sd -= term * ((6.0 * kf + 1.0) / (6.0 * kf - 1.0));
+        if last < 1e-17 * s.norm() {
+            break;
+        }
+    }
+    let e = (-zeta).exp() / (2.0 * PI.sqrt());
+    let z4 = z.powf(0.25);
+    (e / z4 * s, -e * z4 * sd)
+}
+
+// (y, y') at z0 + h from the Taylor series of Airy's equation y'' = z y around z0
+fn airy_taylor_step(z0: C64, y: C64, dy: C64, h: C64) -> (C64, C64) {
+    // coefficients of y(z0 + s h) in powers of s
+    let h2 = h * h;
+    let h3 = h2 * h;
+    let (mut c0, mut c1) = (y, dy * h);
+    let mut cm1 = C64::new(0.0, 0.0);
+    let (mut s, mut sd) = (c0 + c1, c1);
+    for k in 0..120 {
+        let c2 = (z0 * c0 * h2 + cm1 * h3) / ((k + 2) as f64 * (k + 1) as f64);
+        s += c2;
+        sd += c2 * (k + 2) as f64;
+        (cm1, c0, c1) = (c0, c1, c2);
+        if k > 4 && c1.norm() + c0.norm() < 1e-18 * s.norm() {
+            break;
+        }
+    }
+    (s, sd / h)
+}
+
+// principal branch W_0 of the solution of w e^w = z, with its cut along (-inf, -1/e]
+pub fn lambert_w(z: C64) -> C64 {
+    if z == C64::new(0.0, 0.0) {
+        return z;
+    }
+    let e = std::f64::consts::E;
+    let mut w = if (z + 1.0 / e).norm() < 1.0 {
+        let p = (2.0 * (e * z + 1.0)).sqrt();
+        -1.0 + p - p * p / 3.0 + 11.0 / 72.0 * p * p * p
+    } else if z.norm() < 3.0 {
+        (1.0 + z).ln()
+    } else {
+        let l = z.ln();
+        l - l.ln()
+    };
+    for _ in 0..40 {
+        let ew = w.exp();
+        let f = w * ew - z;
+        let w1 = w + 1.0;
+        if w1 == C64::new(0.0, 0.0) {
+            break;
+        }
+        let d = f / (ew * w1 - (w + 2.0) * f / (2.0 * w1));
+        w -= d;
+        if d.norm() <= 1e-15 * (1.0 + w.norm()) {
+            break;
+        }
+    }
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex_catalog::c;
+
+    fn assert_close(a: C64, b: C64, tol: f64) {
+        assert!((a - b).norm() <= tol * b.norm().max(1e-300), "{} vs {}", a, b);
+    }
+
+    // reference values from DLMF and Abramowitz and Stegun where tabulated, otherwise from mpmath
+    #[test]
+    fn gamma_and_log_gamma_match_reference_values() {
+        assert_close(gamma(c(5.0, 0.0)), c(24.0, 0.0), 1e-14);
+        assert_close(gamma(c(0.5, 0.0)), c(PI.sqrt(), 0.0), 1e-14);
+        assert_close(gamma(c(0.0, 1.0)), c(-0.15494982830181067, -0.49801566811835607), 1e-13);
+        assert_close(gamma(c(-1.5, 0.0)), c(2.363271801207355, 0.0), 1e-13);
+        assert_close(gamma(c(2.5, 3.0)), c(-0.2181189710811229, 0.07203476340717503), 1e-13);
+        assert_close(gamma(c(-3.2, 0.4)), c(0.04356088470635085, 0.23686713340250093), 1e-12);
+
+        assert_close(log_gamma(c(100.0, 0.0)), c(359.1342053695754, 0.0), 1e-14);
+        assert_close(log_gamma(c(1.0, 10.0)), c(-13.63773218824727, 13.802912974229901), 1e-13);
+        // on either side of the cut
+        assert_close(log_gamma(c(-2.5, 0.1)), c(-0.10314924404281921, -9.314444268359837), 1e-13);
+        assert_close(log_gamma(c(-2.5, -0.1)), c(-0.10314924404281921, 9.314444268359837), 1e-13);
+        assert_close(log_gamma(c(0.3, 0.7)).exp(), gamma(c(0.3, 0.7)), 1e-13);
+        // far to the left, where shifting to the right half plane would take 10^6 steps
+        assert_close(log_gamma(c(-1e6 + 0.5, 0.5)), c(-12815511.252128155, -3141585.7458345143), 1e-13);
+        assert_close(log_gamma(c(-2.5, 1e-3)), c(-0.05624848611288207, -9.4236748041107), 1e-13);
+        assert!(log_gamma(c(-1e17, 1.0)).is_finite());
+        assert!(log_gamma(c(f64::NEG_INFINITY, 0.0)).is_nan());
+        assert!(log_gamma(c(f64::NAN, 0.0)).is_nan());
+        assert!(log_gamma(c(0.5, f64::NAN)).is_nan());
+    }
+
+    #[test]
+    fn zeta_and_eta_match_reference_values() {
+        assert_close(zeta(c(2.0, 0.0)), c(PI * PI / 6.0, 0.0), 1e-14);
+        assert_close(zeta(c(3.0, 0.0)), c(1.2020569031595942, 0.0), 1e-14);
+        assert_close(zeta(c(0.0, 0.0)), c(-0.5, 0.0), 1e-14);
+        assert_close(zeta(c(-1.0, 0.0)), c(-1.0 / 12.0, 0.0), 1e-13);
+        assert!(zeta(c(-2.0, 0.0)).norm() < 1e-15);
+        assert!(zeta(c(0.5, 14.134725141734694)).norm() < 1e-12);
+        assert_close(zeta(c(2.0, 3.0)), c(0.7980219851462758, -0.1137443080529385), 1e-13);
+        assert_close(zeta(c(-2.5, 10.0)), c(4.263590288889194, 1.4598166199175628), 1e-12);
+        assert_close(zeta(c(0.3, -25.0)), c(-0.28820166958746124, 0.12962518049689428), 1e-12);
+
+        assert_close(eta(c(1.0, 0.0)), c(2f64.ln(), 0.0), 1e-14);
+        assert_close(eta(c(0.0, 0.0)), c(0.5, 0.0), 1e-14);
+        assert_close(eta(c(2.0, 3.0)), c(1.0420105395745811, 0.20705749899589493), 1e-13);
+        assert_close(eta(c(-1.5, 2.0)), c(0.08265312939956655, 0.691513328068467), 1e-12);
+    }
+
+    #[test]
+    fn erf_and_erfc_match_reference_values() {
+        assert_close(erf(c(1.0, 0.0)), c(0.8427007929497149, 0.0), 1e-14);
+        assert_close(erf(c(0.0, 1.0)), c(0.0, 1.6504257587975428), 1e-14);
+        assert_close(erf(c(0.1, -0.2)), c(0.1170214863039043, -0.22638445718145092), 1e-14);
+        assert_close(erf(c(2.0, 1.0)), c(1.0036063427256519, -0.011259006028815025), 1e-14);
+        assert_close(erfc(c(4.0, 0.0)), c(1.541725790028002e-08, 0.0), 1e-13);
+        assert_close(erfc(c(5.0, 5.0)), c(0.06962039625690489, -0.03893619089512138), 1e-13);
+        assert_close(erfc(c(-1.5, 3.0)), c(119.8559040465755, 88.12089067150646), 1e-13);
+        // odd and conjugate symmetric
+        let z = c(0.7, 1.9);
+        assert_close(erf(-z), -erf(z), 1e-15);
+        assert_close(erf(z.conj()), erf(z).conj(), 1e-15);
+    }
+
+    #[test]
+    fn bessel_functions_match_reference_values() {
+        assert_close(bessel_j(0, c(1.0, 0.0)), c(0.7651976865579666, 0.0), 1e-14);
+        assert_close(bessel_j(1, c(2.5, 0.0)), c(0.4970941024642741, 0.0), 1e-14);
+        assert_close(bessel_j(5, c(10.0, 0.0)), c(-0.23406152818679363, 0.0), 1e-12);
+        assert_close(bessel_j(0, c(20.0, 0.0)), c(0.1670246643405832, 0.0), 1e-12);
+        assert_close(bessel_j(0, c(1.0, 1.0)), c(0.9376084768060293, -0.4965299476091221), 1e-14);
+        assert_close(bessel_j(3, c(25.0, -4.0)), c(2.6155455235732914, 3.3190537944137515), 1e-12);
+        assert_close(bessel_j(-3, c(0.0, 2.0)), c(0.0, 0.21273995923985264), 1e-14);
+
+        assert_close(bessel_y(0, c(1.0, 0.0)), c(0.08825696421567696, 0.0), 1e-13);
+        assert_close(bessel_y(1, c(30.0, 0.0)), c(0.08442557066174723, 0.0), 1e-12);
+        assert_close(bessel_y(2, c(-3.0, 0.5)), c(-0.13676446433431685, 0.8236347839408061), 1e-13);
+        // Wronskian J_n+1 Y_n - J_n Y_n+1 = 2/(pi z)
+        let z = c(3.7, 1.2);
+        let w = bessel_j(5, z) * bessel_y(4, z) - bessel_j(4, z) * bessel_y(5, z);
+        assert_close(w, 2.0 / (PI * z), 1e-12);
+    }
+
+    #[test]
+    fn bessel_order_is_clamped() {
+        // without the clamp, order 1e6 takes milliseconds per value and 1e9 practically never returns
+        let z = c(150.0, 1.0);
+        assert_eq!(bessel_j(i32::MAX, z), bessel_j(BESSEL_MAX_ORDER, z));
+        assert_eq!(bessel_y(-1_000_000_000, z), bessel_y(-BESSEL_MAX_ORDER, z));
+        assert_eq!(bessel_j(i32::MIN, z), bessel_j(-BESSEL_MAX_ORDER, z));
+    }
+
+    #[test]
+    fn airy_functions_match_reference_values() {
+        assert_close(airy_ai(c(0.0, 0.0)), c(AIRY_C1, 0.0), 1e-15);
+        assert_close(airy_ai(c(1.0, 0.0)), c(0.13529241631288141, 0.0), 1e-14);
+        assert_close(airy_ai(c(-2.0, 0.0)), c(0.22740742820168557, 0.0), 1e-14);
+        assert_close(airy_ai(c(1.0, 1.0)), c(0.060458308371838146, -0.15188956587718141), 1e-14);
+        assert_close(airy_ai(c(10.0, 0.0)), c(1.1047532552898686e-10, 0.0), 1e-13);
+        assert_close(airy_ai(c(-10.0, 0.0)), c(0.04024123848644319, 0.0), 1e-12);
+        assert_close(airy_ai(c(6.0, 0.5)), c(3.2627622868707076e-06, -9.660542535122289e-06), 1e-11);
+        assert_close(airy_bi(c(1.0, 0.0)), c(1.2074235949528713, 0.0), 1e-14);
+        assert_close(airy_bi(c(-5.0, 8.0)), c(-17851029.582090754, 34190805.0857689), 1e-12);
+    }
+
+    #[test]
+    fn lambert_w_matches_reference_values() {
+        assert_close(lambert_w(c(1.0, 0.0)), c(0.5671432904097838, 0.0), 1e-15);
+        assert_close(lambert_w(c(std::f64::consts::E, 0.0)), c(1.0, 0.0), 1e-15);
+        assert_close(lambert_w(c(-(-1f64).exp(), 0.0)), c(-1.0, 0.0), 1e-7);
+        assert_close(lambert_w(c(0.0, 1.0)), c(0.37469902073711747, 0.5764127230314353), 1e-14);
+        assert_close(lambert_w(c(-1.0, 0.0)), c(-0.31813150520476413, 1.3372357014306895), 1e-14);
+        assert_close(lambert_w(c(-0.5, -1e-9)), c(-0.7940236299210427, -0.7701117491586148), 1e-12);
+        assert_close(lambert_w(c(1e6, 1e6)), c(11.700540314897427, 0.7236308963832878), 1e-14);
+    }
+}
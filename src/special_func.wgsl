@@ -0,0 +1,427 @@
+// Special functions on vec2f, the f32 counterparts of special_func.rs. compose_shader appends this
+// file to complex_func.wgsl, so the functions are called through the same alias, e.g. cf::cGamma(z).
+// The series and asymptotic regions are narrowed to what f32 can hold: Bessel and Airy series stop at
+// |z| = 8 and 4, Gamma uses the 6-term Lanczos fit of Numerical Recipes, eta the Borwein series with
+// n = 32, and Ai near the positive real axis is taken from its asymptotic expansion from |z| = 4 on.
+// special_func_f32.rs mirrors this file on the CPU, where its tests find a relative error of about 1e-5
+// to 1e-4, except for Ai around |z| = 4 near the positive real axis with only about 3 digits. Bessel
+// orders are clamped to |n| <= 100, since the series and recurrences take time in proportion to n.
+
+const besselMaxOrder: f32 = 100.0;
+
+// principal square root, unlike cSqrt also on the negative real axis
+fn sfSqrt(z: vec2f) -> vec2f {
+    let m = length(z);
+    let s = sqrt(max(0.5*vec2(m + z.x, m - z.x), vec2(0.0)));
+    return vec2(s.x, select(-s.y, s.y, z.y >= 0.0));
+}
+
+// log Gamma(z) for Re z >= 1/2, up to a multiple of 2 pi i
+fn sfLogGammaLanczos(z: vec2f) -> vec2f {
+    var c = array<f32, 6>(76.18009172947146, -86.50532032941677, 24.01409824083091,
+        -1.231739572450155, 0.1208650973866179e-2, -0.5395239384953e-5);
+    var ser = vec2(1.000000000190015, 0.0);
+    for (var j = 0; j < 6; j = j + 1) {
+        ser = ser + c[j]*cInv(z + vec2(f32(j + 1), 0.0));
+    }
+    let t = z + vec2(5.5, 0.0);
+    return cMul(z + vec2(0.5, 0.0), cLog(t)) - t + cLog(2.5066282746310005*cDiv(ser, z));
+}
+
+fn cGamma(z: vec2f) -> vec2f {
+    if (z.x < 0.5) {
+        let g = cExp(sfLogGammaLanczos(vec2(1.0, 0.0) - z));
+        return cDiv(vec2(pi, 0.0), cMul(cSin(pi*z), g));
+    }
+    return cExp(sfLogGammaLanczos(z));
+}
+
+// log Gamma(z) for Re z >= 1/2 by Stirling's series, after shifting z to |z| >= 6 in at most 6 steps
+fn sfLogGammaStirling(z: vec2f) -> vec2f {
+    var w = z;
+    var shift = vec2(0.0, 0.0);
+    for (var k = 0; k < 6 && length(w) < 6.0; k = k + 1) {
+        shift = shift + cLog(w);
+        w = w + vec2(1.0, 0.0);
+    }
+    let w2 = cInv(cMul(w, w));
+    var series = vec2(-1.0/1680.0, 0.0);
+    series = cMul(series, w2) + vec2(1.0/1260.0, 0.0);
+    series = cMul(series, w2) + vec2(-1.0/360.0, 0.0);
+    series = cMul(series, w2) + vec2(1.0/12.0, 0.0);
+    return cMul(w - vec2(0.5, 0.0), cLog(w)) - w + vec2(0.9189385332046727, 0.0) + cDiv(series, w) - shift;
+}
+
+// principal branch; for Re z < 1/2 by reflection, with log sin(pi z) continued over the upper half
+// plane from sin(pi z) = i/2 exp(-i pi z) (1 - exp(2 pi i z)), and the lower half plane by symmetry
+fn cLogGamma(z: vec2f) -> vec2f {
+    if (z.x >= 0.5) {
+        return sfLogGammaStirling(z);
+    }
+    let lower = z.y < 0.0;
+    let u = select(z, cConj(z), lower);
+    let iu = vec2(-u.y, u.x);
+    let logSin = vec2(-0.6931471805599453, 0.5*pi) - pi*iu + cLog(vec2(1.0, 0.0) - cExp(2.0*pi*iu));
+    let r = vec2(log(pi), 0.0) - logSin - sfLogGammaStirling(vec2(1.0, 0.0) - u);
+    return select(r, cConj(r), lower);
+}
+
+fn sfPow2(s: vec2f) -> vec2f {
+    return cExp(s*log(2.0));
+}
+
+// Borwein's algorithm 2 with n = 32, for Re s >= 0
+fn sfEtaBorwein(s: vec2f) -> vec2f {
+    let n = 32.0;
+    var term = 1.0;
+    var dn = 1.0;
+    for (var i = 1.0; i <= n; i = i + 1.0) {
+        term = term*4.0*(n + i - 1.0)*(n - i + 1.0)/((2.0*i)*(2.0*i - 1.0));
+        dn = dn + term;
+    }
+    term = 1.0;
+    var dk = 1.0;
+    var sum = vec2(0.0, 0.0);
+    var sign = 1.0;
+    for (var k = 0.0; k < n; k = k + 1.0) {
+        sum = sum + sign*(dk - dn)*cExp(-s*log(k + 1.0));
+        term = term*4.0*(n + k)*(n - k)/((2.0*k + 2.0)*(2.0*k + 1.0));
+        dk = dk + term;
+        sign = -sign;
+    }
+    return -sum/dn;
+}
+
+fn cZeta(s: vec2f) -> vec2f {
+    if (s.x >= 0.0) {
+        return cDiv(sfEtaBorwein(s), vec2(1.0, 0.0) - sfPow2(vec2(1.0, 0.0) - s));
+    }
+    // zeta(s) = (2 pi)^s / pi sin(pi s/2) Gamma(1 - s) zeta(1 - s)
+    let a = vec2(1.0, 0.0) - s;
+    let scale = cExp(s*log(2.0*pi) - vec2(log(pi), 0.0) + cLogGamma(a));
+    let z1 = cDiv(sfEtaBorwein(a), vec2(1.0, 0.0) - sfPow2(s));
+    return cMul(cMul(scale, cSin(0.5*pi*s)), z1);
+}
+
+fn cEta(s: vec2f) -> vec2f {
+    if (s.x >= 0.0) {
+        return sfEtaBorwein(s);
+    }
+    return cMul(vec2(1.0, 0.0) - sfPow2(vec2(1.0, 0.0) - s), cZeta(s));
+}
+
+// Gautschi's algorithm for w(x + iy) with x, y >= 0
+fn sfFaddeevaQuadrant(x: f32, y: f32) -> vec2f {
+    var h = 0.0;
+    var capn = 0;
+    var nu = 8;
+    if (y < 4.29 && x < 5.33) {
+        let s = (1.0 - y/4.29)*sqrt(1.0 - x*x/28.41);
+        h = 1.6*s;
+        capn = 6 + i32(23.0*s);
+        nu = 9 + i32(21.0*s);
+    }
+    let h2 = 2.0*h;
+    var lambda = 0.0;
+    if (h > 0.0) {
+        lambda = pow(h2, f32(capn));
+    }
+    var r = vec2(0.0, 0.0);
+    var s = vec2(0.0, 0.0);
+    for (var n = nu; n >= 0; n = n - 1) {
+        let np1 = f32(n + 1);
+        let t = vec2(y + h + np1*r.x, x - np1*r.y);
+        r = 0.5*t/dot(t, t);
+        if (h > 0.0 && n <= capn) {
+            s = cMul(r, s + vec2(lambda, 0.0));
+            lambda = lambda/h2;
+        }
+    }
+    var w = select(s, r, h == 0.0 || lambda == 0.0)*1.1283791670955126;
+    if (y == 0.0) {
+        w.x = exp(-x*x);
+    }
+    return w;
+}
+
+// w(z) = exp(-z^2) erfc(-iz)
+fn cFaddeeva(z: vec2f) -> vec2f {
+    let q = select(z, -z, z.y < 0.0);
+    var w = sfFaddeevaQuadrant(abs(q.x), q.y);
+    if (q.x < 0.0) {
+        w = cConj(w);
+    }
+    if (z.y < 0.0) {
+        w = 2.0*cExp(-cMul(z, z)) - w;
+    }
+    return w;
+}
+
+fn cErfc(z: vec2f) -> vec2f {
+    let p = select(z, -z, z.x < 0.0);
+    let r = cMul(cExp(-cMul(p, p)), cFaddeeva(vec2(-p.y, p.x)));
+    return select(r, vec2(2.0, 0.0) - r, z.x < 0.0);
+}
+
+fn cErf(z: vec2f) -> vec2f {
+    if (length(z) < 0.5) {
+        let z2 = -cMul(z, z);
+        var term = z;
+        var sum = z;
+        for (var n = 1; n < 12; n = n + 1) {
+            term = cMul(term, z2)/f32(n);
+            sum = sum + term/f32(2*n + 1);
+        }
+        return 1.1283791670955126*sum;
+    }
+    if (z.x >= 0.0) {
+        return vec2(1.0, 0.0) - cErfc(z);
+    }
+    return cErfc(-z) - vec2(1.0, 0.0);
+}
+
+// (z/2)^n sum (-z^2/4)^k/(k! (n+k)!)
+fn sfBesselJSeries(n: u32, z: vec2f) -> vec2f {
+    let half = 0.5*z;
+    var term = vec2(1.0, 0.0);
+    for (var k = 1u; k <= n; k = k + 1u) {
+        term = cMul(term, half)/f32(k);
+    }
+    let q = -cMul(half, half);
+    var sum = term;
+    for (var k = 1u; k < 200u; k = k + 1u) {
+        term = cMul(term, q)/(f32(k)*f32(n + k));
+        sum = sum + term;
+        if (k > 2u && length(term) <= 1e-8*length(sum)) {
+            break;
+        }
+    }
+    return sum;
+}
+
+// Y_0 and Y_1 by DLMF 10.8.1
+fn sfBesselYSeries(n: u32, z: vec2f) -> vec2f {
+    let half = 0.5*z;
+    let q = cMul(half, half);
+    var psi1 = -0.5772156649015329;
+    var psi2 = psi1;
+    var finite = vec2(0.0, 0.0);
+    var term = vec2(1.0, 0.0);
+    if (n == 1u) {
+        finite = -cInv(half)/pi;
+        psi2 = psi2 + 1.0;
+        term = half;
+    }
+    var sum = (psi1 + psi2)*term;
+    for (var k = 1u; k < 200u; k = k + 1u) {
+        psi1 = psi1 + 1.0/f32(k);
+        psi2 = psi2 + 1.0/f32(n + k);
+        term = -cMul(term, q)/(f32(k)*f32(n + k));
+        let d = (psi1 + psi2)*term;
+        sum = sum + d;
+        if (k > 2u && length(d) <= 1e-8*length(sum)) {
+            break;
+        }
+    }
+    return finite + 2.0/pi*cMul(cLog(half), sfBesselJSeries(n, z)) - sum/pi;
+}
+
+// (J_n, Y_n) of order 0 or 1 from Hankel's expansion, as vec4(J, Y)
+fn sfBesselHankel(n: u32, z: vec2f) -> vec4f {
+    let mu = 4.0*f32(n*n);
+    var p = vec2(1.0, 0.0);
+    var q = vec2(0.0, 0.0);
+    var term = vec2(1.0, 0.0);
+    var last = 3.0e38;
+    for (var k = 1; k < 30; k = k + 1) {
+        term = cDiv(term*(mu - f32((2*k - 1)*(2*k - 1))), 8.0*f32(k)*z);
+        let size = length(term);
+        if (size > last || size < 1e-8) {
+            break;
+        }
+        last = size;
+        let sign = select(-1.0, 1.0, (k/2)%2 == 0);
+        if (k%2 == 1) {
+            q = q + sign*term;
+        } else {
+            p = p + sign*term;
+        }
+    }
+    let chi = z - vec2((0.5*f32(n) + 0.25)*pi, 0.0);
+    let a = sfSqrt(cInv(pi*z)*2.0);
+    let j = cMul(a, cMul(p, cCos(chi)) - cMul(q, cSin(chi)));
+    let y = cMul(a, cMul(p, cSin(chi)) + cMul(q, cCos(chi)));
+    return vec4(j, y);
+}
+
+fn sfBesselJ01(n: u32, z: vec2f) -> vec2f {
+    if (length(z) <= 8.0) {
+        return sfBesselJSeries(n, z);
+    }
+    return sfBesselHankel(n, z).xy;
+}
+
+fn sfBesselY01(n: u32, z: vec2f) -> vec2f {
+    if (length(z) <= 8.0) {
+        return sfBesselYSeries(n, z);
+    }
+    return sfBesselHankel(n, z).zw;
+}
+
+// J_n for Re z >= 0, by forward recurrence only where n < |z| and the series would cancel
+fn sfBesselJRight(m: u32, z: vec2f) -> vec2f {
+    if (m <= 1u) {
+        return sfBesselJ01(m, z);
+    }
+    if (abs(z.x) <= 6.0 || f32(m) >= length(z)) {
+        return sfBesselJSeries(m, z);
+    }
+    var j0 = sfBesselJ01(0u, z);
+    var j1 = sfBesselJ01(1u, z);
+    for (var k = 1u; k < m; k = k + 1u) {
+        let j2 = cDiv(2.0*f32(k)*j1, z) - j0;
+        j0 = j1;
+        j1 = j2;
+    }
+    return j1;
+}
+
+// Y_n for Re z >= 0
+fn sfBesselYRight(m: u32, z: vec2f) -> vec2f {
+    var y0 = sfBesselY01(0u, z);
+    var y1 = sfBesselY01(1u, z);
+    if (m == 0u) {
+        return y0;
+    }
+    for (var k = 1u; k < m; k = k + 1u) {
+        let y2 = cDiv(2.0*f32(k)*y1, z) - y0;
+        y0 = y1;
+        y1 = y2;
+    }
+    return y1;
+}
+
+// Bessel function of the first kind, of integer order round(n)
+fn cBesselJ(n: f32, z: vec2f) -> vec2f {
+    let m = u32(min(abs(round(n)), besselMaxOrder));
+    var sign = select(1.0, -1.0, n < 0.0 && m%2u == 1u);
+    var w = z;
+    if (z.x < 0.0) {
+        w = -z;
+        if (m%2u == 1u) {
+            sign = -sign;
+        }
+    }
+    return sign*sfBesselJRight(m, w);
+}
+
+// Bessel function of the second kind, of integer order round(n), with its cut along the negative
+// real axis
+fn cBesselY(n: f32, z: vec2f) -> vec2f {
+    let m = u32(min(abs(round(n)), besselMaxOrder));
+    let sign = select(1.0, -1.0, n < 0.0 && m%2u == 1u);
+    if (z.x >= 0.0) {
+        return sign*sfBesselYRight(m, z);
+    }
+    let w = -z;
+    let i2 = select(vec2(0.0, -2.0), vec2(0.0, 2.0), z.y >= 0.0);
+    let parity = select(1.0, -1.0, m%2u == 1u);
+    return sign*parity*(sfBesselYRight(m, w) + cMul(i2, sfBesselJRight(m, w)));
+}
+
+// Maclaurin series f and g of the Airy functions, as vec4(f, g)
+fn sfAirySeries(z: vec2f) -> vec4f {
+    let z3 = cMul(cMul(z, z), z);
+    var f = vec2(1.0, 0.0);
+    var g = z;
+    var tf = f;
+    var tg = g;
+    for (var k = 1.0; k < 100.0; k = k + 1.0) {
+        tf = cMul(tf, z3)/((3.0*k - 1.0)*(3.0*k));
+        tg = cMul(tg, z3)/((3.0*k)*(3.0*k + 1.0));
+        f = f + tf;
+        g = g + tg;
+        if (length(tf) + length(tg) <= 1e-8*(length(f) + length(g))) {
+            break;
+        }
+    }
+    return vec4(f, g);
+}
+
+// Ai for large |z| with |arg z| <= 2 pi/3
+fn sfAiryAsymptotic(z: vec2f) -> vec2f {
+    let zeta = 2.0/3.0*cMul(z, sfSqrt(z));
+    let x = -cInv(zeta);
+    var s = vec2(1.0, 0.0);
+    var u = 1.0;
+    var xk = vec2(1.0, 0.0);
+    var last = 3.0e38;
+    for (var k = 1.0; k < 30.0; k = k + 1.0) {
+        u = u*(6.0*k - 5.0)*(6.0*k - 3.0)*(6.0*k - 1.0)/((2.0*k - 1.0)*216.0*k);
+        xk = cMul(xk, x);
+        let term = u*xk;
+        if (length(term) > last || length(term) < 1e-8) {
+            break;
+        }
+        last = length(term);
+        s = s + term;
+    }
+    return cDiv(cMul(cExp(-zeta), s), 2.0*sqrt(pi)*cPow(z, 0.25));
+}
+
+fn cAiryAi(z: vec2f) -> vec2f {
+    if (length(z) <= 4.0) {
+        let fg = sfAirySeries(z);
+        return 0.3550280538878172*fg.xy - 0.2588194037928068*fg.zw;
+    }
+    if (abs(cArg(z)) <= 2.0*pi/3.0) {
+        return sfAiryAsymptotic(z);
+    }
+    // Ai(z) + w Ai(w z) + w^2 Ai(w^2 z) = 0 with w = exp(2 pi i/3)
+    let w = vec2(-0.5, 0.8660254037844386);
+    let w2 = cConj(w);
+    return -cMul(w, sfAiryAsymptotic(cMul(w, z))) - cMul(w2, sfAiryAsymptotic(cMul(w2, z)));
+}
+
+fn cAiryBi(z: vec2f) -> vec2f {
+    if (length(z) <= 4.0) {
+        let fg = sfAirySeries(z);
+        return 1.7320508075688772*(0.3550280538878172*fg.xy + 0.2588194037928068*fg.zw);
+    }
+    // DLMF 9.2.10
+    let w = vec2(-0.5, 0.8660254037844386);
+    let e6 = vec2(0.8660254037844386, 0.5);
+    return cMul(e6, cAiryAi(cMul(w, z))) + cMul(cConj(e6), cAiryAi(cMul(cConj(w), z)));
+}
+
+// principal branch of the Lambert W function
+fn cLambertW(z: vec2f) -> vec2f {
+    if (dot(z, z) == 0.0) {
+        return z;
+    }
+    var w = vec2(0.0, 0.0);
+    if (length(z + vec2(1.0/e, 0.0)) < 1.0) {
+        let p = sfSqrt(2.0*(e*z + vec2(1.0, 0.0)));
+        let p2 = cMul(p, p);
+        w = vec2(-1.0, 0.0) + p - p2/3.0 + 11.0/72.0*cMul(p2, p);
+    } else if (length(z) < 3.0) {
+        w = cLog(z + vec2(1.0, 0.0));
+    } else {
+        let l = cLog(z);
+        w = l - cLog(l);
+    }
+    for (var k = 0; k < 20; k = k + 1) {
+        let ew = cExp(w);
+        let f = cMul(w, ew) - z;
+        let w1 = w + vec2(1.0, 0.0);
+        if (dot(w1, w1) == 0.0) {
+            break;
+        }
+        let d = cDiv(f, cMul(ew, w1) - cDiv(cMul(w + vec2(2.0, 0.0), f), 2.0*w1));
+        w = w - d;
+        if (length(d) <= 1e-7*(1.0 + length(w))) {
+            break;
+        }
+    }
+    return w;
+}
@@ -0,0 +1,562 @@
+#![allow(dead_code)]
+// same literals as special_func.wgsl
+#![allow(clippy::approx_constant, clippy::excessive_precision)]
+use cgmath::{vec2, InnerSpace};
+use super::complex_func::{c_arg, c_conj, c_cos, c_div, c_exp, c_inv, c_log, c_mul, c_pow, c_sin, Vec2, E, PI};
+
+// f32 mirror of special_func.wgsl, operation by operation as complex_func.rs mirrors
+// complex_func.wgsl, so that the shader algorithms (which differ from the f64 ones in special_func.rs)
+// can be tested against reference values on the CPU. The Bessel orders are rounded half to even and
+// clamped to BESSEL_MAX_ORDER, as the WGSL round() and min() do.
+
+pub const BESSEL_MAX_ORDER: f32 = 100.0;
+
+// principal square root, unlike c_sqrt also on the negative real axis
+fn sf_sqrt(z: Vec2) -> Vec2 {
+    let m = z.magnitude();
+    let s = vec2((0.5*(m + z.x)).max(0.0).sqrt(), (0.5*(m - z.x)).max(0.0).sqrt());
+    vec2(s.x, if z.y >= 0.0 { s.y } else { -s.y })
+}
+
+const LANCZOS: [f32; 6] = [76.18009172947146, -86.50532032941677, 24.01409824083091,
+    -1.231739572450155, 0.1208650973866179e-2, -0.5395239384953e-5];
+
+fn log_gamma_lanczos(z: Vec2) -> Vec2 {
+    let mut ser = vec2(1.000000000190015, 0.0);
+    for (j, c) in LANCZOS.iter().enumerate() {
+        ser += *c*c_inv(z + vec2((j + 1) as f32, 0.0));
+    }
+    let t = z + vec2(5.5, 0.0);
+    c_mul(z + vec2(0.5, 0.0), c_log(t)) - t + c_log(2.5066282746310005*c_div(ser, z))
+}
+
+pub fn c_gamma(z: Vec2) -> Vec2 {
+    if z.x < 0.5 {
+        let g = c_exp(log_gamma_lanczos(vec2(1.0, 0.0) - z));
+        return c_div(vec2(PI, 0.0), c_mul(c_sin(PI*z), g));
+    }
+    c_exp(log_gamma_lanczos(z))
+}
+
+fn log_gamma_stirling(z: Vec2) -> Vec2 {
+    let mut w = z;
+    let mut shift = vec2(0.0, 0.0);
+    for _ in 0..6 {
+        if w.magnitude() >= 6.0 {
+            break;
+        }
+        shift += c_log(w);
+        w += vec2(1.0, 0.0);
+    }
+    let w2 = c_inv(c_mul(w, w));
+    let mut series = vec2(-1.0/1680.0, 0.0);
+    series = c_mul(series, w2) + vec2(1.0/1260.0, 0.0);
+    series = c_mul(series, w2) + vec2(-1.0/360.0, 0.0);
+    series = c_mul(series, w2) + vec2(1.0/12.0, 0.0);
+    c_mul(w - vec2(0.5, 0.0), c_log(w)) - w + vec2(0.9189385332046727, 0.0) + c_div(series, w) - shift
+}
+
+pub fn c_log_gamma(z: Vec2) -> Vec2 {
+    if z.x >= 0.5 {
+        return log_gamma_stirling(z);
+    }
+    let lower = z.y < 0.0;
+    let u = if lower { c_conj(z) } else { z };
+    let iu = vec2(-u.y, u.x);
+    let log_sin = vec2(-0.6931471805599453, 0.5*PI) - PI*iu + c_log(vec2(1.0, 0.0) - c_exp(2.0*PI*iu));
+    let r = vec2(PI.ln(), 0.0) - log_sin - log_gamma_stirling(vec2(1.0, 0.0) - u);
+    if lower { c_conj(r) } else { r }
+}
+
+fn pow2(s: Vec2) -> Vec2 {
+    c_exp(s*2f32.ln())
+}
+
+fn eta_borwein(s: Vec2) -> Vec2 {
+    let n = 32.0;
+    let mut term = 1.0;
+    let mut dn = 1.0;
+    for i in 1..=32 {
+        let i = i as f32;
+        term = term*4.0*(n + i - 1.0)*(n - i + 1.0)/((2.0*i)*(2.0*i - 1.0));
+        dn += term;
+    }
+    term = 1.0;
+    let mut dk = 1.0;
+    let mut sum = vec2(0.0, 0.0);
+    let mut sign = 1.0;
+    for k in 0..32 {
+        let k = k as f32;
+        sum += sign*(dk - dn)*c_exp(-s*(k + 1.0).ln());
+        term = term*4.0*(n + k)*(n - k)/((2.0*k + 2.0)*(2.0*k + 1.0));
+        dk += term;
+        sign = -sign;
+    }
+    -sum/dn
+}
+
+pub fn c_zeta(s: Vec2) -> Vec2 {
+    let one = vec2(1.0, 0.0);
+    if s.x >= 0.0 {
+        return c_div(eta_borwein(s), one - pow2(one - s));
+    }
+    let a = one - s;
+    let scale = c_exp(s*(2.0*PI).ln() - vec2(PI.ln(), 0.0) + c_log_gamma(a));
+    let z1 = c_div(eta_borwein(a), one - pow2(s));
+    c_mul(c_mul(scale, c_sin(0.5*PI*s)), z1)
+}
+
+pub fn c_eta(s: Vec2) -> Vec2 {
+    if s.x >= 0.0 {
+        return eta_borwein(s);
+    }
+    c_mul(vec2(1.0, 0.0) - pow2(vec2(1.0, 0.0) - s), c_zeta(s))
+}
+
+fn faddeeva_quadrant(x: f32, y: f32) -> Vec2 {
+    let (mut h, mut capn, mut nu) = (0.0f32, 0, 8);
+    if y < 4.29 && x < 5.33 {
+        let s = (1.0 - y/4.29)*(1.0 - x*x/28.41).sqrt();
+        h = 1.6*s;
+        capn = 6 + (23.0*s) as i32;
+        nu = 9 + (21.0*s) as i32;
+    }
+    let h2 = 2.0*h;
+    let mut lambda = if h > 0.0 { h2.powf(capn as f32) } else { 0.0 };
+    let mut r = vec2(0.0, 0.0);
+    let mut s = vec2(0.0, 0.0);
+    for n in (0..=nu).rev() {
+        let np1 = (n + 1) as f32;
+        let t = vec2(y + h + np1*r.x, x - np1*r.y);
+        r = 0.5*t/t.dot(t);
+        if h > 0.0 && n <= capn {
+            s = c_mul(r, s + vec2(lambda, 0.0));
+            lambda /= h2;
+        }
+    }
+    let mut w = if h == 0.0 || lambda == 0.0 { r } else { s }*1.1283791670955126;
+    if y == 0.0 {
+        w.x = (-x*x).exp();
+    }
+    w
+}
+
+pub fn c_faddeeva(z: Vec2) -> Vec2 {
+    let q = if z.y < 0.0 { -z } else { z };
+    let mut w = faddeeva_quadrant(q.x.abs(), q.y);
+    if q.x < 0.0 {
+        w = c_conj(w);
+    }
+    if z.y < 0.0 {
+        w = 2.0*c_exp(-c_mul(z, z)) - w;
+    }
+    w
+}
+
+pub fn c_erfc(z: Vec2) -> Vec2 {
+    let p = if z.x < 0.0 { -z } else { z };
+    let r = c_mul(c_exp(-c_mul(p, p)), c_faddeeva(vec2(-p.y, p.x)));
+    if z.x < 0.0 { vec2(2.0, 0.0) - r } else { r }
+}
+
+pub fn c_erf(z: Vec2) -> Vec2 {
+    if z.magnitude() < 0.5 {
+        let z2 = -c_mul(z, z);
+        let mut term = z;
+        let mut sum = z;
+        for n in 1..12 {
+            term = c_mul(term, z2)/n as f32;
+            sum += term/(2*n + 1) as f32;
+        }
+        return 1.1283791670955126*sum;
+    }
+    if z.x >= 0.0 {
+        return vec2(1.0, 0.0) - c_erfc(z);
+    }
+    c_erfc(-z) - vec2(1.0, 0.0)
+}
+
+fn bessel_j_series(n: u32, z: Vec2) -> Vec2 {
+    let half = 0.5*z;
+    let mut term = vec2(1.0, 0.0);
+    for k in 1..=n {
+        term = c_mul(term, half)/k as f32;
+    }
+    let q = -c_mul(half, half);
+    let mut sum = term;
+    for k in 1..200 {
+        term = c_mul(term, q)/(k as f32*(n + k) as f32);
+        sum += term;
+        if k > 2 && term.magnitude() <= 1e-8*sum.magnitude() {
+            break;
+        }
+    }
+    sum
+}
+
+fn bessel_y_series(n: u32, z: Vec2) -> Vec2 {
+    let half = 0.5*z;
+    let q = c_mul(half, half);
+    let mut psi1 = -0.5772156649015329;
+    let mut psi2 = psi1;
+    let mut finite = vec2(0.0, 0.0);
+    let mut term = vec2(1.0, 0.0);
+    if n == 1 {
+        finite = -c_inv(half)/PI;
+        psi2 += 1.0;
+        term = half;
+    }
+    let mut sum = (psi1 + psi2)*term;
+    for k in 1..200 {
+        psi1 += 1.0/k as f32;
+        psi2 += 1.0/(n + k) as f32;
+        term = -c_mul(term, q)/(k as f32*(n + k) as f32);
+        let d = (psi1 + psi2)*term;
+        sum += d;
+        if k > 2 && d.magnitude() <= 1e-8*sum.magnitude() {
+            break;
+        }
+    }
+    finite + 2.0/PI*c_mul(c_log(half), bessel_j_series(n, z)) - sum/PI
+}
+
+// (J_n, Y_n) of order 0 or 1 from Hankel's expansion
+fn bessel_hankel(n: u32, z: Vec2) -> (Vec2, Vec2) {
+    let mu = 4.0*(n*n) as f32;
+    let mut p = vec2(1.0, 0.0);
+    let mut q = vec2(0.0, 0.0);
+    let mut term = vec2(1.0, 0.0);
+    let mut last = 3.0e38;
+    for k in 1..30 {
+        term = c_div(term*(mu - ((2*k - 1)*(2*k - 1)) as f32), 8.0*k as f32*z);
+        let size = term.magnitude();
+        if size > last || size < 1e-8 {
+            break;
+        }
+        last = size;
+        let sign = if (k/2)%2 == 0 { 1.0 } else { -1.0 };
+        if k%2 == 1 {
+            q += sign*term;
+        } else {
+            p += sign*term;
+        }
+    }
+    let chi = z - vec2((0.5*n as f32 + 0.25)*PI, 0.0);
+    let a = sf_sqrt(c_inv(PI*z)*2.0);
+    let j = c_mul(a, c_mul(p, c_cos(chi)) - c_mul(q, c_sin(chi)));
+    let y = c_mul(a, c_mul(p, c_sin(chi)) + c_mul(q, c_cos(chi)));
+    (j, y)
+}
+
+fn bessel_j01(n: u32, z: Vec2) -> Vec2 {
+    if z.magnitude() <= 8.0 { bessel_j_series(n, z) } else { bessel_hankel(n, z).0 }
+}
+
+fn bessel_y01(n: u32, z: Vec2) -> Vec2 {
+    if z.magnitude() <= 8.0 { bessel_y_series(n, z) } else { bessel_hankel(n, z).1 }
+}
+
+fn bessel_j_right(m: u32, z: Vec2) -> Vec2 {
+    if m <= 1 {
+        return bessel_j01(m, z);
+    }
+    if z.x.abs() <= 6.0 || m as f32 >= z.magnitude() {
+        return bessel_j_series(m, z);
+    }
+    let mut j0 = bessel_j01(0, z);
+    let mut j1 = bessel_j01(1, z);
+    for k in 1..m {
+        let j2 = c_div(2.0*k as f32*j1, z) - j0;
+        j0 = j1;
+        j1 = j2;
+    }
+    j1
+}
+
+fn bessel_y_right(m: u32, z: Vec2) -> Vec2 {
+    let mut y0 = bessel_y01(0, z);
+    let mut y1 = bessel_y01(1, z);
+    if m == 0 {
+        return y0;
+    }
+    for k in 1..m {
+        let y2 = c_div(2.0*k as f32*y1, z) - y0;
+        y0 = y1;
+        y1 = y2;
+    }
+    y1
+}
+
+fn bessel_order(n: f32) -> u32 {
+    n.round_ties_even().abs().min(BESSEL_MAX_ORDER) as u32
+}
+
+pub fn c_bessel_j(n: f32, z: Vec2) -> Vec2 {
+    let m = bessel_order(n);
+    let mut sign = if n < 0.0 && m%2 == 1 { -1.0 } else { 1.0 };
+    let mut w = z;
+    if z.x < 0.0 {
+        w = -z;
+        if m%2 == 1 {
+            sign = -sign;
+        }
+    }
+    sign*bessel_j_right(m, w)
+}
+
+pub fn c_bessel_y(n: f32, z: Vec2) -> Vec2 {
+    let m = bessel_order(n);
+    let sign = if n < 0.0 && m%2 == 1 { -1.0 } else { 1.0 };
+    if z.x >= 0.0 {
+        return sign*bessel_y_right(m, z);
+    }
+    let w = -z;
+    let i2 = if z.y >= 0.0 { vec2(0.0, 2.0) } else { vec2(0.0, -2.0) };
+    let parity = if m%2 == 1 { -1.0 } else { 1.0 };
+    sign*parity*(bessel_y_right(m, w) + c_mul(i2, bessel_j_right(m, w)))
+}
+
+fn airy_series(z: Vec2) -> (Vec2, Vec2) {
+    let z3 = c_mul(c_mul(z, z), z);
+    let mut f = vec2(1.0, 0.0);
+    let mut g = z;
+    let mut tf = f;
+    let mut tg = g;
+    for k in 1..100 {
+        let k = k as f32;
+        tf = c_mul(tf, z3)/((3.0*k - 1.0)*(3.0*k));
+        tg = c_mul(tg, z3)/((3.0*k)*(3.0*k + 1.0));
+        f += tf;
+        g += tg;
+        if tf.magnitude() + tg.magnitude() <= 1e-8*(f.magnitude() + g.magnitude()) {
+            break;
+        }
+    }
+    (f, g)
+}
+
+fn airy_asymptotic(z: Vec2) -> Vec2 {
+    let zeta = 2.0/3.0*c_mul(z, sf_sqrt(z));
+    let x = -c_inv(zeta);
+    let mut s = vec2(1.0, 0.0);
+    let mut u = 1.0;
+    let mut xk = vec2(1.0, 0.0);
+    let mut last = 3.0e38;
+    for k in 1..30 {
+        let k = k as f32;
+        u = u*(6.0*k - 5.0)*(6.0*k - 3.0)*(6.0*k - 1.0)/((2.0*k - 1.0)*216.0*k);
+        xk = c_mul(xk, x);
+        let term = u*xk;
+        if term.magnitude() > last || term.magnitude() < 1e-8 {
+            break;
+        }
+        last = term.magnitude();
+        s += term;
+    }
+    c_div(c_mul(c_exp(-zeta), s), 2.0*PI.sqrt()*c_pow(z, 0.25))
+}
+
+pub fn c_airy_ai(z: Vec2) -> Vec2 {
+    if z.magnitude() <= 4.0 {
+        let (f, g) = airy_series(z);
+        return 0.3550280538878172*f - 0.2588194037928068*g;
+    }
+    if c_arg(z).abs() <= 2.0*PI/3.0 {
+        return airy_asymptotic(z);
+    }
+    let w = vec2(-0.5, 0.8660254037844386);
+    let w2 = c_conj(w);
+    -c_mul(w, airy_asymptotic(c_mul(w, z))) - c_mul(w2, airy_asymptotic(c_mul(w2, z)))
+}
+
+pub fn c_airy_bi(z: Vec2) -> Vec2 {
+    if z.magnitude() <= 4.0 {
+        let (f, g) = airy_series(z);
+        return 1.7320508075688772*(0.3550280538878172*f + 0.2588194037928068*g);
+    }
+    let w = vec2(-0.5, 0.8660254037844386);
+    let e6 = vec2(0.8660254037844386, 0.5);
+    c_mul(e6, c_airy_ai(c_mul(w, z))) + c_mul(c_conj(e6), c_airy_ai(c_mul(c_conj(w), z)))
+}
+
+pub fn c_lambert_w(z: Vec2) -> Vec2 {
+    if z.dot(z) == 0.0 {
+        return z;
+    }
+    let one = vec2(1.0, 0.0);
+    let mut w = if (z + vec2(1.0/E, 0.0)).magnitude() < 1.0 {
+        let p = sf_sqrt(2.0*(E*z + one));
+        let p2 = c_mul(p, p);
+        vec2(-1.0, 0.0) + p - p2/3.0 + 11.0/72.0*c_mul(p2, p)
+    } else if z.magnitude() < 3.0 {
+        c_log(z + one)
+    } else {
+        let l = c_log(z);
+        l - c_log(l)
+    };
+    for _ in 0..20 {
+        let ew = c_exp(w);
+        let f = c_mul(w, ew) - z;
+        let w1 = w + one;
+        if w1.dot(w1) == 0.0 {
+            break;
+        }
+        let d = c_div(f, c_mul(ew, w1) - c_div(c_mul(w + vec2(2.0, 0.0), f), 2.0*w1));
+        w -= d;
+        if d.magnitude() <= 1e-7*(1.0 + w.magnitude()) {
+            break;
+        }
+    }
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec2, b: [f64; 2], tol: f64) {
+        let d = ((a.x as f64 - b[0]).powi(2) + (a.y as f64 - b[1]).powi(2)).sqrt();
+        let scale = (b[0] * b[0] + b[1] * b[1]).sqrt().max(1e-30);
+        assert!(d <= tol * scale, "{:?} vs {:?}, relative error {:.1e}", a, b, d / scale);
+    }
+
+    fn c(re: f32, im: f32) -> Vec2 {
+        vec2(re, im)
+    }
+
+    // the reference values of special_func.rs, with the tolerances of f32 and of the shorter
+    // expansions of special_func.wgsl
+    #[test]
+    fn gamma_and_log_gamma_match_reference_values() {
+        assert_close(c_gamma(c(5.0, 0.0)), [24.0, 0.0], 1e-5);
+        assert_close(c_gamma(c(0.5, 0.0)), [std::f64::consts::PI.sqrt(), 0.0], 1e-5);
+        assert_close(c_gamma(c(0.0, 1.0)), [-0.15494982830181067, -0.49801566811835607], 1e-5);
+        assert_close(c_gamma(c(-1.5, 0.0)), [2.363271801207355, 0.0], 1e-5);
+        assert_close(c_gamma(c(2.5, 3.0)), [-0.2181189710811229, 0.07203476340717503], 1e-5);
+        assert_close(c_gamma(c(-3.2, 0.4)), [0.04356088470635085, 0.23686713340250093], 1e-5);
+
+        assert_close(c_log_gamma(c(100.0, 0.0)), [359.1342053695754, 0.0], 1e-6);
+        assert_close(c_log_gamma(c(1.0, 10.0)), [-13.63773218824727, 13.802912974229901], 1e-5);
+        assert_close(c_log_gamma(c(-2.5, 0.1)), [-0.10314924404281921, -9.314444268359837], 1e-5);
+        assert_close(c_log_gamma(c(-2.5, -0.1)), [-0.10314924404281921, 9.314444268359837], 1e-5);
+        assert_close(c_log_gamma(c(-1e6 + 0.5, 0.5)), [-12815511.252128155, -3141585.7458345143], 1e-5);
+        assert!(c_log_gamma(c(f32::NEG_INFINITY, 0.0)).x.is_nan());
+        assert!(c_log_gamma(c(f32::NAN, 0.0)).x.is_nan());
+    }
+
+    #[test]
+    fn zeta_and_eta_match_reference_values() {
+        let pi = std::f64::consts::PI;
+        assert_close(c_zeta(c(2.0, 0.0)), [pi * pi / 6.0, 0.0], 1e-5);
+        assert_close(c_zeta(c(3.0, 0.0)), [1.2020569031595942, 0.0], 1e-5);
+        assert_close(c_zeta(c(0.0, 0.0)), [-0.5, 0.0], 1e-5);
+        assert_close(c_zeta(c(-1.0, 0.0)), [-1.0 / 12.0, 0.0], 1e-5);
+        assert!(c_zeta(c(-2.0, 0.0)).magnitude() < 1e-6);
+        assert!(c_zeta(c(0.5, 14.134725)).magnitude() < 1e-4);
+        assert_close(c_zeta(c(2.0, 3.0)), [0.7980219851462758, -0.1137443080529385], 1e-5);
+        assert_close(c_zeta(c(-2.5, 10.0)), [4.263590288889194, 1.4598166199175628], 1e-4);
+        assert_close(c_zeta(c(0.3, -25.0)), [-0.28820166958746124, 0.12962518049689428], 1e-4);
+
+        assert_close(c_eta(c(1.0, 0.0)), [2f64.ln(), 0.0], 1e-5);
+        assert_close(c_eta(c(0.0, 0.0)), [0.5, 0.0], 1e-5);
+        assert_close(c_eta(c(2.0, 3.0)), [1.0420105395745811, 0.20705749899589493], 1e-5);
+        assert_close(c_eta(c(-1.5, 2.0)), [0.08265312939956655, 0.691513328068467], 1e-5);
+    }
+
+    #[test]
+    fn erf_and_erfc_match_reference_values() {
+        assert_close(c_erf(c(1.0, 0.0)), [0.8427007929497149, 0.0], 1e-5);
+        assert_close(c_erf(c(0.0, 1.0)), [0.0, 1.6504257587975428], 1e-5);
+        assert_close(c_erf(c(0.1, -0.2)), [0.1170214863039043, -0.22638445718145092], 1e-5);
+        assert_close(c_erf(c(2.0, 1.0)), [1.0036063427256519, -0.011259006028815025], 1e-5);
+        assert_close(c_erfc(c(4.0, 0.0)), [1.541725790028002e-08, 0.0], 1e-5);
+        assert_close(c_erfc(c(5.0, 5.0)), [0.06962039625690489, -0.03893619089512138], 1e-5);
+        assert_close(c_erfc(c(-1.5, 3.0)), [119.8559040465755, 88.12089067150646], 1e-5);
+    }
+
+    #[test]
+    fn bessel_functions_match_reference_values() {
+        assert_close(c_bessel_j(0.0, c(1.0, 0.0)), [0.7651976865579666, 0.0], 1e-5);
+        assert_close(c_bessel_j(1.0, c(2.5, 0.0)), [0.4970941024642741, 0.0], 1e-5);
+        assert_close(c_bessel_j(5.0, c(10.0, 0.0)), [-0.23406152818679363, 0.0], 1e-5);
+        assert_close(c_bessel_j(0.0, c(20.0, 0.0)), [0.1670246643405832, 0.0], 1e-5);
+        assert_close(c_bessel_j(0.0, c(1.0, 1.0)), [0.9376084768060293, -0.4965299476091221], 1e-5);
+        assert_close(c_bessel_j(3.0, c(25.0, -4.0)), [2.6155455235732914, 3.3190537944137515], 1e-5);
+        assert_close(c_bessel_j(-3.0, c(0.0, 2.0)), [0.0, 0.21273995923985264], 1e-5);
+
+        assert_close(c_bessel_y(0.0, c(1.0, 0.0)), [0.08825696421567696, 0.0], 1e-5);
+        assert_close(c_bessel_y(1.0, c(30.0, 0.0)), [0.08442557066174723, 0.0], 1e-5);
+        assert_close(c_bessel_y(2.0, c(-3.0, 0.5)), [-0.13676446433431685, 0.8236347839408061], 1e-5);
+    }
+
+    #[test]
+    fn airy_functions_match_reference_values() {
+        assert_close(c_airy_ai(c(0.0, 0.0)), [0.355028053887817239, 0.0], 1e-6);
+        assert_close(c_airy_ai(c(1.0, 0.0)), [0.13529241631288141, 0.0], 1e-5);
+        assert_close(c_airy_ai(c(-2.0, 0.0)), [0.22740742820168557, 0.0], 1e-5);
+        assert_close(c_airy_ai(c(1.0, 1.0)), [0.060458308371838146, -0.15188956587718141], 1e-5);
+        assert_close(c_airy_ai(c(10.0, 0.0)), [1.1047532552898686e-10, 0.0], 1e-5);
+        assert_close(c_airy_ai(c(-10.0, 0.0)), [0.04024123848644319, 0.0], 1e-5);
+        assert_close(c_airy_ai(c(6.0, 0.5)), [3.2627622868707076e-06, -9.660542535122289e-06], 1e-5);
+        assert_close(c_airy_bi(c(1.0, 0.0)), [1.2074235949528713, 0.0], 1e-5);
+        assert_close(c_airy_bi(c(-5.0, 8.0)), [-17851029.582090754, 34190805.0857689], 1e-5);
+    }
+
+    #[test]
+    fn lambert_w_matches_reference_values() {
+        assert_close(c_lambert_w(c(1.0, 0.0)), [0.5671432904097838, 0.0], 1e-5);
+        assert_close(c_lambert_w(c(E, 0.0)), [1.0, 0.0], 1e-5);
+        assert_close(c_lambert_w(c(0.0, 1.0)), [0.37469902073711747, 0.5764127230314353], 1e-5);
+        assert_close(c_lambert_w(c(-1.0, 0.0)), [-0.31813150520476413, 1.3372357014306895], 1e-5);
+        assert_close(c_lambert_w(c(-0.5, -1e-9)), [-0.7940236299210427, -0.7701117491586148], 1e-5);
+        assert_close(c_lambert_w(c(1e6, 1e6)), [11.700540314897427, 0.7236308963832878], 1e-5);
+    }
+
+    // worst relative error against the f64 functions over a grid around the origin; Ai keeps only
+    // about 3 digits near |z| = 4 on the positive real axis, where its series cancels
+    #[test]
+    fn sweep_against_f64() {
+        use crate::special_func as sf;
+        use num_complex::Complex;
+        type F32 = fn(Vec2) -> Vec2;
+        type F64 = fn(Complex<f64>) -> Complex<f64>;
+        let cases: [(&str, F32, F64, f32, f64); 12] = [
+            ("gamma", c_gamma, sf::gamma, 6.0, 1e-4),
+            ("log_gamma", c_log_gamma, sf::log_gamma, 10.0, 2e-4),
+            ("zeta", c_zeta, sf::zeta, 10.0, 1e-4),
+            ("eta", c_eta, sf::eta, 10.0, 1e-4),
+            ("erf", c_erf, sf::erf, 4.0, 1e-5),
+            ("erfc", c_erfc, sf::erfc, 4.0, 1e-5),
+            ("bessel_j0", |z| c_bessel_j(0.0, z), |z| sf::bessel_j(0, z), 20.0, 1e-4),
+            ("bessel_j3", |z| c_bessel_j(3.0, z), |z| sf::bessel_j(3, z), 20.0, 1e-4),
+            ("bessel_y1", |z| c_bessel_y(1.0, z), |z| sf::bessel_y(1, z), 20.0, 1e-4),
+            ("airy_ai", c_airy_ai, sf::airy_ai, 10.0, 3e-3),
+            ("airy_bi", c_airy_bi, sf::airy_bi, 10.0, 1e-4),
+            ("lambert_w", c_lambert_w, sf::lambert_w, 10.0, 1e-6),
+        ];
+        let n = 40;
+        for (name, f32_fn, f64_fn, r, tol) in cases {
+            for i in 0..=n {
+                for j in 0..=n {
+                    // off the axes, where some of the functions have cuts
+                    let z = c(r*(2.0*(i as f32 + 0.013)/n as f32 - 1.0), r*(2.0*(j as f32 + 0.027)/n as f32 - 1.0));
+                    let expected = f64_fn(Complex::new(z.x as f64, z.y as f64));
+                    if expected.norm() < 1e30 {
+                        let a = f32_fn(z);
+                        let d = (Complex::new(a.x as f64, a.y as f64) - expected).norm();
+                        assert!(d <= tol * expected.norm(), "{}({:?}) = {:?}, expected {}", name, z, a, expected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bessel_order_is_clamped() {
+        let z = c(150.0, 1.0);
+        assert_eq!(c_bessel_j(1e9, z), c_bessel_j(BESSEL_MAX_ORDER, z));
+        assert_eq!(c_bessel_y(-1e9, z), c_bessel_y(-BESSEL_MAX_ORDER, z));
+        // WGSL round() takes halves to even
+        assert_eq!(c_bessel_j(2.5, z), c_bessel_j(2.0, z));
+    }
+}